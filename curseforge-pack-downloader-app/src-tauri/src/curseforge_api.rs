@@ -1,52 +1,62 @@
+use curseforge_pack_downloader::cancellation::{is_cancelled, CancellationToken};
+use curseforge_pack_downloader::curseforge_api::search_projects;
+use curseforge_pack_downloader::curseforge_client::CurseforgeClient;
+use curseforge_pack_downloader::download_cache::DownloadCache;
+use curseforge_pack_downloader::install_plan::PlannedMod;
 use curseforge_pack_downloader::modpack_version_file::ModpackVersionFile;
+use curseforge_pack_downloader::optional_mods::OptionalModsPolicy;
+use curseforge_pack_downloader::output_sink::OutputPolicy;
+use curseforge_pack_downloader::pack_identifier::PackIdentifier;
+use curseforge_pack_downloader::process_result::ProcessResult;
+use curseforge_pack_downloader::search::SearchQuery;
 use curseforge_pack_downloader::ProcessProgressResponse;
-use log::{error, info};
-use reqwest::header::HeaderMap;
-use reqwest::Client;
-use std::convert::From;
+use log::info;
+use serde::Deserialize;
 use std::path::PathBuf;
+use std::sync::Mutex;
 use tauri::ipc::Channel;
+use tauri::State;
 
-/// Macro to parse the CURSEFORGE_API_KEY from environment variables.
-///
-/// This macro attempts to retrieve the API key from the environment, parse it,
-/// and handle any potential errors during parsing. If successful, it returns
-/// the parsed API key.
-macro_rules! header_parsed_api_key {
-    () => {
-        match std::env::var("CURSEFORGE_API_KEY")
-            .unwrap_or("".to_string())
-            .parse()
-        {
-            Ok(key) => key, // Return the parsed API key if successful
-            Err(err) => {
-                // Log an error message if parsing fails
-                error!("Failed to parse API key: {}", err);
-                return Err("Failed to parse API key".into());
+/// Holds the cancellation token of the running unpack, so the UI can cancel it.
+#[derive(Default)]
+pub struct UnpackState {
+    cancellation_token: Mutex<Option<CancellationToken>>,
+}
+
+impl UnpackState {
+    /// Creates the token for a new unpack, cancelling an unpack that is still running.
+    fn begin(&self) -> CancellationToken {
+        let token = CancellationToken::new();
+        if let Ok(mut current) = self.cancellation_token.lock() {
+            if let Some(previous) = current.replace(token.clone()) {
+                previous.cancel();
             }
         }
-    };
+        token
+    }
+}
+
+/// The choices of the user for an unpack, missing fields use the defaults of the library.
+#[derive(Deserialize, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub struct UnpackOptions {
+    optional_mods: OptionalModsPolicy,
+    /// What happens to an existing install, it is never touched by default.
+    output_policy: OutputPolicy,
 }
 
 #[tauri::command]
-pub async fn search_modpacks(query: String) -> Result<String, String> {
+pub async fn search_modpacks(
+    query: String,
+    client: State<'_, CurseforgeClient>,
+) -> Result<String, String> {
     info!("Searching for modpacks with query {:?}", query);
-    // Create a new HTTP client instance
-    let client = Client::new();
-
-    // Prepare the headers with the API key
-    let mut headers: HeaderMap = HeaderMap::new();
-    headers.insert("x-api-key", header_parsed_api_key!());
 
-    // Create the API request to get the files for the specified project ID
-    let request = client
-        .get( format!("https://api.curseforge.com/v1/mods/search?gameId=432&searchFilter={}&classId=4471&sortOrder=desc&pageSize=50",query))
-        .headers(headers)
-        .send()
+    // Keep the raw response, the frontend reads fields the library doesn't model
+    let response: serde_json::Value = search_projects(&SearchQuery::new(query), &client)
         .await
         .map_err(|e| e.to_string())?;
-    let body = request.text().await.map_err(|e| e.to_string())?;
-    Ok(body)
+    Ok(response.to_string())
 }
 
 #[tauri::command]
@@ -54,15 +64,25 @@ pub async fn unpack(
     id: u64,
     pack_version: u64,
     output: String,
+    options: UnpackOptions,
     on_event: Channel<ProcessProgressResponse>,
-) -> Result<(), String> {
+    state: State<'_, UnpackState>,
+    client: State<'_, CurseforgeClient>,
+) -> Result<ProcessResult, String> {
     let mut downloader = curseforge_pack_downloader::CurseforgePackDownloader::default();
+    downloader.set_client(client.inner().clone());
     downloader.set_parallel_downloads(16);
+    // An existing install in the chosen folder is only touched if the user chose to
     downloader.set_output_directory(&output);
-    downloader.set_temp_directory(PathBuf::from(output).join("temp"));
+    downloader.set_output_policy(options.output_policy);
+    downloader.set_temp_directory(temp_directory());
     downloader.set_validate(true);
+    downloader.set_cancellation_token(state.begin());
     downloader.set_validate_if_size_less_than(10000);
     downloader.set_pack_version(pack_version);
+    downloader.set_optional_mods(options.optional_mods);
+    // Share downloads with earlier installs
+    downloader.set_download_cache(DownloadCache::default_location());
 
     match downloader
         .process_id(id, move |e| {
@@ -70,7 +90,8 @@ pub async fn unpack(
         })
         .await
     {
-        Ok(_) => Ok(()),
+        Ok(result) => Ok(result),
+        Err(e) if is_cancelled(e.as_ref()) => Err("Cancelled".to_string()),
         Err(e) => Err(e.to_string()),
     }
 }
@@ -79,13 +100,20 @@ pub async fn unpack(
 pub async fn unpack_file(
     file: String,
     output: String,
+    options: UnpackOptions,
     on_event: Channel<ProcessProgressResponse>,
-) -> Result<(), String> {
+    state: State<'_, UnpackState>,
+    client: State<'_, CurseforgeClient>,
+) -> Result<ProcessResult, String> {
     let mut downloader = curseforge_pack_downloader::CurseforgePackDownloader::default();
+    downloader.set_client(client.inner().clone());
     downloader.set_parallel_downloads(16);
+    // An existing install in the chosen folder is only touched if the user chose to
     downloader.set_output_directory(&output);
-    downloader.set_temp_directory(PathBuf::from(output).join("temp"));
+    downloader.set_output_policy(options.output_policy);
+    downloader.set_temp_directory(temp_directory());
     downloader.set_validate(true);
+    downloader.set_cancellation_token(state.begin());
 
     match downloader
         .process_file(file, move |e| {
@@ -93,14 +121,63 @@ pub async fn unpack_file(
         })
         .await
     {
-        Ok(_) => Ok(()),
+        Ok(result) => Ok(result),
+        Err(e) if is_cancelled(e.as_ref()) => Err("Cancelled".to_string()),
         Err(e) => Err(e.to_string()),
     }
 }
 
+/// Cancels the running unpack, partial files are removed and nothing is installed.
+#[tauri::command]
+pub fn cancel_unpack(state: State<'_, UnpackState>) -> Result<(), String> {
+    let current = state
+        .cancellation_token
+        .lock()
+        .map_err(|_| "Unpack state lock poisoned".to_string())?;
+    if let Some(token) = current.as_ref() {
+        info!("Cancelling unpack");
+        token.cancel();
+    }
+    Ok(())
+}
+
+/// A unique temp directory for in-flight downloads, outside of the output folder.
+fn temp_directory() -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "curseforge-pack-downloader-{}",
+        std::time::SystemTime::now()
+            .duration_since(std::time::SystemTime::UNIX_EPOCH)
+            .unwrap_or_else(|_| std::time::Duration::new(0, 0))
+            .as_millis()
+    ))
+}
+
+/// Lists the optional mods of a pack version with their names, so the user can pick them
+/// before calling `unpack`. Only the pack archive is downloaded, to read its manifest.
 #[tauri::command]
-pub async fn get_pack_versions(id: u64) -> Result<Vec<ModpackVersionFile>, String> {
-    curseforge_pack_downloader::curseforge_api::get_pack_versions(id)
+pub async fn get_optional_mods(
+    id: u64,
+    pack_version: u64,
+    client: State<'_, CurseforgeClient>,
+) -> Result<Vec<PlannedMod>, String> {
+    let mut downloader = curseforge_pack_downloader::CurseforgePackDownloader::default();
+    downloader.set_client(client.inner().clone());
+    downloader.set_temp_directory(temp_directory());
+    downloader.set_pack_version(pack_version);
+
+    let plan = downloader
+        .plan_identifier(&PackIdentifier::ProjectId(id))
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(plan.optional_mods().cloned().collect())
+}
+
+#[tauri::command]
+pub async fn get_pack_versions(
+    id: u64,
+    client: State<'_, CurseforgeClient>,
+) -> Result<Vec<ModpackVersionFile>, String> {
+    curseforge_pack_downloader::curseforge_api::get_pack_versions(id, &client)
         .await
         .map_err(|e| e.to_string())
 }
@@ -1,6 +1,8 @@
+use curseforge_pack_downloader::curseforge_client::{
+    config_file_path, resolve_api_key, CurseforgeClient, API_KEY_ENV_VAR,
+};
 use log::error;
 use std::env::set_var;
-use std::process::exit;
 
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
 mod curseforge_api;
@@ -12,28 +14,34 @@ pub fn run() {
     set_var("RUST_LOG", "info");
     env_logger::init();
 
-    // Attempt to read environment variables from the embedded env.ini file
-    let env = match env::Env::new() {
-        Ok(env) => env,
-        Err(err) => {
-            // Log an error and exit if env variables cannot be read
-            error!("Unable to read environment variables from the env.ini file, make sure its filled out before building: {}", err);
-            exit(1);
+    // Resolve the API key: the environment, then the config file, then the embedded env.ini
+    let api_key = match resolve_api_key(None, Some(env::ENV_FILE)) {
+        Some((api_key, _)) => api_key,
+        None => {
+            // Keep running so the window opens, requests will fail until a key is configured
+            error!(
+                "No CurseForge API key found, set {} or add 'curseforge_api_key=<key>' to {}",
+                API_KEY_ENV_VAR,
+                config_file_path()
+                    .map(|path| path.display().to_string())
+                    .unwrap_or("the config file".to_string())
+            );
+            String::new()
         }
     };
 
-    // set the `CURSEFORGE_API_KEY` environment variable
-    // this will be required by the `CurseforgePackDownloader`
-    set_var("CURSEFORGE_API_KEY", &env.curseforge_api_key);
-
     tauri::Builder::default()
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_shell::init())
+        .manage(curseforge_api::UnpackState::default())
+        .manage(CurseforgeClient::new(api_key))
         .invoke_handler(tauri::generate_handler![
             curseforge_api::search_modpacks,
             curseforge_api::unpack,
             curseforge_api::unpack_file,
+            curseforge_api::cancel_unpack,
             curseforge_api::get_pack_versions,
+            curseforge_api::get_optional_mods,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -1,11 +1,11 @@
-import {Button, Progress, Spinner} from "@nextui-org/react";
+import {Button, Progress, Select, SelectItem, Spinner} from "@nextui-org/react";
 import PackItemComponent from "../components/PackItemComponent.tsx";
 import {FontAwesomeIcon} from "@fortawesome/react-fontawesome";
 import {faArchive} from "@fortawesome/free-solid-svg-icons";
 import OTooltip from "../components/Extends/OTooltip.tsx";
 import OInput from "../components/Extends/OInput.tsx";
 import {useEffect, useState} from "react";
-import {ProcessProgressResponse, search_modpacks, unpack_modpack, unpack_modpack_file} from "../ts/curseforge.ts";
+import {cancel_unpack, OutputPolicy, ProcessProgressResponse, ProcessResult, search_modpacks, unpack_modpack, unpack_modpack_file} from "../ts/curseforge.ts";
 import {ModSearchResult} from "../ts/ModSearchResult.ts";
 import {useAlertModal} from "../providers/AlertModalProvider.tsx";
 import {open} from "@tauri-apps/plugin-shell";
@@ -16,6 +16,8 @@ export default function Home()
     const [search, setSearch] = useState("");
     const [items, setItems] = useState<ModSearchResult | null>(null);
     const [unpackProgress, setUnpackProgress] = useState<ProcessProgressResponse | null>(null);
+    // Never touch an existing install unless the user picks what should happen to it
+    const [outputPolicy, setOutputPolicy] = useState<OutputPolicy>("failIfExists");
     const {alert} = useAlertModal();
 
     useEffect(() =>
@@ -42,6 +44,9 @@ export default function Home()
                             color={"primary"}
                             size={"sm"}
                         />
+                        <Button className={"mt-4"} color={"danger"} variant={"flat"} onClick={cancel_unpack}>
+                            Cancel
+                        </Button>
                     </div>
                 </div>
             }
@@ -55,6 +60,18 @@ export default function Home()
                             value={search}
                             onValueChange={setSearch}
                         />
+                        <Select
+                            className={"max-w-56"}
+                            label={"If the pack is already installed"}
+                            disallowEmptySelection
+                            selectedKeys={[outputPolicy]}
+                            onSelectionChange={keys => setOutputPolicy(Array.from(keys)[0] as OutputPolicy)}
+                        >
+                            <SelectItem key={"failIfExists"}>Stop</SelectItem>
+                            <SelectItem key={"backup"}>Back up and replace</SelectItem>
+                            <SelectItem key={"merge"}>Merge into it</SelectItem>
+                            <SelectItem key={"overwrite"}>Replace it</SelectItem>
+                        </Select>
                         <OTooltip content={`Select pack archive from the file system.`}>
                             <Button className={"min-w-0 h-12"} onClick={async () =>
                             {
@@ -79,17 +96,21 @@ export default function Home()
 
                                 if (!path) return;
 
+                                let result: ProcessResult;
                                 try
                                 {
-                                    await unpack_modpack_file(file, path, setUnpackProgress);
-                                } catch
+                                    result = await unpack_modpack_file(file, path, setUnpackProgress, outputPolicy);
+                                } catch (err)
                                 {
+                                    setUnpackProgress(null);
+                                    if (err === "Cancelled") return;
                                     console.error("Failed to unpack modpack.");
                                     alert({
                                         title: "Failed to unpack",
                                         type: "error",
-                                        message: "Failed to unpack modpack"
+                                        message: `Failed to unpack modpack: ${err}`
                                     });
+                                    return;
                                 }
                                 setUnpackProgress(null);
                                 alert({
@@ -101,7 +122,7 @@ export default function Home()
                                             label: "Open",
                                             onClick: () =>
                                             {
-                                                open(path);
+                                                open(result.output);
                                             }
                                         }
                                     ]
@@ -128,17 +149,21 @@ export default function Home()
                                 websiteUrl={item.links.websiteUrl}
                                 onUnpack={async (path, fileId) =>
                                 {
+                                    let result: ProcessResult;
                                     try
                                     {
-                                        await unpack_modpack(item.id, fileId, path, setUnpackProgress);
-                                    } catch
+                                        result = await unpack_modpack(item.id, fileId, path, setUnpackProgress, undefined, outputPolicy);
+                                    } catch (err)
                                     {
+                                        setUnpackProgress(null);
+                                        if (err === "Cancelled") return;
                                         console.error("Failed to unpack modpack.");
                                         alert({
                                             title: "Failed to unpack",
                                             type: "error",
-                                            message: "Failed to unpack modpack"
+                                            message: `Failed to unpack modpack: ${err}`
                                         });
+                                        return;
                                     }
                                     setUnpackProgress(null);
                                     alert({
@@ -150,7 +175,7 @@ export default function Home()
                                                 label: "Open",
                                                 onClick: () =>
                                                 {
-                                                    open(path);
+                                                    open(result.output);
                                                 }
                                             }
                                         ]
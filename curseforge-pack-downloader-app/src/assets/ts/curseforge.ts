@@ -6,15 +6,30 @@ export interface ProcessProgressResponse
     stage: ProcessStage;
     progress: number;
     message: string;
+    event: ProcessEvent;
 }
 
-enum ProcessStage
-{
-    ExtractingArchive,
-    DownloadingArchive,
-    DownloadingMods,
-    Finalizing,
-}
+export type ProcessStage = "extractingArchive" | "downloadingArchive" | "downloadingMods" | "finalizing";
+
+export type ProcessEvent =
+    | { type: "stageStarted", stage: ProcessStage }
+    | { type: "stageFinished", stage: ProcessStage }
+    | { type: "modResolved", projectId: number, fileId: number, name: string, fileName: string, size: number | null }
+    | { type: "modDownloadStarted", projectId: number, fileId: number, fileName: string }
+    | { type: "modDownloadProgress", projectId: number, fileId: number, downloaded: number, total: number | null }
+    | { type: "modDownloadCompleted", projectId: number, fileId: number, path: string, bytes: number, source: DownloadSource }
+    | { type: "modDownloadFailed", projectId: number, fileId: number, error: string }
+    | { type: "manualDownloadRequired", projectId: number, fileId: number, fileName: string, url: string }
+    | { type: "modSkipped", projectId: number, fileId: number, reason: string }
+    | { type: "modValidated", projectId: number, fileId: number, passed: boolean }
+    | { type: "overridesCopied", files: number, bytes: number }
+    | { type: "outputWritten", path: string }
+    | { type: "ruleApplied", rule: AppliedRule };
+
+export type AppliedRule =
+    | { type: "excluded", projectId: number, fileId: number, rule: string }
+    | { type: "replaced", projectId: number, fileId: number, rule: string, with: string }
+    | { type: "added", source: string };
 
 
 export async function search_modpacks(query: string): Promise<ModSearchResult>
@@ -31,16 +46,92 @@ export async function search_modpacks(query: string): Promise<ModSearchResult>
     return {} as ModSearchResult;
 }
 
-export async function unpack_modpack(id: number, pack_version: number, path: string, callback: (progress: ProcessProgressResponse) => void): Promise<void>
+/** What happens to the mods a pack doesn't mark as required, required mods are always installed. */
+export type OptionalModsPolicy =
+    | { type: "includeAll" }
+    | { type: "excludeAll" }
+    | { type: "allowlist", projectIds: number[] }
+    | { type: "disabled" };
+
+/** What happens when the install folder already contains files, an empty folder is always installed into. */
+export type OutputPolicy = "failIfExists" | "overwrite" | "merge" | "backup";
+
+export interface PlannedMod
+{
+    projectId: number;
+    fileId: number;
+    name: string;
+    path: string;
+    size: number | null;
+    required: boolean;
+    usesFallbackUrl: boolean;
+    manualDownloadUrl: string | null;
+}
+
+/** Everything a finished unpack did, `output` is the resolved path of the pack. */
+export interface ProcessResult
+{
+    output: string;
+    pack: {
+        name: string;
+        version: string | null;
+        author: string | null;
+        projectId: number | null;
+        fileId: number | null;
+        minecraftVersion: string | null;
+        modLoader: string | null;
+    };
+    mods: ModResult[];
+    overrideFiles: number;
+    overrideBytes: number;
+    downloadedBytes: number;
+    rules: AppliedRule[];
+    stages: { stage: ProcessStage, durationMs: number }[];
+    durationMs: number;
+}
+
+export interface ModResult
+{
+    projectId: number;
+    fileId: number;
+    name: string | null;
+    fileName: string | null;
+    status: "pending" | "completed" | "failed" | "skipped";
+    path: string | null;
+    bytes: number | null;
+    validated: boolean | null;
+    message: string | null;
+    url: string | null;
+    source: DownloadSource | null;
+    durationMs: number | null;
+}
+
+export type DownloadSource =
+    | { type: "api", url: string }
+    | { type: "mirror", url: string }
+    | { type: "cache" }
+    | { type: "manual", path: string }
+    | { type: "installed" };
+
+export async function unpack_modpack(id: number, pack_version: number, path: string, callback: (progress: ProcessProgressResponse) => void, optional_mods?: OptionalModsPolicy, output_policy?: OutputPolicy): Promise<ProcessResult>
 {
     const downloadEvent = new Channel<ProcessProgressResponse>();
     downloadEvent.onmessage = callback;
-    await invoke("unpack", {id: id, packVersion: pack_version, output: path, onEvent: downloadEvent});
+    return await invoke("unpack", {id: id, packVersion: pack_version, output: path, options: {optionalMods: optional_mods, outputPolicy: output_policy}, onEvent: downloadEvent}) as ProcessResult;
 }
 
-export async function unpack_modpack_file(file: string, path: string, callback: (progress: ProcessProgressResponse) => void): Promise<void>
+export async function get_optional_mods(id: number, pack_version: number): Promise<PlannedMod[]>
+{
+    return await invoke("get_optional_mods", {id: id, packVersion: pack_version}) as PlannedMod[];
+}
+
+export async function unpack_modpack_file(file: string, path: string, callback: (progress: ProcessProgressResponse) => void, output_policy?: OutputPolicy): Promise<ProcessResult>
 {
     const downloadEvent = new Channel<ProcessProgressResponse>();
     downloadEvent.onmessage = callback;
-    await invoke("unpack_file", {file: file, output: path, onEvent: downloadEvent});
-}
\ No newline at end of file
+    return await invoke("unpack_file", {file: file, output: path, options: {outputPolicy: output_policy}, onEvent: downloadEvent}) as ProcessResult;
+}
+export async function cancel_unpack(): Promise<void>
+{
+    await invoke("cancel_unpack");
+}
@@ -0,0 +1,62 @@
+use std::error::Error;
+use std::fmt::Display;
+use std::future::Future;
+
+pub use tokio_util::sync::CancellationToken;
+
+/// The error returned when processing is stopped through a `CancellationToken`.
+///
+/// Use `is_cancelled` to tell a cancelled run apart from a failed one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cancelled;
+
+impl Display for Cancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "The operation was cancelled")
+    }
+}
+
+impl Error for Cancelled {}
+
+/// Checks whether an error returned by the downloader is a `Cancelled` error.
+///
+/// # Arguments
+///
+/// * `err` - The error to check.
+///
+/// # Returns
+///
+/// `true` if the run was cancelled rather than failed.
+pub fn is_cancelled(err: &(dyn Error + 'static)) -> bool {
+    err.downcast_ref::<Cancelled>().is_some()
+}
+
+/// Runs a future until it completes or the token is cancelled, whichever happens first.
+/// A cancelled future is dropped, which aborts in-flight HTTP requests.
+///
+/// # Arguments
+///
+/// * `token` - The token that cancels the future.
+/// * `future` - The future to run.
+///
+/// # Returns
+///
+/// The output of the future, or `Cancelled` if the token was cancelled first.
+pub(crate) async fn cancellable<F: Future>(
+    token: &CancellationToken,
+    future: F,
+) -> Result<F::Output, Cancelled> {
+    token.run_until_cancelled(future).await.ok_or(Cancelled)
+}
+
+/// Returns `Cancelled` if the token has been cancelled.
+///
+/// # Arguments
+///
+/// * `token` - The token to check.
+pub(crate) fn check_cancelled(token: &CancellationToken) -> Result<(), Cancelled> {
+    if token.is_cancelled() {
+        return Err(Cancelled);
+    }
+    Ok(())
+}
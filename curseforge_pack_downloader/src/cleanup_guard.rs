@@ -0,0 +1,62 @@
+use log::{error, info};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Removes a file or directory when dropped, unless it has been disarmed.
+///
+/// This makes sure staging and temp data is cleaned up on every exit path,
+/// including errors returned with `?` and cancelled futures.
+pub struct CleanupGuard {
+    path: PathBuf,
+    armed: bool,
+}
+
+impl CleanupGuard {
+    /// Creates a new guard for `path`. Nothing is created on disk.
+    ///
+    /// # Parameters
+    ///
+    /// - `path`: The file or directory that will be removed on drop.
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            armed: true,
+        }
+    }
+
+    /// The guarded path.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Keeps the guarded path on disk.
+    ///
+    /// # Returns
+    ///
+    /// The guarded path.
+    pub fn disarm(mut self) -> PathBuf {
+        self.armed = false;
+        std::mem::take(&mut self.path)
+    }
+}
+
+impl Drop for CleanupGuard {
+    fn drop(&mut self) {
+        if !self.armed {
+            return;
+        }
+
+        // `symlink_metadata` doesn't follow links, so a link is removed rather than its target
+        let result = match self.path.symlink_metadata() {
+            Ok(metadata) if metadata.is_dir() => fs::remove_dir_all(&self.path),
+            Ok(_) => fs::remove_file(&self.path),
+            // Nothing to clean up
+            Err(_) => return,
+        };
+
+        match result {
+            Ok(_) => info!("Removed {}", self.path.display()),
+            Err(err) => error!("Unable to remove {}: {}", self.path.display(), err),
+        }
+    }
+}
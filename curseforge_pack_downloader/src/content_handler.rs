@@ -0,0 +1,189 @@
+use crate::cancellation::check_cancelled;
+use crate::download_options::DownloadOptions;
+use crate::mod_type::ModType;
+use crate::output_sink::OutputSink;
+use crate::safe_path::{sanitize_entry_name, sanitize_file_name, UnsafePathError};
+use crate::server_properties::{property_line, SERVER_PROPERTIES_FILE_NAME};
+use log::{error, info};
+use std::error::Error;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use zip::ZipArchive;
+
+/// The file every Minecraft world has at its root.
+const LEVEL_DAT_FILE_NAME: &str = "level.dat";
+
+/// What a `ContentHandler` installed.
+#[derive(Debug, Clone)]
+pub struct InstalledContent {
+    /// The file or directory relative to the output root.
+    pub path: PathBuf,
+    /// The number of bytes written.
+    pub bytes: u64,
+}
+
+/// Installs a completed download of a project class into the output.
+pub trait ContentHandler: Send + Sync {
+    /// Installs a download.
+    ///
+    /// # Parameters
+    ///
+    /// - `download`: The completed download in the temp directory.
+    /// - `relative`: Where the file belongs relative to the output root, e.g. `saves/world.zip`.
+    /// - `known_md5`: The MD5 hash of `download` if it was already calculated.
+    /// - `sink`: The output to write to.
+    /// - `options`: The download settings.
+    ///
+    /// # Returns
+    ///
+    /// What was installed, or an error if the download can't be installed.
+    fn install(
+        &self,
+        download: &Path,
+        relative: &Path,
+        known_md5: Option<&str>,
+        sink: &mut dyn OutputSink,
+        options: &DownloadOptions,
+    ) -> Result<InstalledContent, Box<dyn Error>>;
+}
+
+/// Moves the download into the output as is.
+pub struct FileHandler;
+
+/// Extracts a world archive into a directory next to where the archive belongs, e.g. `saves/<name>`.
+///
+/// The world is named after the folder that contains `level.dat`, or after the archive if
+/// `level.dat` is at its root. In server mode the world is set as the `level-name` of
+/// `server.properties`.
+pub struct WorldHandler;
+
+/// The handler that installs the downloads of a project class.
+pub fn handler_for(mod_type: &ModType) -> &'static dyn ContentHandler {
+    match mod_type {
+        ModType::World => &WorldHandler,
+        _ => &FileHandler,
+    }
+}
+
+impl ContentHandler for FileHandler {
+    fn install(
+        &self,
+        download: &Path,
+        relative: &Path,
+        known_md5: Option<&str>,
+        sink: &mut dyn OutputSink,
+        _options: &DownloadOptions,
+    ) -> Result<InstalledContent, Box<dyn Error>> {
+        let bytes = sink.add_file(relative, download, known_md5)?;
+        Ok(InstalledContent {
+            path: relative.to_path_buf(),
+            bytes,
+        })
+    }
+}
+
+impl ContentHandler for WorldHandler {
+    fn install(
+        &self,
+        download: &Path,
+        relative: &Path,
+        _known_md5: Option<&str>,
+        sink: &mut dyn OutputSink,
+        options: &DownloadOptions,
+    ) -> Result<InstalledContent, Box<dyn Error>> {
+        let mut archive = ZipArchive::new(File::open(download)?)
+            .map_err(|err| format!("World {} is not a zip archive: {}", relative.display(), err))?;
+
+        // Sanitize every entry before anything is written
+        let mut entries = Vec::with_capacity(archive.len());
+        for index in 0..archive.len() {
+            let entry = archive.by_index(index)?;
+            let path = sanitize_entry_name(entry.name())?;
+            if entry.is_symlink() {
+                error!("Refusing to extract symbolic link '{}'", entry.name());
+                return Err(UnsafePathError::Symlink(entry.name().to_string()).into());
+            }
+            entries.push(path);
+        }
+
+        let root = world_root(&entries).ok_or_else(|| {
+            format!(
+                "World {} has no {}",
+                relative.display(),
+                LEVEL_DAT_FILE_NAME
+            )
+        })?;
+        let name = match root.file_name() {
+            Some(name) => name.to_string_lossy().to_string(),
+            None => relative
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .ok_or_else(|| format!("World {} has no name", relative.display()))?,
+        };
+        let target = relative
+            .parent()
+            .unwrap_or(Path::new(""))
+            .join(sanitize_file_name(&name)?);
+        info!("Extracting world {} to {}", name, target.display());
+
+        let mut bytes = 0;
+        for (index, path) in entries.iter().enumerate() {
+            check_cancelled(&options.cancellation_token)?;
+            // Anything outside of the world folder, like `__MACOSX`, is left out
+            let path = match path.strip_prefix(&root) {
+                Ok(path) if !path.as_os_str().is_empty() => target.join(path),
+                _ => continue,
+            };
+            let mut entry = archive.by_index(index)?;
+            if entry.is_dir() {
+                sink.create_dir(&path)?;
+            } else {
+                bytes += sink.write_file(&path, &mut entry)?;
+            }
+        }
+
+        // The extracted archive isn't needed anymore, free the space right away
+        fs::remove_file(download)?;
+
+        if options.server {
+            let level_name = target
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            info!("Setting the level-name of the server to {}", level_name);
+            sink.write_file(
+                Path::new(SERVER_PROPERTIES_FILE_NAME),
+                &mut property_line("level-name", &level_name).as_bytes(),
+            )?;
+        }
+
+        Ok(InstalledContent {
+            path: target,
+            bytes,
+        })
+    }
+}
+
+/// Finds the folder of a world archive that contains `level.dat`.
+///
+/// # Returns
+///
+/// An empty path if `level.dat` is at the root, the folder if exactly one top level folder
+/// contains it, or `None` if the archive isn't a single world.
+fn world_root(entries: &[PathBuf]) -> Option<PathBuf> {
+    if entries
+        .iter()
+        .any(|path| path == Path::new(LEVEL_DAT_FILE_NAME))
+    {
+        return Some(PathBuf::new());
+    }
+    let mut roots = entries.iter().filter_map(|path| {
+        let mut components = path.components();
+        let root = components.next()?;
+        (components.as_path() == Path::new(LEVEL_DAT_FILE_NAME))
+            .then(|| PathBuf::from(root.as_os_str()))
+    });
+    let root = roots.next()?;
+    roots.next().is_none().then_some(root)
+}
@@ -1,414 +1,840 @@
-use crate::mod_file::ModFileResponse;
-use crate::mod_type::{ModType, ModTypeExt};
+use crate::cancellation::{cancellable, check_cancelled, CancellationToken, Cancelled};
+use crate::content_handler::{handler_for, ContentHandler, FileHandler};
+use crate::curseforge_client::CurseforgeClient;
+use crate::download_cache::DownloadCache;
+use crate::download_options::DownloadOptions;
+use crate::download_sources::{download_candidates, DownloadSource};
+use crate::manual_downloads::{is_distribution_blocked, website_url, ManualDownload};
+use crate::mod_file::{ModFileItem, ModFileResponse};
+use crate::mod_rules::{ModDownload, ModJobs, ModMetadata};
+use crate::mod_type::ModType;
+use crate::modpack_version_file::{ModpackVersionFile, PackVersionSelector, ReleaseType};
+use crate::optional_mods::disabled_path;
+use crate::output_sink::OutputSink;
 use crate::pack_manifest::Manifest;
-use crate::project_structure::ProjectItem;
-use crate::modpack_version_file::ModpackVersionFile;
+use crate::progress::{ProcessEvent, ProgressReporter};
+use crate::project_structure::{ProjectData, ProjectItem};
+use crate::response_validation::{
+    check_archive_structure, check_length, check_response, ExpectedContent,
+};
+use crate::safe_path::{safe_join, sanitize_file_name};
+use crate::search::{Pagination, SearchQuery, MAX_PAGE_SIZE};
 use futures::future;
 use log::{error, info, warn};
 use md5::{Digest, Md5};
-use reqwest::header::HeaderMap;
-use reqwest::Client;
+use reflink_copy::reflink_or_copy;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 use serde_json::Value;
 use std::error::Error;
+use std::fmt::Display;
 use std::fs::{create_dir_all, File};
 use std::io::Write;
 use std::io::{self, Read};
 use std::iter::Map;
 use std::path::{Path, PathBuf};
-use uri_encode::encode_uri_component;
+use std::sync::Mutex;
 
-pub struct ModDownloadProgressResponse {
-    pub downloaded: u32,
-    pub total: u32,
-}
+/// How many bytes of a mod are downloaded between two `ModDownloadProgress` events.
+const PROGRESS_REPORT_INTERVAL: u64 = 256 * 1024;
 
-/// Macro to parse the CURSEFORGE_API_KEY from environment variables.
-///
-/// This macro attempts to retrieve the API key from the environment, parse it,
-/// and handle any potential errors during parsing. If successful, it returns
-/// the parsed API key.
-macro_rules! header_parsed_api_key {
-    () => {
-        match std::env::var("CURSEFORGE_API_KEY")
-            .unwrap_or("".to_string())
-            .parse()
-        {
-            Ok(key) => key, // Return the parsed API key if successful
-            Err(err) => {
-                // Log an error message if parsing fails
-                error!("Failed to parse API key: {}", err);
-                return Err("Failed to parse API key".into());
-            }
-        }
-    };
-}
+/// How many IDs are sent in a single request to the batch endpoints.
+const BATCH_SIZE: usize = 500;
 
-/// Downloads the latest version archive of a mod pack given a project ID.
+/// Downloads the archive of a mod pack version given a project ID.
 ///
-/// This asynchronous function queries the CurseForge API for the latest
-/// mod pack files associated with the specified project ID. It then
-/// downloads the file and stores it in a temporary directory, returning
-/// the path to the downloaded file.
+/// This asynchronous function fetches every file of the project from the CurseForge API,
+/// picks the requested version and downloads its archive into the temporary directory,
+/// returning the path to the downloaded file.
 ///
 /// # Arguments
 ///
 /// * `project_id` - A `u64` representing the CurseForge project ID.
+/// * `version` - Which version of the pack to download.
+/// * `channel` - The least stable release channel `PackVersionSelector::Latest` may pick,
+///   or `None` for any channel.
+/// * `temp_dir` - The directory the archive is downloaded to.
+/// * `client` - The client used for the API request and the download.
+/// * `cache` - The archive is taken from this cache if it is there, and added to it otherwise.
+/// * `cancellation_token` - Aborts the download when cancelled.
 ///
 /// # Returns
 ///
-/// A `Result` containing the `PathBuf` to the downloaded file if successful,
-/// or a boxed error if any operation fails.
+/// A `Result` containing a tuple of the `PathBuf` to the downloaded file and the ID of the
+/// selected file, if the API reported one, or a boxed error if any operation fails.
 ///
 /// # Errors
 ///
 /// This function will return an error if:
 /// - The API request fails.
-/// - The API response does not contain the expected 'data' array.
-/// - The 'downloadUrl' or 'fileName' fields are missing from the response.
+/// - No version matches `version` and `channel`.
+/// - The 'downloadUrl' or 'fileName' fields are missing from the selected version.
 /// - An error occurs during file creation or writing.
+/// - The token is cancelled, in which case the error is `Cancelled`.
 ///
 /// # Example
 ///
 /// ```no-run
-/// let path = download_latest_pack_archive(123456).await?;
+/// let client = CurseforgeClient::new("api key");
+/// let (path, file_id) = download_pack_archive(
+///     123456,
+///     &PackVersionSelector::Latest,
+///     Some(ReleaseType::Release),
+///     "./temp",
+///     &client,
+///     None,
+///     &token,
+/// )
+/// .await?;
 /// println!("Downloaded to: {:?}", path);
 /// ```
-pub async fn download_latest_pack_archive(
+pub async fn download_pack_archive(
     project_id: u64,
-    file_id: Option<u64>,
+    version: &PackVersionSelector,
+    channel: Option<ReleaseType>,
     temp_dir: impl AsRef<Path>,
-) -> Result<PathBuf, Box<dyn Error>> {
-    info!("Downloading the latest pack version");
-
-    // Create a new HTTP client instance
-    let client = Client::new();
-
-    // Prepare the headers with the API key
-    let mut headers: HeaderMap = HeaderMap::new();
-    headers.insert("x-api-key", header_parsed_api_key!());
-
-    // Create the API request to get the files for the specified project ID
-    let request = client
-        .get(format!(
-            "https://api.curseforge.com/v1/mods/{}/files",
-            project_id
-        ))
-        .headers(headers);
-
-    // Send the request and process the response
-    let response: Value = match request.send().await {
-        Ok(response) => response.json().await?, // Deserialize the response to JSON
-        Err(err) => {
-            // Return an error if the request fails
-            return Err(Box::new(err));
-        }
-    };
+    client: &CurseforgeClient,
+    cache: Option<&DownloadCache>,
+    cancellation_token: &CancellationToken,
+) -> Result<(PathBuf, Option<u64>), Box<dyn Error>> {
+    info!("Resolving pack version '{}'", version);
 
-    // Extract the 'data' field from the response JSON
-    let data: Vec<Value> = response
-        .get("data")
-        .ok_or("Missing 'data' field in response")?
-        .as_array()
-        .ok_or("'data' field is not an array")?
-        .to_vec();
-
-    // Retrieve the first file in the 'data' array as the latest file
-    let latest_file: &Value = data
-        .iter()
-        .find(|file| {
-            let id = file.get("id").and_then(Value::as_u64).unwrap_or(0);
-            if let Some(file_id) = file_id {
-                return id == file_id;
-            }
-            true
-        })
-        .ok_or("Failed to find a suitable file.")?;
-
-    // Extract the download URL and file name from the latest file
-    let download_url: String = latest_file
-        .get("downloadUrl")
-        .ok_or("Missing 'downloadUrl' in latest file")?
-        .as_str()
-        .ok_or("'downloadUrl' is not a string")?
-        .to_string();
+    // The files endpoint isn't sorted, so select the version from all files of the project
+    let versions = cancellable(cancellation_token, get_pack_versions(project_id, client)).await??;
+    let selected = version.select(&versions, channel).ok_or_else(|| {
+        error!(
+            "No version '{}' found for project {}{}",
+            version,
+            project_id,
+            channel
+                .map(|channel| format!(" in the {} channel", channel))
+                .unwrap_or_default()
+        );
+        "Failed to find a suitable file."
+    })?;
+    info!(
+        "Selected version {} ({}, {})",
+        selected.display_name.as_deref().unwrap_or("[Unnamed]"),
+        selected.id.unwrap_or_default(),
+        selected.file_date.as_deref().unwrap_or("unknown date")
+    );
 
-    let file_name: String = latest_file
-        .get("fileName")
-        .ok_or("Missing 'fileName' in latest file")?
-        .as_str()
-        .ok_or("'fileName' is not a string")?
-        .to_string();
+    // Extract the download URL and file name from the selected file
+    let download_url: String = selected
+        .download_url
+        .clone()
+        .ok_or("Missing 'downloadUrl' in selected file")?;
+    let file_name: String = selected
+        .file_name
+        .clone()
+        .ok_or("Missing 'fileName' in selected file")?;
 
     create_dir_all(temp_dir.as_ref())?;
 
     // Determine the path to save the downloaded file
-    let file_path = temp_dir.as_ref().join(file_name);
+    // The file name comes from the API, so make sure it can't escape the temp directory
+    let file_path = safe_join(temp_dir.as_ref(), sanitize_file_name(&file_name)?)?;
+
+    // Use the cached archive if an earlier install downloaded it
+    let file_id = selected.id.and_then(|id| u64::try_from(id).ok());
+    let md5 = selected
+        .hashes
+        .iter()
+        .flatten()
+        .find(|hash| hash.algo == Some(2))
+        .and_then(|hash| hash.value.as_deref());
+    let size = selected
+        .file_length
+        .and_then(|length| u64::try_from(length).ok())
+        .filter(|length| *length > 0);
+    let cache_key = cache.zip(file_id).zip(md5);
+    if let Some(((cache, file_id), md5)) = cache_key {
+        match cache.restore(file_id, md5, size, &file_path) {
+            Ok(true) => {
+                info!("Using the cached pack archive {}", file_name);
+                return Ok((file_path, Some(file_id)));
+            }
+            Ok(false) => {}
+            Err(err) => warn!("Unable to read {} from the cache: {}", file_name, err),
+        }
+    }
 
-    // Create the file and write the downloaded content to it
+    // Create the file and write the downloaded content to it, an error page or a truncated
+    // download fails here instead of when the archive is read
     let mut file = File::create(&file_path)?;
-    let response = reqwest::get(download_url).await?;
-    let bytes = response.bytes().await?;
-    file.write_all(&bytes)?;
+    let response =
+        cancellable(cancellation_token, client.http().get(download_url).send()).await??;
+    let mut response = check_response(response, ExpectedContent::File)?;
+
+    // Stream the archive to disk, packs can be several gigabytes large
+    let mut downloaded = 0u64;
+    let mut hasher = Md5::new();
+    while let Some(chunk) = cancellable(cancellation_token, response.chunk()).await?? {
+        file.write_all(&chunk)?;
+        hasher.update(&chunk);
+        downloaded += chunk.len() as u64;
+    }
+    file.flush()?;
+    check_length(size, downloaded)?;
+    if let Some(md5) = md5 {
+        let hash = bytes_to_hex_string(&hasher.finalize());
+        if !hash.eq_ignore_ascii_case(md5) {
+            error!("Pack archive {} failed validation!", file_name);
+            return Err(format!("Expected MD5 {} for {}, got {}", md5, file_name, hash).into());
+        }
+    }
+    check_archive_structure(&file_path)?;
+
+    if let Some(((cache, file_id), md5)) = cache_key {
+        // The hash was checked against the downloaded bytes above
+        if let Err(err) = cache.insert(file_id, md5, Some(md5), &file_path) {
+            warn!("Unable to cache {}: {}", file_name, err);
+        }
+    }
 
     // Return the path to the downloaded file
-    Ok(file_path)
+    Ok((file_path, file_id))
 }
 
-/// Downloads mods specified in the manifest to the given directory.
+/// Downloads mods specified in the manifest to the given output.
 ///
 /// # Arguments
 ///
 /// * `manifest` - A reference to the manifest containing information about which mods to download.
-/// * `directory` - The directory path where mods will be stored.
-/// * `parallel` - The number of downloads to perform in parallel.
-/// * `validate` - A flag indicating whether to validate downloaded files.
-/// * `validate_if_size_less_than` - Optional size parameter. Files smaller than this value will be validated.
+/// * `sink` - The output the mods will be placed in.
+/// * `options` - The download settings, in-flight downloads are written to `options.temp_dir`
+///   before being moved to the output. Cancelling `options.cancellation_token` aborts
+///   in-flight downloads and stops scheduling new ones.
+/// * `reporter` - Receives an event for every step of every mod download.
 ///
 /// # Returns
 ///
 /// A Result that is Ok if successful, or an error if any download or IO operation fails.
-pub async fn download_mods_from_manifest<F>(
+/// If the token is cancelled, the error is `Cancelled`. If mods failed, the remaining mods are
+/// still downloaded and the error is `ModsFailed`, so a partial install is never finalized.
+pub async fn download_mods_from_manifest(
     manifest: &Manifest,
-    directory: impl AsRef<Path>,
-    parallel: u8,
-    validate: bool,
-    validate_if_size_less_than: Option<u64>,
-    mut on_progress: F,
-) -> Result<(), Box<dyn Error>>
-where
-    F: FnMut(ModDownloadProgressResponse) + 'static + Send + Sync,
-{
+    sink: &Mutex<Box<dyn OutputSink>>,
+    options: &DownloadOptions,
+    reporter: &ProgressReporter,
+) -> Result<(), Box<dyn Error>> {
     info!("Downloading mods from manifest");
 
-    let directory = directory.as_ref().join("overrides");
+    let parallel = options.parallel;
+    let cancellation_token = &options.cancellation_token;
+
+    // Create the temp directory if it does not exist
+    create_dir_all(&options.temp_dir)?;
 
-    // Create the directory if it does not exist
-    create_dir_all(&directory)?;
+    // Apply the optional mods policy and the rules,
+    // the mod details are only fetched if a rule matches by slug or file name
+    // Mods that have to be downloaded by hand are detected up front from the same details
+    let metadata = if options.rules.needs_metadata() || options.manual_downloads.is_some() {
+        cancellable(
+            cancellation_token,
+            ModMetadata::fetch(manifest, &options.rules, &options.client),
+        )
+        .await??
+    } else {
+        ModMetadata::default()
+    };
+    let jobs = ModJobs::new(manifest, &options.optional_mods, &options.rules, &metadata);
+    reporter.set_total_mods(jobs.downloads.len() + jobs.skipped.len());
+    for rule in &jobs.applied {
+        reporter.emit(ProcessEvent::RuleApplied { rule: rule.clone() });
+    }
+    for skipped in &jobs.skipped {
+        info!("Skipping mod {}: {}", skipped.project_id, skipped.reason);
+        reporter.emit(ProcessEvent::ModSkipped {
+            project_id: skipped.project_id,
+            file_id: skipped.file_id,
+            reason: skipped.reason.clone(),
+        });
+    }
+
+    // Local jars of the rules are copied as is
+    for local_mod in &jobs.local {
+        let path = &local_mod.path;
+        let file_name = path
+            .file_name()
+            .ok_or_else(|| format!("Local mod {} has no file name", path.display()))?;
+        let mut relative = options.paths.path(&ModType::Mod).join(file_name);
+        if local_mod.disabled {
+            relative = disabled_path(relative);
+        }
+        info!("Copying {} to {}", path.display(), relative.display());
+        let mut file = File::open(path)?;
+        let mut sink = sink.lock().map_err(|_| "Output sink lock poisoned")?;
+        sink.write_file(&relative, &mut file)?;
+    }
+
+    // Mods that have to be downloaded by hand are waited for alongside the other downloads,
+    // so the user can download them while the rest of the pack is installed
+    let (manual, files): (Vec<_>, Vec<_>) = jobs.downloads.iter().partition(|download| {
+        options.manual_downloads.is_some() && metadata.is_distribution_blocked(download)
+    });
+    let manual_downloads = future::join_all(
+        manual
+            .iter()
+            .map(|download| install_mod(download, sink, options, reporter)),
+    );
 
     // Determine the number of file chunks based on the parallel parameter
-    let file_chunks =
-        manifest
-            .files
-            .chunks(if parallel == 0 || parallel > manifest.files.len() as u8 {
-                manifest.files.len()
-            } else {
-                parallel as usize
+    let file_chunks = files.chunks(if parallel == 0 || parallel as usize > files.len() {
+        files.len().max(1)
+    } else {
+        parallel as usize
+    });
+    let automatic_downloads = async {
+        let mut failures = Vec::new();
+        // Download each chunk of files
+        for file_chunk in file_chunks {
+            // Stop scheduling new downloads once cancelled
+            check_cancelled(cancellation_token)?;
+
+            // Map each file in the chunk to a download task
+            let download_tasks = file_chunk
+                .iter()
+                .map(|download| install_mod(download, sink, options, reporter))
+                .collect::<Vec<_>>();
+
+            warn!("Waiting for downloads to complete...");
+            // Wait for all download tasks to complete before continuing
+            let results = future::join_all(download_tasks).await;
+            failures.extend(results.into_iter().filter_map(Result::err));
+        }
+        check_cancelled(cancellation_token)?;
+        Ok::<_, Cancelled>(failures)
+    };
+
+    let (manual_failures, result) = future::join(manual_downloads, automatic_downloads).await;
+    let mut failures = result?;
+    check_cancelled(cancellation_token)?;
+
+    failures.extend(manual_failures.into_iter().filter_map(Result::err));
+    if !failures.is_empty() {
+        error!("{} of {} mods failed", failures.len(), jobs.downloads.len());
+        return Err(ModsFailed { failures }.into());
+    }
+    Ok(())
+}
+
+/// Downloads a single mod and moves it into the output, reporting how it ended up.
+///
+/// A failed mod is reported with `ModDownloadFailed` and returned, so processing continues
+/// with the remaining mods.
+async fn install_mod(
+    download: &ModDownload,
+    sink: &Mutex<Box<dyn OutputSink>>,
+    options: &DownloadOptions,
+    reporter: &ProgressReporter,
+) -> Result<(), ModFailure> {
+    let project_id = download.project_id;
+    let file_id = download.file_id;
+    let downloaded = match cancellable(
+        &options.cancellation_token,
+        download_mod(download, sink, options, reporter),
+    )
+    .await
+    {
+        Ok(downloaded) => downloaded,
+        // The download was aborted, the partial file is removed with the temp directory
+        Err(_) => return Ok(()),
+    };
+
+    // Move the completed download into the output
+    let result = downloaded.and_then(|downloaded| {
+        let mut sink = sink.lock().map_err(|_| "Output sink lock poisoned")?;
+        // A disabled download is installed as is, e.g. a disabled world isn't extracted
+        let handler: &dyn ContentHandler = if download.disabled {
+            &FileHandler
+        } else {
+            handler_for(&downloaded.mod_type)
+        };
+        let installed = handler.install(
+            &downloaded.temp_path,
+            &downloaded.relative,
+            downloaded.md5.as_deref(),
+            sink.as_mut(),
+            options,
+        )?;
+        Ok((installed, downloaded.source))
+    });
+    match result {
+        Ok((_, DownloadSource::Installed)) => {
+            reporter.emit(ProcessEvent::ModSkipped {
+                project_id,
+                file_id,
+                reason: "Already installed".to_string(),
             });
+            Ok(())
+        }
+        Ok((installed, source)) => {
+            reporter.emit(ProcessEvent::ModDownloadCompleted {
+                project_id,
+                file_id,
+                path: installed.path,
+                bytes: installed.bytes,
+                source,
+            });
+            Ok(())
+        }
+        Err(err) => {
+            error!("Failed to download mod: {}", err);
+            let failure = ModFailure {
+                project_id,
+                file_id,
+                error: err.to_string(),
+            };
+            reporter.emit(ProcessEvent::ModDownloadFailed {
+                project_id,
+                file_id,
+                error: failure.error.clone(),
+            });
+            Err(failure)
+        }
+    }
+}
 
-    let total_mods_count = manifest.files.len() as u32;
-    let mut mods_downloaded_count = 0u32;
+/// The error returned when mods of the manifest couldn't be downloaded or installed.
+///
+/// The remaining mods are still processed, but the output isn't finalized, so an existing
+/// install is left untouched. Every failure is also reported with `ModDownloadFailed`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModsFailed {
+    pub failures: Vec<ModFailure>,
+}
 
-    // Download each chunk of files
-    for file_chunk in file_chunks {
-        // Map each file in the chunk to a download task
-        let download_tasks = file_chunk
-            .iter()
-            .map(|file| async {
-                match download_mod(
-                    file.project_id as u64,
-                    file.file_id as u64,
-                    directory.clone(),
-                    validate,
-                    validate_if_size_less_than,
-                )
-                .await
-                {
-                    Ok(_) => (),
-                    Err(err) => {
-                        error!("Failed to download mod: {}", err);
-                    }
-                }
-            })
-            .collect::<Vec<_>>();
-
-        warn!("Waiting for downloads to complete...");
-        // Wait for all download tasks to complete before continuing
-        future::join_all(download_tasks).await;
-        mods_downloaded_count += file_chunk.len() as u32;
-        on_progress(ModDownloadProgressResponse {
-            downloaded: mods_downloaded_count,
-            total: total_mods_count,
-        });
+/// A mod that couldn't be downloaded or installed, e.g. because every download source was
+/// rejected for an error page, a wrong size or hash or a broken archive.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ModFailure {
+    pub project_id: u64,
+    pub file_id: u64,
+    pub error: String,
+}
+
+impl Display for ModsFailed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} mods failed to download", self.failures.len())?;
+        for failure in &self.failures {
+            write!(
+                f,
+                "\n  {} (file {}): {}",
+                failure.project_id, failure.file_id, failure.error
+            )?;
+        }
+        Ok(())
     }
+}
 
-    Ok(())
+impl Error for ModsFailed {}
+
+/// A mod file in the temp directory, ready to be installed.
+struct DownloadedMod {
+    temp_path: PathBuf,
+    /// Where the file belongs relative to the output root.
+    relative: PathBuf,
+    mod_type: ModType,
+    /// Where the file was taken from, `DownloadSource::Installed` if it wasn't downloaded.
+    source: DownloadSource,
+    /// The MD5 hash of the file if it was calculated, so the install record doesn't hash it again.
+    md5: Option<String>,
 }
 
-/// Downloads a single mod file and writes it to the specified directory.
+/// Downloads a single mod file into the temp directory.
+///
+/// The caller is responsible for moving the file into the output once it has been
+/// fully written (and validated, if requested).
 ///
 /// # Arguments
 ///
-/// * `project_id` - The ID of the project to download.
-/// * `file_id` - The ID of the file within the project.
-/// * `directory` - The directory path where the file will be stored.
-/// * `validate` - A flag indicating whether to validate the downloaded file.
-/// * `validate_if_size_less_than` - Optional size parameter. Files smaller than this value will be validated.
+/// * `download` - The mod to download.
+/// * `sink` - The output the mod will be placed in. With `options.incremental`, an intact file
+///   of the install it replaces is reused instead of downloading it again.
+/// * `options` - The download settings. The in-flight download is written to `options.temp_dir`,
+///   and validated if `options.validate` is set and the file is smaller than
+///   `options.validate_if_size_less_than`.
+/// * `reporter` - Receives the resolve, download progress and validation events of the mod.
 ///
 /// # Returns
 ///
-/// A Result containing the path to the downloaded file if successful, or an error if the download or any IO operation fails.
+/// A Result containing the file in the temp directory and where it belongs in the output if
+/// successful, or an error if the download or any IO operation fails.
 async fn download_mod(
-    project_id: u64,
-    file_id: u64,
-    directory: impl AsRef<Path>,
-    validate: bool,
-    validate_if_size_less_than: Option<u64>,
-) -> Result<PathBuf, Box<dyn Error>> {
+    download: &ModDownload,
+    sink: &Mutex<Box<dyn OutputSink>>,
+    options: &DownloadOptions,
+    reporter: &ProgressReporter,
+) -> Result<DownloadedMod, Box<dyn Error>> {
+    let project_id = download.project_id;
+    let file_id = download.file_id;
+
     // Set a default value for validate_if_size_less_than if none is provided
-    let validate_if_size_less_than = validate_if_size_less_than.unwrap_or(0);
+    let validate_if_size_less_than = options.validate_if_size_less_than.unwrap_or(0);
 
-    let client = Client::new();
+    let client = &options.client;
 
     // Fetch project and file information from the API
-    let project = get_project(project_id, &client).await?.data;
-    let file_item = get_mod_item(project_id, file_id, &client).await?.data;
+    let project = get_project(project_id, client).await?.data;
+    let file_item = get_mod_item(project_id, file_id, client).await?.data;
 
     // Determine if access to the download URL is denied
-    let denied_api_access = file_item.download_url.is_none();
-    let file_name = file_item.file_name.clone();
+    let denied_api_access = is_distribution_blocked(&project, &file_item);
+    let manual_downloads = options
+        .manual_downloads
+        .as_ref()
+        .filter(|_| denied_api_access);
 
-    // Obtain the download URL or a fallback URL if access is denied
-    let download_url: String = if denied_api_access {
-        get_no_api_download_url(file_id, &file_name)?
-    } else {
-        file_item.download_url.clone().ok_or_else(|| {
-            error!("No 'downloadUrl' in response data");
-            "No 'downloadUrl' in response data"
+    // The file name is used as a path, so reject anything that isn't a plain file name
+    let file_name = sanitize_file_name(&file_item.file_name)
+        .map_err(|err| {
+            error!("Refusing to download mod {}: {}", project_id, err);
+            err
         })?
+        .to_string();
+    let file_length = file_item
+        .file_length
+        .and_then(|length| u64::try_from(length).ok())
+        .filter(|length| *length > 0);
+    reporter.emit(ProcessEvent::ModResolved {
+        project_id,
+        file_id,
+        name: project.name.clone().unwrap_or(file_name.clone()),
+        file_name: file_name.clone(),
+        size: file_length,
+    });
+
+    // Construct the path relative to the output root based on the project class ID
+    let mod_type = project.class_id.clone().unwrap_or(ModType::Mod);
+    let relative = options.paths.path(&mod_type).join(&file_name);
+    let relative = if download.disabled {
+        disabled_path(relative)
+    } else {
+        relative
     };
 
-    let directory = directory.as_ref();
+    // The download is written to the temp directory until it is complete
+    let file_path = safe_join(&options.temp_dir, format!("{}-{}", file_id, file_name))?;
+    let md5 = file_item
+        .hashes
+        .iter()
+        .find(|e| e.algo == 2)
+        .map(|hash| hash.value.as_str());
 
-    // Construct the full directory path based on the project class ID
-    let directory = directory.join(project.class_id.unwrap_or(ModType::Mod).to_path());
-    create_dir_all(&directory).map_err(|err| {
-        error!(
-            "Failed to create directory {}: {}",
-            directory.display(),
-            err
-        );
-        "Failed to create directory"
-    })?;
+    // Reuse the file of the install the output replaces if it is intact
+    if let (true, Some(md5)) = (options.incremental, md5) {
+        let existing = sink
+            .lock()
+            .map_err(|_| "Output sink lock poisoned")?
+            .existing_file(&relative);
+        if let Some(existing) = existing {
+            if is_intact(&existing, file_length, md5)? {
+                info!("{} is already installed", relative.display());
+                // Never link, so the previous install stays untouched if the run fails
+                reflink_or_copy(&existing, &file_path)?;
+                return Ok(DownloadedMod {
+                    temp_path: file_path,
+                    relative,
+                    mod_type,
+                    source: DownloadSource::Installed,
+                    md5: Some(md5.to_ascii_lowercase()),
+                });
+            }
+        }
+    }
 
-    // Determine the final file path for the downloaded file
-    let file_path = directory.join(Path::new(&file_name));
     info!(
-        "Downloading {} from {} to {}",
+        "Downloading {} to {}",
         file_name,
-        download_url,
-        file_path.to_str().unwrap_or("[Invalid file path]")
+        relative.to_str().unwrap_or("[Invalid file path]")
     );
+    reporter.emit(ProcessEvent::ModDownloadStarted {
+        project_id,
+        file_id,
+        file_name: file_name.clone(),
+    });
 
-    // Perform the file download
-    let response = reqwest::get(download_url).await.map_err(|err| {
-        error!("Failed to download file: {}", err);
-        "Failed to download file"
-    })?;
-
-    // Create the file where the downloaded bytes will be saved
-    let mut file = File::create(&file_path).map_err(|err| {
-        error!("Failed to create file {}: {}", file_path.display(), err);
-        "Failed to create file"
-    })?;
+    // Use the cached file if an earlier install downloaded it
+    let cached = match (&options.cache, md5) {
+        (Some(cache), Some(md5)) => cache
+            .restore(file_id, md5, file_length, &file_path)
+            .unwrap_or_else(|err| {
+                warn!("Unable to read {} from the cache: {}", file_name, err);
+                false
+            }),
+        _ => false,
+    };
+    // Whether the file was checked against the hash or fingerprint the API reports
+    let mut verified = false;
+    // The hash of the file if it was calculated, so caching and recording it doesn't hash it again
+    let mut known_md5 = None;
+    let source = if cached {
+        info!("Using the cached {}", file_name);
+        // The cache checks the hash before it hands out a file
+        verified = true;
+        known_md5 = md5;
+        DownloadSource::Cache
+    } else if let Some(manual_downloads) = manual_downloads {
+        let fingerprint = file_item
+            .file_fingerprint
+            .and_then(|fingerprint| u32::try_from(fingerprint).ok())
+            .ok_or("No fingerprint in response data, unable to recognize a manual download")?;
+        let url = website_url(&project, file_id);
+        warn!(
+            "{} can't be downloaded automatically, download it from {} into {}",
+            file_name,
+            url,
+            manual_downloads.watch_dir.display()
+        );
+        reporter.emit(ProcessEvent::ManualDownloadRequired {
+            project_id,
+            file_id,
+            file_name: file_name.clone(),
+            url,
+        });
+        let manual_download = ManualDownload {
+            file_name: &file_name,
+            size: file_length,
+            fingerprint,
+        };
+        let path = manual_downloads
+            .wait_for(&manual_download, &file_path)
+            .await?;
+        reporter.emit(ProcessEvent::ModValidated {
+            project_id,
+            file_id,
+            passed: true,
+        });
+        verified = true;
+        DownloadSource::Manual { path }
+    } else {
+        if file_item.download_url.is_none() {
+            warn!(
+                "The API withholds the download URL of '{}', trying the CDN mirrors",
+                file_name
+            );
+        }
+        let candidates = download_candidates(
+            file_item.download_url.as_deref(),
+            &options.cdn_mirrors,
+            file_id,
+            &file_name,
+        );
+        let request = FileRequest {
+            project_id,
+            file_id,
+            file_path: &file_path,
+            size: file_length,
+            md5,
+        };
+        let source = fetch_from_sources(&request, &candidates, client, reporter).await?;
+        // Every candidate is checked against the hash before it is accepted
+        if md5.is_some() {
+            reporter.emit(ProcessEvent::ModValidated {
+                project_id,
+                file_id,
+                passed: true,
+            });
+            verified = true;
+            known_md5 = md5;
+        }
+        source
+    };
 
-    // Read the downloaded bytes into the file
-    let bytes = response.bytes().await.map_err(|err| {
-        error!("Failed to read response bytes: {}", err);
-        "Failed to read response bytes"
-    })?;
+    // Validate the file if needed and if it is below the size threshold
+    if options.validate && !verified && file_path.metadata()?.len() <= validate_if_size_less_than {
+        warn!("Validating {}...", file_name);
+        let md5_hash = md5.ok_or_else(|| {
+            error!("No MD5 hash in response data");
+            "No MD5 hash in response data"
+        })?;
 
-    file.write_all(&bytes).map_err(|err| {
-        error!("Failed to write to file {}: {}", file_path.display(), err);
-        "Failed to write to file"
-    })?;
+        let passed = validate_file(&file_path, md5_hash)?;
+        reporter.emit(ProcessEvent::ModValidated {
+            project_id,
+            file_id,
+            passed,
+        });
+        if !passed {
+            error!("File '{}' failed validation!", file_name);
+            return Err("File failed validation".into());
+        }
+        info!("File '{}' passed validation!", file_name);
+        known_md5 = Some(md5_hash);
+    }
 
-    // Validate the file if needed and if it is below the size threshold
-    if validate && file_path.metadata()?.len() <= validate_if_size_less_than {
-        if denied_api_access {
-            error!(
-                "Unable to validate file '{}', API access was denied!",
-                file_name
-            );
-        } else {
-            warn!("Validating {}...", file_name);
-            let md5_hash = file_item
-                .hashes
-                .iter()
-                .find(|e| e.algo == 2)
-                .ok_or_else(|| {
-                    error!("No MD5 hash in response data");
-                    "No MD5 hash in response data"
-                })?;
-
-            if !validate_file(&file_path, md5_hash.value.clone())? {
-                error!("File '{}' failed validation!", file_name);
-                return Err("File failed validation".into());
-            }
-            info!("File '{}' passed validation!", file_name);
+    // Keep the download for the next install, a failure only costs a download later
+    if let (Some(cache), Some(md5), false) = (&options.cache, md5, cached) {
+        if let Err(err) = cache.insert(file_id, md5, known_md5, &file_path) {
+            warn!("Unable to cache {}: {}", file_name, err);
         }
     }
 
-    Ok(file_path)
+    Ok(DownloadedMod {
+        temp_path: file_path,
+        relative,
+        mod_type,
+        source,
+        md5: known_md5.map(str::to_ascii_lowercase),
+    })
 }
 
-/// Generates a download URL using a file ID and name when API access is denied.
+/// Checks whether a file has the size and MD5 hash the API reports.
 ///
 /// # Arguments
 ///
-/// * `file_id` - A 64-bit identifier for the file.
-/// * `file_name` - The name of the file to be downloaded.
+/// * `path` - The file to check.
+/// * `size` - The expected size, not checked if `None`.
+/// * `md5` - The expected MD5 hash as a hex string.
 ///
 /// # Returns
 ///
-/// * `Result<String, Box<dyn Error>>` - A URL string on success or an error on failure.
-fn get_no_api_download_url(
-    file_id: u64,
-    file_name: impl AsRef<str>,
-) -> Result<String, Box<dyn Error>> {
-    let file_name = file_name.as_ref();
-
-    // Log a warning about using the no-api download URL
-    warn!(
-        "API access denied for '{}', falling back to no-api download url",
-        file_name
-    );
+/// Whether the file matches, or an error if it can't be read.
+fn is_intact(path: &Path, size: Option<u64>, md5: &str) -> Result<bool, io::Error> {
+    let actual_size = path.metadata()?.len();
+    if size.is_some_and(|size| size != actual_size) {
+        return Ok(false);
+    }
+    Ok(md5_file(path)? == md5.to_ascii_lowercase())
+}
 
-    // Encode the file name for URL compatibility
-    let file_name = encode_uri_component(file_name);
+/// A mod file that is downloaded from one of its download sources.
+struct FileRequest<'a> {
+    project_id: u64,
+    file_id: u64,
+    /// The file the download is written to.
+    file_path: &'a Path,
+    /// The size the API reports.
+    size: Option<u64>,
+    /// The MD5 hash the API reports, as a hex string.
+    md5: Option<&'a str>,
+}
 
-    // Modify the file ID for URL path structure
-    let file_id = modify_id(file_id)?;
+/// Downloads a mod file from the first of its download sources that serves it intact.
+///
+/// A source is rejected if its response fails, isn't a file, e.g. an HTML error page,
+/// doesn't match the size and hash the API reports or isn't a readable archive.
+///
+/// # Arguments
+///
+/// * `request` - The file to download.
+/// * `candidates` - The sources to try, in order.
+/// * `client` - The client to use for sending the requests.
+/// * `reporter` - Receives the download progress events.
+///
+/// # Returns
+///
+/// The source the file was downloaded from, or an error if every source failed.
+async fn fetch_from_sources(
+    request: &FileRequest<'_>,
+    candidates: &[DownloadSource],
+    client: &CurseforgeClient,
+    reporter: &ProgressReporter,
+) -> Result<DownloadSource, Box<dyn Error>> {
+    let mut failures = Vec::new();
+    for candidate in candidates {
+        let url = match candidate {
+            DownloadSource::Api { url } | DownloadSource::Mirror { url } => url,
+            _ => continue,
+        };
+        let failure = match fetch_file(request, url, client, reporter).await {
+            Ok(hash) => match request.md5 {
+                Some(md5) if !hash.eq_ignore_ascii_case(md5) => {
+                    format!("expected MD5 {}, got {}", md5, hash)
+                }
+                _ => return Ok(candidate.clone()),
+            },
+            Err(err) => err.to_string(),
+        };
+        warn!("Rejected {}: {}", candidate, failure);
+        failures.push(format!("{}: {}", url, failure));
+    }
 
-    // Construct and return the final download URL
-    Ok(format!(
-        "https://mediafilez.forgecdn.net/files/{}/{}",
-        file_id, file_name
-    ))
+    if failures.is_empty() {
+        return Err("No download source for the file".into());
+    }
+    Err(format!("All download sources failed: {}", failures.join("; ")).into())
 }
 
-/// Modifies a numeric ID for creating a part of a URL path.
+/// Streams a mod file from a URL into the file of `request`, reporting the progress along the way.
 ///
 /// # Arguments
 ///
-/// * `id` - A 64-bit numeric identifier.
+/// * `request` - The file to download.
+/// * `download_url` - The URL to download from.
+/// * `client` - The client to use for sending the request.
+/// * `reporter` - Receives the download progress events.
 ///
 /// # Returns
 ///
-/// * `Result<String, Box<dyn Error>>` - A formatted string on success or an error if the ID is invalid.
-fn modify_id(id: u64) -> Result<String, Box<dyn Error>> {
-    let id_str = id.to_string();
-    let first_part = &id_str[0..4];
-    let remaining = id_str[4..].trim_start_matches('0').to_string();
+/// The MD5 hash of the downloaded bytes as a hex string, or an error if the download fails.
+async fn fetch_file(
+    request: &FileRequest<'_>,
+    download_url: &str,
+    client: &CurseforgeClient,
+    reporter: &ProgressReporter,
+) -> Result<String, Box<dyn Error>> {
+    let response = client
+        .http()
+        .get(download_url)
+        .send()
+        .await
+        .map_err(|err| format!("Failed to download file: {}", err))?;
+    let mut response = check_response(response, ExpectedContent::File)?;
 
-    // Handle cases where no remaining part is valid
-    let remaining = if remaining.is_empty() {
-        Err("Invalid Id")
-    } else {
-        Ok(&remaining)
-    }?;
+    // Create the file where the downloaded bytes will be saved
+    let mut file = File::create(request.file_path).map_err(|err| {
+        error!(
+            "Failed to create file {}: {}",
+            request.file_path.display(),
+            err
+        );
+        "Failed to create file"
+    })?;
 
-    // Return formatted string with parts separated by a '/'
-    Ok(format!("{}/{}", first_part, remaining))
+    // Stream the response into the file, reporting progress along the way
+    let total = response.content_length().or(request.size);
+    let mut downloaded = 0u64;
+    let mut hasher = Md5::new();
+    let mut last_reported = 0u64;
+    while let Some(chunk) = response.chunk().await.map_err(|err| {
+        error!("Failed to read response bytes: {}", err);
+        "Failed to read response bytes"
+    })? {
+        file.write_all(&chunk).map_err(|err| {
+            error!(
+                "Failed to write to file {}: {}",
+                request.file_path.display(),
+                err
+            );
+            "Failed to write to file"
+        })?;
+        hasher.update(&chunk);
+        downloaded += chunk.len() as u64;
+
+        // Reporting every chunk would flood the callback, so only report every so often
+        if downloaded - last_reported >= PROGRESS_REPORT_INTERVAL {
+            last_reported = downloaded;
+            reporter.emit(ProcessEvent::ModDownloadProgress {
+                project_id: request.project_id,
+                file_id: request.file_id,
+                downloaded,
+                total,
+            });
+        }
+    }
+    reporter.emit(ProcessEvent::ModDownloadProgress {
+        project_id: request.project_id,
+        file_id: request.file_id,
+        downloaded,
+        total,
+    });
+
+    check_length(request.size, downloaded)?;
+    check_archive_structure(request.file_path)?;
+    Ok(bytes_to_hex_string(&hasher.finalize()))
 }
 
 /// Validates a file's integrity by comparing its hash with an expected hash.
@@ -422,6 +848,21 @@ fn modify_id(id: u64) -> Result<String, Box<dyn Error>> {
 ///
 /// * `Result<bool, io::Error>` - `true` if the calculated hash matches the expected hash, `false` otherwise.
 fn validate_file(file_path: impl AsRef<Path>, hash: impl AsRef<str>) -> Result<bool, io::Error> {
+    // Calculate the hash and compare with the expected hash
+    let calculated_hash = md5_file(file_path)?;
+    Ok(calculated_hash == hash.as_ref())
+}
+
+/// Calculates the MD5 hash of a file.
+///
+/// # Arguments
+///
+/// * `file_path` - The file system path for the file to hash.
+///
+/// # Returns
+///
+/// * `Result<String, io::Error>` - The hash as a lowercase hex string.
+pub(crate) fn md5_file(file_path: impl AsRef<Path>) -> Result<String, io::Error> {
     let mut file = File::open(file_path)?;
     let mut hasher = Md5::new();
     let mut buffer = [0u8; 4096];
@@ -435,9 +876,7 @@ fn validate_file(file_path: impl AsRef<Path>, hash: impl AsRef<str>) -> Result<b
         hasher.update(&buffer[..bytes_read]);
     }
 
-    // Calculate the hash and compare with the expected hash
-    let calculated_hash = bytes_to_hex_string(&hasher.finalize());
-    Ok(calculated_hash == hash.as_ref())
+    Ok(bytes_to_hex_string(&hasher.finalize()))
 }
 
 /// Converts a byte slice to a hex string.
@@ -449,7 +888,7 @@ fn validate_file(file_path: impl AsRef<Path>, hash: impl AsRef<str>) -> Result<b
 /// # Returns
 ///
 /// * `String` - Hexadecimal representation of the input bytes.
-fn bytes_to_hex_string(bytes: &[u8]) -> String {
+pub(crate) fn bytes_to_hex_string(bytes: &[u8]) -> String {
     // Map each byte to a 2-digit hex string and collect into a single string
     Map::collect(bytes.iter().map(|byte| format!("{:02x}", byte)))
 }
@@ -459,26 +898,21 @@ fn bytes_to_hex_string(bytes: &[u8]) -> String {
 /// # Arguments
 ///
 /// * `project_id` - The unique identifier of the project to retrieve.
-/// * `client` - The HTTP client to use for sending requests.
+/// * `client` - The client to use for sending requests.
 ///
 /// # Returns
 ///
 /// A `Result` containing the `ProjectItem` if the operation succeeds,
 /// or an error boxed as `Box<dyn Error>` if it fails.
-pub async fn get_project(project_id: u64, client: &Client) -> Result<ProjectItem, Box<dyn Error>> {
-    // Create a new header map to store request headers.
-    let mut headers: HeaderMap = HeaderMap::new();
-
-    // Insert the API key into the headers.
-    headers.insert("x-api-key", header_parsed_api_key!());
-
+pub async fn get_project(
+    project_id: u64,
+    client: &CurseforgeClient,
+) -> Result<ProjectItem, Box<dyn Error>> {
     // Prepare the GET request to the CurseForge API, inserting the appropriate project ID.
-    let request = client
-        .get(format!("https://api.curseforge.com/v1/mods/{}", project_id))
-        .headers(headers);
+    let request = client.get(format!("https://api.curseforge.com/v1/mods/{}", project_id))?;
 
     // Send the request asynchronously and wait for the response.
-    let response = request.send().await?;
+    let response = check_response(request.send().await?, ExpectedContent::Json)?;
     // Parse the JSON response into a ProjectItem.
     let data: ProjectItem = response.json().await?;
 
@@ -492,7 +926,7 @@ pub async fn get_project(project_id: u64, client: &Client) -> Result<ProjectItem
 ///
 /// * `project_id` - The unique identifier of the project to which the mod file belongs.
 /// * `file_id` - The unique identifier of the mod file to retrieve.
-/// * `client` - The HTTP client to use for sending requests.
+/// * `client` - The client to use for sending requests.
 ///
 /// # Returns
 ///
@@ -501,24 +935,16 @@ pub async fn get_project(project_id: u64, client: &Client) -> Result<ProjectItem
 async fn get_mod_item(
     project_id: u64,
     file_id: u64,
-    client: &Client,
+    client: &CurseforgeClient,
 ) -> Result<ModFileResponse, Box<dyn Error>> {
-    // Create a new header map to store request headers.
-    let mut headers: HeaderMap = HeaderMap::new();
-
-    // Insert the API key into the headers.
-    headers.insert("x-api-key", header_parsed_api_key!());
-
     // Prepare the GET request to the CurseForge API, inserting the appropriate project and file ID.
-    let request = client
-        .get(format!(
-            "https://api.curseforge.com/v1/mods/{}/files/{}",
-            project_id, file_id
-        ))
-        .headers(headers);
+    let request = client.get(format!(
+        "https://api.curseforge.com/v1/mods/{}/files/{}",
+        project_id, file_id
+    ))?;
 
     // Send the request asynchronously and wait for the response.
-    let response = request.send().await?;
+    let response = check_response(request.send().await?, ExpectedContent::Json)?;
     let response_text = response.text().await?;
     // Attempt to parse the JSON response into a ModFileResponse.
     let data: ModFileResponse = serde_json::from_str(&response_text).map_err(|err| {
@@ -530,20 +956,167 @@ async fn get_mod_item(
     Ok(data)
 }
 
-pub async fn get_pack_versions(project_id: u64) -> Result<Vec<ModpackVersionFile>, Box<dyn Error>> {
-    let url = format!("https://api.curseforge.com/v1/mods/{}/files", project_id);
-    let client = Client::new();
-    let mut headers: HeaderMap = HeaderMap::new();
-    headers.insert("x-api-key", header_parsed_api_key!());
-    let request = client.get(url).headers(headers);
-
-    let response = request.send().await?;
-    let response_json: Value = response.json().await?;
-    let files: Vec<ModpackVersionFile> = serde_json::from_value(
-        response_json
-            .get("data")
-            .ok_or("Missing 'data' field in response")?
-            .clone(),
-    )?;
+/// Retrieves every file of a mod pack from the CurseForge API.
+///
+/// The files endpoint is paginated, so pages are requested until every file has been fetched.
+/// The files are returned in the order of the API, which isn't guaranteed to be sorted.
+///
+/// # Arguments
+///
+/// * `project_id` - The unique identifier of the mod pack.
+/// * `client` - The client to use for sending requests.
+///
+/// # Returns
+///
+/// A `Result` containing every `ModpackVersionFile` of the project, or an error if a request fails.
+pub async fn get_pack_versions(
+    project_id: u64,
+    client: &CurseforgeClient,
+) -> Result<Vec<ModpackVersionFile>, Box<dyn Error>> {
+    let mut files: Vec<ModpackVersionFile> = Vec::new();
+
+    loop {
+        let url = format!(
+            "https://api.curseforge.com/v1/mods/{}/files?index={}&pageSize={}",
+            project_id,
+            files.len(),
+            MAX_PAGE_SIZE
+        );
+        let request = client.get(url)?;
+
+        let response = check_response(request.send().await?, ExpectedContent::Json)?;
+        let response_json: Value = response.json().await?;
+        let page: Vec<ModpackVersionFile> = serde_json::from_value(
+            response_json
+                .get("data")
+                .ok_or("Missing 'data' field in response")?
+                .clone(),
+        )?;
+        let pagination: Option<Pagination> = response_json
+            .get("pagination")
+            .and_then(|pagination| serde_json::from_value(pagination.clone()).ok());
+
+        let page_size = page.len();
+        files.extend(page);
+
+        // Stop on an empty page too, so a wrong total can't cause an endless loop
+        match pagination {
+            Some(pagination) if page_size > 0 && (files.len() as u64) < pagination.total_count => {}
+            _ => break,
+        }
+    }
+
     Ok(files)
 }
+
+/// Retrieves many mod files at once from the CurseForge API.
+///
+/// # Arguments
+///
+/// * `file_ids` - The IDs of the files, requested in batches of `BATCH_SIZE`.
+/// * `client` - The client to use for sending requests.
+///
+/// # Returns
+///
+/// A `Result` containing the files that were found, in no particular order.
+/// Unknown IDs are left out, and so are files the response can't be parsed for.
+pub async fn get_mod_files(
+    file_ids: &[u64],
+    client: &CurseforgeClient,
+) -> Result<Vec<ModFileItem>, Box<dyn Error>> {
+    post_batch(
+        "https://api.curseforge.com/v1/mods/files",
+        "fileIds",
+        file_ids,
+        client,
+    )
+    .await
+}
+
+/// Retrieves many projects at once from the CurseForge API.
+///
+/// # Arguments
+///
+/// * `project_ids` - The IDs of the projects, requested in batches of `BATCH_SIZE`.
+/// * `client` - The client to use for sending requests.
+///
+/// # Returns
+///
+/// A `Result` containing the projects that were found, in no particular order.
+/// Unknown IDs are left out, and so are projects the response can't be parsed for.
+pub async fn get_projects(
+    project_ids: &[u64],
+    client: &CurseforgeClient,
+) -> Result<Vec<ProjectData>, Box<dyn Error>> {
+    post_batch(
+        "https://api.curseforge.com/v1/mods",
+        "modIds",
+        project_ids,
+        client,
+    )
+    .await
+}
+
+/// Posts `ids` to a batch endpoint and collects the `data` of every response.
+///
+/// Every item is parsed on its own, so a single unexpected item doesn't fail the whole batch.
+async fn post_batch<T: DeserializeOwned>(
+    url: &str,
+    key: &str,
+    ids: &[u64],
+    client: &CurseforgeClient,
+) -> Result<Vec<T>, Box<dyn Error>> {
+    let mut items = Vec::with_capacity(ids.len());
+
+    for chunk in ids.chunks(BATCH_SIZE) {
+        let body = serde_json::json!({ key: chunk });
+        let request = client.post(url)?.json(&body);
+        let response = check_response(request.send().await?, ExpectedContent::Json)?;
+        let response_json: Value = response.json().await?;
+        let data = match response_json.get("data") {
+            Some(Value::Array(data)) => data,
+            _ => return Err("Missing 'data' field in response".into()),
+        };
+
+        for item in data {
+            match serde_json::from_value(item.clone()) {
+                Ok(item) => items.push(item),
+                Err(err) => warn!(
+                    "Skipping item {} of {}: {}",
+                    item.get("id").unwrap_or(&Value::Null),
+                    url,
+                    err
+                ),
+            }
+        }
+    }
+
+    Ok(items)
+}
+
+/// Searches CurseForge for projects.
+///
+/// The response is deserialized into `T`, so callers can use `SearchResponse` for typed results
+/// or `serde_json::Value` to keep every field of the response.
+///
+/// # Arguments
+///
+/// * `query` - The search filters.
+/// * `client` - The client to use for sending requests.
+///
+/// # Returns
+///
+/// A `Result` containing the deserialized response, or an error if the request fails.
+pub async fn search_projects<T: DeserializeOwned>(
+    query: &SearchQuery,
+    client: &CurseforgeClient,
+) -> Result<T, Box<dyn Error>> {
+    let request = client.get(query.url())?;
+
+    let response = check_response(request.send().await?, ExpectedContent::Json)?;
+    let data: T = response.json().await.map_err(|err| {
+        error!("Failed to parse search response: {}", err);
+        "Failed to parse search response"
+    })?;
+    Ok(data)
+}
@@ -0,0 +1,197 @@
+use log::{error, info, warn};
+use reqwest::header::{HeaderMap, HeaderValue};
+use reqwest::{Client, RequestBuilder};
+use std::error::Error;
+use std::fmt::Display;
+use std::path::PathBuf;
+
+/// The environment variable the API key is read from.
+pub const API_KEY_ENV_VAR: &str = "CURSEFORGE_API_KEY";
+
+/// The name of the folder inside the user's config directory.
+const CONFIG_DIRECTORY_NAME: &str = "curseforge_pack_downloader";
+
+/// The name of the config file inside `CONFIG_DIRECTORY_NAME`.
+const CONFIG_FILE_NAME: &str = "config.ini";
+
+/// An HTTP client for the CurseForge API together with the API key it authenticates with.
+///
+/// Cloning is cheap, clones share the same connection pool.
+#[derive(Clone, Default)]
+pub struct CurseforgeClient {
+    client: Client,
+    api_key: String,
+}
+
+impl CurseforgeClient {
+    /// Creates a new client that authenticates with `api_key`.
+    ///
+    /// # Parameters
+    ///
+    /// - `api_key`: The CurseForge API key.
+    pub fn new(api_key: impl Into<String>) -> Self {
+        Self {
+            client: Client::new(),
+            api_key: api_key.into(),
+        }
+    }
+
+    /// Creates a new client with the key from the `CURSEFORGE_API_KEY` environment variable.
+    /// The key is empty if the variable isn't set, so API requests will be rejected.
+    pub fn from_env() -> Self {
+        Self::new(std::env::var(API_KEY_ENV_VAR).unwrap_or_default())
+    }
+
+    /// The API key requests are authenticated with.
+    pub fn api_key(&self) -> &str {
+        &self.api_key
+    }
+
+    /// The underlying HTTP client, used for requests that don't need the API key like file downloads.
+    pub fn http(&self) -> &Client {
+        &self.client
+    }
+
+    /// Creates a GET request to the CurseForge API with the API key header set.
+    ///
+    /// # Parameters
+    ///
+    /// - `url`: The full URL of the API endpoint.
+    ///
+    /// # Returns
+    ///
+    /// The request, or an error if the API key isn't a valid header value.
+    pub fn get(&self, url: impl AsRef<str>) -> Result<RequestBuilder, Box<dyn Error>> {
+        Ok(self.client.get(url.as_ref()).headers(self.headers()?))
+    }
+
+    /// Creates a POST request to the CurseForge API with the API key header set.
+    ///
+    /// # Parameters
+    ///
+    /// - `url`: The full URL of the API endpoint.
+    ///
+    /// # Returns
+    ///
+    /// The request, or an error if the API key isn't a valid header value.
+    pub fn post(&self, url: impl AsRef<str>) -> Result<RequestBuilder, Box<dyn Error>> {
+        Ok(self.client.post(url.as_ref()).headers(self.headers()?))
+    }
+
+    /// Builds the headers every API request needs.
+    fn headers(&self) -> Result<HeaderMap, Box<dyn Error>> {
+        let key: HeaderValue = self.api_key.parse().map_err(|err| {
+            error!("Failed to parse API key: {}", err);
+            "Failed to parse API key"
+        })?;
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-api-key", key);
+        Ok(headers)
+    }
+}
+
+/// Where a resolved API key came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiKeySource {
+    /// Passed explicitly, e.g. with `--api-key`.
+    Argument,
+    /// The `CURSEFORGE_API_KEY` environment variable.
+    Environment,
+    /// The user config file, see `config_file_path`.
+    ConfigFile,
+    /// The key embedded into the binary at build time.
+    Embedded,
+}
+
+impl Display for ApiKeySource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            ApiKeySource::Argument => "command line argument".to_string(),
+            ApiKeySource::Environment => format!("{} environment variable", API_KEY_ENV_VAR),
+            ApiKeySource::ConfigFile => match config_file_path() {
+                Some(path) => format!("config file {}", path.display()),
+                None => "config file".to_string(),
+            },
+            ApiKeySource::Embedded => "embedded env.ini".to_string(),
+        };
+        write!(f, "{}", str)
+    }
+}
+
+/// The path of the user config file, e.g. `~/.config/curseforge_pack_downloader/config.ini`
+/// on Linux or `%APPDATA%\curseforge_pack_downloader\config.ini` on Windows.
+///
+/// The file uses the same `key=value` format as `env.ini`.
+///
+/// # Returns
+///
+/// The path, or `None` if the platform has no config directory.
+pub fn config_file_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join(CONFIG_DIRECTORY_NAME).join(CONFIG_FILE_NAME))
+}
+
+/// Resolves the API key at runtime.
+///
+/// The first non-empty key wins, in this order:
+/// 1. `argument`, e.g. from `--api-key`
+/// 2. the `CURSEFORGE_API_KEY` environment variable
+/// 3. `curseforge_api_key` in the user config file, see `config_file_path`
+/// 4. `curseforge_api_key` in `embedded`, the contents of an `env.ini` embedded at build time
+///
+/// # Parameters
+///
+/// - `argument`: A key passed explicitly by the user.
+/// - `embedded`: The contents of the embedded `env.ini`, if any.
+///
+/// # Returns
+///
+/// The key and where it came from, or `None` if no key was found anywhere.
+pub fn resolve_api_key(
+    argument: Option<&str>,
+    embedded: Option<&str>,
+) -> Option<(String, ApiKeySource)> {
+    let non_empty = |key: String| {
+        let key = key.trim().to_string();
+        (!key.is_empty()).then_some(key)
+    };
+
+    let resolved = argument
+        .and_then(|key| non_empty(key.to_string()))
+        .map(|key| (key, ApiKeySource::Argument))
+        .or_else(|| {
+            std::env::var(API_KEY_ENV_VAR)
+                .ok()
+                .and_then(non_empty)
+                .map(|key| (key, ApiKeySource::Environment))
+        })
+        .or_else(|| {
+            let path = config_file_path()?;
+            let contents = std::fs::read_to_string(&path).ok()?;
+            parse_api_key(&contents)
+                .and_then(non_empty)
+                .map(|key| (key, ApiKeySource::ConfigFile))
+        })
+        .or_else(|| {
+            parse_api_key(embedded?)
+                .and_then(non_empty)
+                .map(|key| (key, ApiKeySource::Embedded))
+        });
+
+    match &resolved {
+        Some((_, source)) => info!("Using the API key from the {}", source),
+        None => warn!("No CurseForge API key found"),
+    }
+
+    resolved
+}
+
+/// Reads `curseforge_api_key` from `key=value` lines. Everything after a `#` is a comment.
+fn parse_api_key(contents: &str) -> Option<String> {
+    contents
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or(line))
+        .filter_map(|line| line.split_once('='))
+        .find(|(key, _)| key.trim().eq_ignore_ascii_case("curseforge_api_key"))
+        .map(|(_, value)| value.trim().to_string())
+}
@@ -0,0 +1,497 @@
+use crate::curseforge_api::md5_file;
+use log::{info, warn};
+use reflink_copy::reflink_or_copy;
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// The name of the cache directory inside the user's cache directory.
+const CACHE_DIRECTORY_NAME: &str = "curseforge_pack_downloader";
+
+/// The directory inside the cache root that holds the cached files.
+const FILES_DIRECTORY_NAME: &str = "files";
+
+/// The extension of files that are still being written into the cache.
+const PARTIAL_EXTENSION: &str = "partial";
+
+/// The extension of the empty file next to a cached file whose modification time is the last use.
+/// The cached file itself isn't touched, it may be hardlinked into installs.
+const USED_EXTENSION: &str = "used";
+
+/// A cache of downloaded files shared by every install, keyed by CurseForge file ID and MD5 hash.
+///
+/// Files are reflinked into the temp directory where the filesystem supports it, e.g. Btrfs,
+/// XFS or APFS, and copied otherwise, then moved into the output. A reflinked file shares its
+/// contents with the cache until either is changed. With `set_hardlinks`, files are hardlinked
+/// instead, so an install only takes space once on any filesystem.
+///
+/// Every cached file is checked against its hash before it is used, so a file that was edited
+/// through a hardlink in an install is removed from the cache instead of being installed again.
+#[derive(Clone, Debug)]
+pub struct DownloadCache {
+    root: PathBuf,
+    hardlinks: bool,
+}
+
+/// The size of a cache.
+#[derive(Serialize, Clone, Copy, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheStats {
+    pub files: u64,
+    pub bytes: u64,
+}
+
+/// What `DownloadCache::collect_garbage` removed.
+#[derive(Serialize, Clone, Copy, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct GarbageCollection {
+    pub removed_files: u64,
+    pub removed_bytes: u64,
+    /// The size of the cache afterwards.
+    pub remaining: CacheStats,
+}
+
+impl DownloadCache {
+    /// Creates a cache in `root`, the directory is created on first use.
+    pub fn new(root: impl AsRef<Path>) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+            hardlinks: false,
+        }
+    }
+
+    /// Sets whether files are hardlinked between the cache and the installs instead of reflinked
+    /// or copied.
+    ///
+    /// Hardlinked files share their contents, so a mod edited in place in an install is edited
+    /// in the cache too. The cache notices that by the hash and drops the file.
+    /// Filesystems without hardlinks, or a cache on another filesystem, fall back to reflinking
+    /// or copying.
+    ///
+    /// # Parameters
+    ///
+    /// - `hardlinks`: Whether to hardlink files, `false` by default.
+    ///
+    /// # Returns
+    ///
+    /// Returns a mutable reference to the current `DownloadCache` instance.
+    pub fn set_hardlinks(&mut self, hardlinks: bool) -> &mut Self {
+        self.hardlinks = hardlinks;
+        self
+    }
+
+    /// The cache in the user's cache directory, e.g. `~/.cache/curseforge_pack_downloader`.
+    ///
+    /// # Returns
+    ///
+    /// The cache, or `None` if the platform has no cache directory.
+    pub fn default_location() -> Option<Self> {
+        dirs::cache_dir().map(|dir| Self::new(dir.join(CACHE_DIRECTORY_NAME)))
+    }
+
+    /// The root directory of the cache.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Copies or links a cached file to `target`, e.g. a download in the temp directory.
+    ///
+    /// A cached file is only used if its size matches `size`, when given, and its hash matches
+    /// `md5`. A file with the wrong hash is removed from the cache. Every use is recorded, so
+    /// garbage collection removes the least recently used files first.
+    ///
+    /// # Parameters
+    ///
+    /// - `file_id`: The CurseForge file ID.
+    /// - `md5`: The MD5 hash the API reports for the file.
+    /// - `size`: The size the API reports for the file.
+    /// - `target`: Where the file is linked or copied to, must not exist.
+    ///
+    /// # Returns
+    ///
+    /// Whether the file was in the cache.
+    pub fn restore(
+        &self,
+        file_id: u64,
+        md5: &str,
+        size: Option<u64>,
+        target: impl AsRef<Path>,
+    ) -> io::Result<bool> {
+        let path = match self.entry_path(file_id, md5) {
+            Some(path) => path,
+            None => return Ok(false),
+        };
+        let metadata = match fs::metadata(&path) {
+            Ok(metadata) => metadata,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(false),
+            Err(err) => return Err(err),
+        };
+        if size.is_some_and(|size| size != metadata.len()) {
+            warn!(
+                "Cached file {} has the wrong size, ignoring it",
+                path.display()
+            );
+            return Ok(false);
+        }
+        // A hardlinked install may have changed the file since it was cached
+        if md5_file(&path)? != md5.to_ascii_lowercase() {
+            warn!(
+                "Cached file {} has the wrong hash, removing it",
+                path.display()
+            );
+            let _ = fs::remove_file(&path);
+            let _ = fs::remove_file(path.with_extension(USED_EXTENSION));
+            return Ok(false);
+        }
+
+        self.place(&path, target)?;
+        // Only used to order the garbage collection, a read-only cache still works without it
+        let _ = touch(path.with_extension(USED_EXTENSION));
+        Ok(true)
+    }
+
+    /// Adds a completed download to the cache.
+    ///
+    /// The download is only added if its hash matches `md5`, so a truncated or edited file never
+    /// ends up in the cache.
+    ///
+    /// # Parameters
+    ///
+    /// - `file_id`: The CurseForge file ID.
+    /// - `md5`: The MD5 hash the API reports for the file.
+    /// - `known_md5`: The MD5 hash of `source` if the caller already calculated it while
+    ///   downloading, `source` is hashed otherwise.
+    /// - `source`: The completed download, it is left in place.
+    ///
+    /// # Returns
+    ///
+    /// Whether the file was added.
+    pub fn insert(
+        &self,
+        file_id: u64,
+        md5: &str,
+        known_md5: Option<&str>,
+        source: impl AsRef<Path>,
+    ) -> io::Result<bool> {
+        let path = match self.entry_path(file_id, md5) {
+            Some(path) => path,
+            None => return Ok(false),
+        };
+        if path.exists() {
+            return Ok(true);
+        }
+        let actual = match known_md5 {
+            Some(known_md5) => known_md5.to_ascii_lowercase(),
+            None => md5_file(&source)?,
+        };
+        if actual != md5.to_ascii_lowercase() {
+            warn!(
+                "Not caching {}, its hash doesn't match",
+                source.as_ref().display()
+            );
+            return Ok(false);
+        }
+
+        // Write to a partial file first, so a cached file is always complete
+        fs::create_dir_all(self.files_dir())?;
+        let partial = path.with_extension(PARTIAL_EXTENSION);
+        let _ = fs::remove_file(&partial);
+        self.place(&source, &partial)?;
+        fs::rename(&partial, &path)?;
+        let _ = touch(path.with_extension(USED_EXTENSION));
+        Ok(true)
+    }
+
+    /// Counts the files in the cache.
+    pub fn stats(&self) -> io::Result<CacheStats> {
+        let mut stats = CacheStats::default();
+        for (_, size, _) in self.entries()? {
+            stats.files += 1;
+            stats.bytes += size;
+        }
+        Ok(stats)
+    }
+
+    /// Removes the least recently used files until the cache is at most `max_bytes` large.
+    ///
+    /// Files that are hardlinked into installs only free space once the installs are removed too.
+    ///
+    /// # Parameters
+    ///
+    /// - `max_bytes`: The size the cache is reduced to, `0` empties it.
+    ///
+    /// # Returns
+    ///
+    /// What was removed, or an error if the cache can't be read.
+    pub fn collect_garbage(&self, max_bytes: u64) -> io::Result<GarbageCollection> {
+        let mut entries = self.entries()?;
+        // Oldest first
+        entries.sort_by_key(|(_, _, modified)| *modified);
+
+        let mut result = GarbageCollection::default();
+        let mut total = entries.iter().map(|(_, size, _)| size).sum::<u64>();
+        for (path, size, _) in &entries {
+            if total <= max_bytes {
+                break;
+            }
+            match fs::remove_file(path) {
+                Ok(()) => {
+                    let _ = fs::remove_file(path.with_extension(USED_EXTENSION));
+                    total -= size;
+                    result.removed_files += 1;
+                    result.removed_bytes += size;
+                }
+                Err(err) => warn!("Unable to remove {}: {}", path.display(), err),
+            }
+        }
+
+        result.remaining = CacheStats {
+            files: entries.len() as u64 - result.removed_files,
+            bytes: total,
+        };
+        info!(
+            "Removed {} files ({} bytes) from the cache",
+            result.removed_files, result.removed_bytes
+        );
+        Ok(result)
+    }
+
+    /// Reflinks or copies `source` to `target`, or hardlinks it if enabled.
+    fn place(&self, source: impl AsRef<Path>, target: impl AsRef<Path>) -> io::Result<()> {
+        if self.hardlinks {
+            return link_or_copy(source, target);
+        }
+        reflink_or_copy(source, target).map(|_| ())
+    }
+
+    /// The directory of the cached files.
+    fn files_dir(&self) -> PathBuf {
+        self.root.join(FILES_DIRECTORY_NAME)
+    }
+
+    /// The path of a cached file, `None` if the hash isn't an MD5 hex string.
+    fn entry_path(&self, file_id: u64, md5: &str) -> Option<PathBuf> {
+        // The hash comes from the API and becomes part of a path, so only accept plain hex
+        if md5.len() != 32 || !md5.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        Some(
+            self.files_dir()
+                .join(format!("{}-{}", file_id, md5.to_ascii_lowercase())),
+        )
+    }
+
+    /// Every cached file with its size and last use, partial files included.
+    fn entries(&self) -> io::Result<Vec<(PathBuf, u64, SystemTime)>> {
+        let read_dir = match fs::read_dir(self.files_dir()) {
+            Ok(read_dir) => read_dir,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err),
+        };
+
+        let mut entries = Vec::new();
+        for entry in read_dir {
+            let entry = entry?;
+            let path = entry.path();
+            let metadata = entry.metadata()?;
+            let is_used_marker = path
+                .extension()
+                .is_some_and(|extension| extension == USED_EXTENSION);
+            if metadata.is_file() && !is_used_marker {
+                // Files cached before uses were recorded fall back to their own time
+                let last_use = fs::metadata(path.with_extension(USED_EXTENSION))
+                    .or(Ok::<_, io::Error>(metadata.clone()))
+                    .and_then(|metadata| metadata.modified())
+                    .unwrap_or(SystemTime::UNIX_EPOCH);
+                entries.push((path, metadata.len(), last_use));
+            }
+        }
+        Ok(entries)
+    }
+}
+
+/// Creates an empty file or sets the modification time of an existing one to now.
+fn touch(path: impl AsRef<Path>) -> io::Result<()> {
+    File::create(path)?.set_modified(SystemTime::now())
+}
+
+/// Hardlinks `source` to `target`, or reflinks or copies it if the filesystem can't link them.
+///
+/// # Parameters
+///
+/// - `source`: The existing file.
+/// - `target`: The new path, must not exist.
+///
+/// # Returns
+///
+/// `Ok` if the file was linked or copied.
+pub fn link_or_copy(source: impl AsRef<Path>, target: impl AsRef<Path>) -> io::Result<()> {
+    if fs::hard_link(&source, &target).is_ok() {
+        return Ok(());
+    }
+    reflink_or_copy(&source, &target).map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curseforge_api::bytes_to_hex_string;
+    use md5::{Digest, Md5};
+    use std::error::Error;
+    use std::time::Duration;
+
+    /// A fresh directory below the system temp directory, unique per test.
+    fn test_directory(name: &str) -> Result<PathBuf, Box<dyn Error>> {
+        let directory =
+            std::env::temp_dir().join(format!("download-cache-{}-{}", name, std::process::id()));
+        if directory.exists() {
+            fs::remove_dir_all(&directory)?;
+        }
+        fs::create_dir_all(&directory)?;
+        Ok(directory)
+    }
+
+    fn md5(contents: &[u8]) -> String {
+        bytes_to_hex_string(&Md5::digest(contents))
+    }
+
+    /// Writes a download and adds it to the cache.
+    fn cache_file(
+        cache: &DownloadCache,
+        directory: &Path,
+        file_id: u64,
+        contents: &[u8],
+    ) -> Result<String, Box<dyn Error>> {
+        let download = directory.join(format!("download-{}", file_id));
+        fs::write(&download, contents)?;
+        let md5 = md5(contents);
+        assert!(cache.insert(file_id, &md5, None, &download)?);
+        Ok(md5)
+    }
+
+    #[test]
+    fn insert_only_adds_files_with_the_right_hash() -> Result<(), Box<dyn Error>> {
+        let directory = test_directory("insert")?;
+        let cache = DownloadCache::new(directory.join("cache"));
+        let download = directory.join("download.jar");
+        fs::write(&download, b"mod")?;
+
+        let wrong = cache.insert(1, &md5(b"other"), None, &download)?;
+        let known_wrong = cache.insert(1, &md5(b"mod"), Some(&md5(b"other")), &download)?;
+        let not_hex = cache.insert(1, "../../evil", None, &download)?;
+        let empty_stats = cache.stats()?;
+        let added = cache.insert(1, &md5(b"mod").to_ascii_uppercase(), None, &download)?;
+        let again = cache.insert(1, &md5(b"mod"), Some(&md5(b"mod")), &download)?;
+        let stats = cache.stats()?;
+        let source_kept = download.exists();
+        fs::remove_dir_all(&directory)?;
+
+        assert!(!wrong && !known_wrong && !not_hex);
+        assert_eq!(empty_stats.files, 0);
+        assert!(added && again);
+        assert_eq!((stats.files, stats.bytes), (1, 3));
+        assert!(source_kept);
+        Ok(())
+    }
+
+    #[test]
+    fn restore_copies_intact_files() -> Result<(), Box<dyn Error>> {
+        let directory = test_directory("restore")?;
+        let cache = DownloadCache::new(directory.join("cache"));
+        let md5 = cache_file(&cache, &directory, 1, b"mod")?;
+
+        let missing = cache.restore(2, &md5, None, directory.join("missing.jar"))?;
+        let wrong_size = cache.restore(1, &md5, Some(4), directory.join("wrong-size.jar"))?;
+        let restored = cache.restore(1, &md5, Some(3), directory.join("restored.jar"))?;
+        let contents = fs::read(directory.join("restored.jar"))?;
+        let stats = cache.stats()?;
+        fs::remove_dir_all(&directory)?;
+
+        assert!(!missing && !wrong_size && restored);
+        assert_eq!(contents, b"mod");
+        assert_eq!(stats.files, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn restore_removes_changed_files() -> Result<(), Box<dyn Error>> {
+        let directory = test_directory("changed")?;
+        let cache = DownloadCache::new(directory.join("cache"));
+        let md5 = cache_file(&cache, &directory, 1, b"mod")?;
+        let entry = cache.entry_path(1, &md5).ok_or("no entry path")?;
+        // Same size, other contents, like an edit through a hardlinked install
+        fs::write(&entry, b"MOD")?;
+
+        let restored = cache.restore(1, &md5, Some(3), directory.join("restored.jar"))?;
+        let target_exists = directory.join("restored.jar").exists();
+        let entry_exists = entry.exists() || entry.with_extension(USED_EXTENSION).exists();
+        fs::remove_dir_all(&directory)?;
+
+        assert!(!restored && !target_exists && !entry_exists);
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn restore_only_links_files_if_enabled() -> Result<(), Box<dyn Error>> {
+        use std::os::unix::fs::MetadataExt;
+
+        let directory = test_directory("hardlinks")?;
+        let mut cache = DownloadCache::new(directory.join("cache"));
+        let md5 = cache_file(&cache, &directory, 1, b"mod")?;
+        let entry = cache.entry_path(1, &md5).ok_or("no entry path")?;
+
+        cache.restore(1, &md5, None, directory.join("copied.jar"))?;
+        cache.set_hardlinks(true);
+        cache.restore(1, &md5, None, directory.join("linked.jar"))?;
+        let cached = fs::metadata(&entry)?.ino();
+        let copied = fs::metadata(directory.join("copied.jar"))?.ino();
+        let linked = fs::metadata(directory.join("linked.jar"))?.ino();
+        fs::remove_dir_all(&directory)?;
+
+        assert_ne!(cached, copied);
+        assert_eq!(cached, linked);
+        Ok(())
+    }
+
+    #[test]
+    fn collect_garbage_removes_the_least_recently_used_files() -> Result<(), Box<dyn Error>> {
+        let directory = test_directory("garbage")?;
+        let cache = DownloadCache::new(directory.join("cache"));
+        let mut entries = Vec::new();
+        for (file_id, contents) in [(1, b"first"), (2, b"secon"), (3, b"third")] {
+            let md5 = cache_file(&cache, &directory, file_id, contents)?;
+            entries.push(cache.entry_path(file_id, &md5).ok_or("no entry path")?);
+        }
+        // Use the files in the order 2, 3, 1
+        let now = SystemTime::now();
+        for (entry, age) in entries.iter().zip([10, 30, 20]) {
+            File::options()
+                .write(true)
+                .open(entry.with_extension(USED_EXTENSION))?
+                .set_modified(now - Duration::from_secs(age))?;
+        }
+
+        let collected = cache.collect_garbage(10)?;
+        let remaining = entries
+            .iter()
+            .map(|entry| entry.exists())
+            .collect::<Vec<_>>();
+        let marker_removed = !entries[1].with_extension(USED_EXTENSION).exists();
+        let emptied = cache.collect_garbage(0)?;
+        fs::remove_dir_all(&directory)?;
+
+        assert_eq!((collected.removed_files, collected.removed_bytes), (1, 5));
+        assert_eq!(
+            (collected.remaining.files, collected.remaining.bytes),
+            (2, 10)
+        );
+        assert_eq!(remaining, vec![true, false, true]);
+        assert!(marker_removed);
+        assert_eq!(emptied.removed_files, 2);
+        assert_eq!(emptied.remaining.files, 0);
+        Ok(())
+    }
+}
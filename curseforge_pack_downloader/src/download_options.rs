@@ -0,0 +1,69 @@
+use crate::cancellation::CancellationToken;
+use crate::curseforge_client::CurseforgeClient;
+use crate::download_cache::DownloadCache;
+use crate::download_sources::CdnMirror;
+use crate::manual_downloads::ManualDownloads;
+use crate::mod_rules::ModRules;
+use crate::mod_type::ModPaths;
+use crate::optional_mods::OptionalModsPolicy;
+use std::path::PathBuf;
+
+/// The settings shared by every download of a single processing run.
+///
+/// `CurseforgePackDownloader` builds this from its configuration, so it only needs to be
+/// constructed by hand when calling the lower level functions directly.
+#[derive(Clone)]
+pub struct DownloadOptions {
+    /// The client used for API requests and file downloads.
+    pub client: CurseforgeClient,
+    /// The number of mods that will be downloaded in parallel, `0` for unlimited.
+    pub parallel: u8,
+    /// Whether downloaded mods are validated against the hash provided by the API.
+    pub validate: bool,
+    /// Only files smaller than this value (in bytes) are validated.
+    pub validate_if_size_less_than: Option<u64>,
+    /// The directory in-flight downloads are written to before being moved into the output.
+    pub temp_dir: PathBuf,
+    /// Aborts in-flight downloads and stops scheduling new ones when cancelled.
+    pub cancellation_token: CancellationToken,
+    /// What happens to the mods the manifest doesn't mark as required.
+    pub optional_mods: OptionalModsPolicy,
+    /// Rules that exclude, replace or add mods.
+    pub rules: ModRules,
+    /// Where the files of each project class are installed.
+    pub paths: ModPaths,
+    /// Install as a dedicated server, e.g. a world of the pack becomes the `level-name`
+    /// of `server.properties`.
+    pub server: bool,
+    /// Downloads are taken from and added to this cache, `None` to always download.
+    pub cache: Option<DownloadCache>,
+    /// Reuse the files of the install the output replaces if their size and hash match,
+    /// instead of downloading them again.
+    pub incremental: bool,
+    /// Mods whose authors disabled third-party distribution are picked up from a directory the
+    /// user downloads them into, `None` to download them from the CDN mirrors.
+    pub manual_downloads: Option<ManualDownloads>,
+    /// The mirrors tried in order when the download URL of the API is missing or fails.
+    pub cdn_mirrors: Vec<CdnMirror>,
+}
+
+impl Default for DownloadOptions {
+    fn default() -> Self {
+        Self {
+            client: CurseforgeClient::from_env(),
+            parallel: 16,
+            validate: false,
+            validate_if_size_less_than: None,
+            temp_dir: PathBuf::from("./temp"),
+            cancellation_token: CancellationToken::new(),
+            optional_mods: OptionalModsPolicy::default(),
+            rules: ModRules::default(),
+            paths: ModPaths::default(),
+            server: false,
+            cache: None,
+            incremental: false,
+            manual_downloads: None,
+            cdn_mirrors: CdnMirror::defaults(),
+        }
+    }
+}
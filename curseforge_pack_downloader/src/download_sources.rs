@@ -0,0 +1,203 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::str::FromStr;
+use uri_encode::encode_uri_component;
+
+/// The CDN mirrors tried when the API URL is missing or fails, in order.
+pub const DEFAULT_CDN_MIRRORS: [&str; 2] = [
+    "https://edge.forgecdn.net/files/%FILE_ID_PREFIX%/%FILE_ID_SUFFIX%/%FILE_NAME%",
+    "https://mediafilez.forgecdn.net/files/%FILE_ID_PREFIX%/%FILE_ID_SUFFIX%/%FILE_NAME%",
+];
+
+/// A URL template for a CDN that serves CurseForge files.
+///
+/// Supported variables:
+/// - `%FILE_ID%`: The file ID, e.g. `4567890`.
+/// - `%FILE_ID_PREFIX%`: The file ID without its last three digits, e.g. `4567`.
+/// - `%FILE_ID_SUFFIX%`: The last three digits of the file ID without leading zeros, e.g. `890`.
+/// - `%FILE_NAME%`: The URL encoded file name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CdnMirror {
+    template: String,
+}
+
+impl CdnMirror {
+    /// The mirrors of `DEFAULT_CDN_MIRRORS`.
+    pub fn defaults() -> Vec<Self> {
+        DEFAULT_CDN_MIRRORS
+            .iter()
+            .map(|template| Self {
+                template: template.to_string(),
+            })
+            .collect()
+    }
+
+    /// The URL of a file on this mirror.
+    ///
+    /// # Parameters
+    ///
+    /// - `file_id`: The CurseForge file ID.
+    /// - `file_name`: The file name, it is URL encoded.
+    pub fn url(&self, file_id: u64, file_name: &str) -> String {
+        let (prefix, suffix) = cdn_path(file_id);
+        self.template
+            .replace("%FILE_ID%", &file_id.to_string())
+            .replace("%FILE_ID_PREFIX%", &prefix.to_string())
+            .replace("%FILE_ID_SUFFIX%", &suffix.to_string())
+            .replace("%FILE_NAME%", &encode_uri_component(file_name))
+    }
+}
+
+impl Display for CdnMirror {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.template)
+    }
+}
+
+impl FromStr for CdnMirror {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if !s.starts_with("https://") && !s.starts_with("http://") {
+            return Err(format!(
+                "Invalid CDN mirror '{}', expected an http:// or https:// URL template",
+                s
+            ));
+        }
+        Ok(Self {
+            template: s.to_string(),
+        })
+    }
+}
+
+/// Where a mod file was taken from.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum DownloadSource {
+    /// The download URL the API reported.
+    Api { url: String },
+    /// A `CdnMirror`, tried because the API URL was missing or failed.
+    Mirror { url: String },
+    /// The `DownloadCache`.
+    Cache,
+    /// The watch directory of the `ManualDownloads`.
+    Manual { path: PathBuf },
+    /// The install the output replaces, with an incremental install.
+    Installed,
+}
+
+impl Display for DownloadSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DownloadSource::Api { url } => write!(f, "{}", url),
+            DownloadSource::Mirror { url } => write!(f, "{} (mirror)", url),
+            DownloadSource::Cache => write!(f, "the download cache"),
+            DownloadSource::Manual { path } => write!(f, "{}", path.display()),
+            DownloadSource::Installed => write!(f, "the existing install"),
+        }
+    }
+}
+
+/// The URLs a file is downloaded from, in the order they are tried:
+/// the URL of the API, if it reported one, then every mirror.
+///
+/// # Parameters
+///
+/// - `api_url`: The `downloadUrl` of the file.
+/// - `mirrors`: The CDN mirrors.
+/// - `file_id`: The CurseForge file ID.
+/// - `file_name`: The file name.
+pub(crate) fn download_candidates(
+    api_url: Option<&str>,
+    mirrors: &[CdnMirror],
+    file_id: u64,
+    file_name: &str,
+) -> Vec<DownloadSource> {
+    api_url
+        .map(|url| DownloadSource::Api {
+            url: url.to_string(),
+        })
+        .into_iter()
+        .chain(mirrors.iter().map(|mirror| DownloadSource::Mirror {
+            url: mirror.url(file_id, file_name),
+        }))
+        .collect()
+}
+
+/// Splits a file ID into the two directories of its CDN path, e.g. `4567890` into `4567/890`.
+fn cdn_path(file_id: u64) -> (u64, u64) {
+    (file_id / 1000, file_id % 1000)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_file_ids_of_any_length() {
+        assert_eq!(cdn_path(7), (0, 7));
+        assert_eq!(cdn_path(999), (0, 999));
+        assert_eq!(cdn_path(1000), (1, 0));
+        assert_eq!(cdn_path(4567890), (4567, 890));
+        assert_eq!(cdn_path(4567005), (4567, 5));
+    }
+
+    #[test]
+    fn fills_in_mirror_templates() -> Result<(), String> {
+        let [edge, mediafilez] = DEFAULT_CDN_MIRRORS;
+        let edge = edge.parse::<CdnMirror>()?;
+        assert_eq!(
+            edge.url(4567890, "jei-1.20.1.jar"),
+            "https://edge.forgecdn.net/files/4567/890/jei-1.20.1.jar"
+        );
+        assert_eq!(
+            mediafilez.parse::<CdnMirror>()?.url(7, "My Mod+.jar"),
+            "https://mediafilez.forgecdn.net/files/0/7/My%20Mod%2b.jar"
+        );
+
+        let by_id = "https://mirror.example.com/%FILE_ID%/%FILE_NAME%".parse::<CdnMirror>()?;
+        assert_eq!(
+            by_id.url(1000, "a.jar"),
+            "https://mirror.example.com/1000/a.jar"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_mirrors_that_arent_http_urls() {
+        assert!("ftp://mirror.example.com/%FILE_ID%"
+            .parse::<CdnMirror>()
+            .is_err());
+        assert!("mirror.example.com/%FILE_ID%".parse::<CdnMirror>().is_err());
+    }
+
+    #[test]
+    fn tries_the_api_url_before_the_mirrors() {
+        let candidates = download_candidates(
+            Some("https://api.example.com/a.jar"),
+            &CdnMirror::defaults(),
+            999,
+            "a.jar",
+        );
+        assert_eq!(
+            candidates,
+            vec![
+                DownloadSource::Api {
+                    url: "https://api.example.com/a.jar".to_string()
+                },
+                DownloadSource::Mirror {
+                    url: "https://edge.forgecdn.net/files/0/999/a.jar".to_string()
+                },
+                DownloadSource::Mirror {
+                    url: "https://mediafilez.forgecdn.net/files/0/999/a.jar".to_string()
+                },
+            ]
+        );
+        assert_eq!(download_candidates(None, &[], 1, "a.jar"), Vec::new());
+    }
+}
@@ -0,0 +1,112 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// The seed CurseForge uses for its MurmurHash2 fingerprints.
+const FINGERPRINT_SEED: u32 = 1;
+
+/// Calculates the CurseForge fingerprint of a file, as reported in `fileFingerprint`.
+///
+/// # Parameters
+///
+/// - `path`: The file to fingerprint.
+///
+/// # Returns
+///
+/// The fingerprint, or an error if the file can't be read.
+pub fn file_fingerprint(path: impl AsRef<Path>) -> io::Result<u32> {
+    Ok(fingerprint(&fs::read(path)?))
+}
+
+/// Calculates the CurseForge fingerprint of some bytes.
+///
+/// CurseForge hashes the bytes with MurmurHash2 after removing every tab, line feed,
+/// carriage return and space.
+pub fn fingerprint(bytes: &[u8]) -> u32 {
+    let normalized = bytes
+        .iter()
+        .copied()
+        .filter(|byte| !matches!(byte, b'\t' | b'\n' | b'\r' | b' '))
+        .collect::<Vec<_>>();
+    murmur2(&normalized, FINGERPRINT_SEED)
+}
+
+/// The 32 bit MurmurHash2 of `data`.
+fn murmur2(data: &[u8], seed: u32) -> u32 {
+    const M: u32 = 0x5bd1_e995;
+    const R: u32 = 24;
+
+    // The length is mixed in as 32 bits, like the reference implementation does
+    let mut hash = seed ^ data.len() as u32;
+    let mut chunks = data.chunks_exact(4);
+    for chunk in &mut chunks {
+        let mut k = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        k = k.wrapping_mul(M);
+        k ^= k >> R;
+        k = k.wrapping_mul(M);
+        hash = hash.wrapping_mul(M);
+        hash ^= k;
+    }
+
+    let rest = chunks.remainder();
+    if rest.len() >= 3 {
+        hash ^= u32::from(rest[2]) << 16;
+    }
+    if rest.len() >= 2 {
+        hash ^= u32::from(rest[1]) << 8;
+    }
+    if let Some(first) = rest.first() {
+        hash ^= u32::from(*first);
+        hash = hash.wrapping_mul(M);
+    }
+
+    hash ^= hash >> 13;
+    hash = hash.wrapping_mul(M);
+    hash ^ (hash >> 15)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The verification value SMHasher publishes for the 32 bit MurmurHash2.
+    const SMHASHER_VERIFICATION: u32 = 0x2786_4c1e;
+
+    #[test]
+    fn murmur2_matches_smhasher_verification() {
+        // Hash the keys [], [0], [0, 1], ... with the seed 256 - length,
+        // then hash the concatenated results with seed 0
+        let key = (0..=255u8).collect::<Vec<_>>();
+        let hashes = (0..256usize)
+            .flat_map(|length| murmur2(&key[..length], 256 - length as u32).to_le_bytes())
+            .collect::<Vec<_>>();
+        assert_eq!(murmur2(&hashes, 0), SMHASHER_VERIFICATION);
+    }
+
+    #[test]
+    fn fingerprint_uses_seed_one() {
+        // Values of the reference MurmurHash2 with seed 1, covering every remainder length
+        assert_eq!(fingerprint(b""), 0x5bd1_5e36);
+        assert_eq!(fingerprint(b"a"), 0x2550_b18c);
+        assert_eq!(fingerprint(b"ab"), 0x64e1_50ee);
+        assert_eq!(fingerprint(b"abc"), 0x60a4_fcc1);
+        assert_eq!(fingerprint(b"Hello,world!"), 0x818f_7a84);
+        assert_eq!(
+            fingerprint(b"Thequickbrownfoxjumpsoverthelazydog"),
+            0xdf9f_94f7
+        );
+    }
+
+    #[test]
+    fn fingerprint_ignores_whitespace() {
+        assert_eq!(fingerprint(b" a\tb\r\nc \n"), fingerprint(b"abc"));
+        assert_eq!(fingerprint(b"\t\n\r "), fingerprint(b""));
+        assert_eq!(
+            fingerprint(b"The quick brown fox jumps over the lazy dog"),
+            fingerprint(b"Thequickbrownfoxjumpsoverthelazydog")
+        );
+        // Other control characters and bytes outside ASCII are kept
+        assert_ne!(fingerprint(b"a\x0bb"), fingerprint(b"ab"));
+        assert_ne!(fingerprint(b"a\xa0b"), fingerprint(b"ab"));
+    }
+}
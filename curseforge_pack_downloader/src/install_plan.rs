@@ -0,0 +1,258 @@
+use crate::curseforge_client::CurseforgeClient;
+use crate::manual_downloads::{is_distribution_blocked, website_url};
+use crate::mod_rules::{AppliedRule, ModJobs, ModMetadata, ModRules};
+use crate::mod_type::{ModPaths, ModType};
+use crate::optional_mods::{disabled_path, OptionalModsPolicy};
+use crate::pack_manifest::Manifest;
+use crate::safe_path::sanitize_file_name;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+/// A file that an install would write.
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PlannedFile {
+    /// The path relative to the output root.
+    pub path: PathBuf,
+    /// The size in bytes.
+    pub size: u64,
+}
+
+/// A mod that an install would download.
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PlannedMod {
+    pub project_id: u64,
+    pub file_id: u64,
+    pub name: String,
+    /// The path relative to the output root, e.g. `mods/jei.jar`.
+    pub path: PathBuf,
+    /// The size in bytes, `None` if the API doesn't report it.
+    pub size: Option<u64>,
+    /// Whether the manifest marks the mod as required.
+    pub required: bool,
+    /// The API withholds the download URL, so the mod would be downloaded from the CDN mirrors.
+    pub uses_fallback_url: bool,
+    /// The CurseForge page to download the mod from by hand, set if its author disabled
+    /// third-party distribution.
+    pub manual_download_url: Option<String>,
+}
+
+/// A mod of the manifest that would not be installed.
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SkippedPlannedMod {
+    #[serde(flatten)]
+    pub planned: PlannedMod,
+    /// Why the mod would be left out, e.g. the optional mods policy or a rule.
+    pub reason: String,
+}
+
+/// A mod of the manifest that couldn't be resolved.
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct UnresolvedMod {
+    pub project_id: u64,
+    pub file_id: u64,
+    pub reason: String,
+}
+
+/// What an install would do, see `CurseforgePackDownloader::plan_identifier`.
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct InstallPlan {
+    pub name: String,
+    pub version: Option<String>,
+    pub author: Option<String>,
+    /// Where the pack would be written, e.g. the directory or the archive file.
+    pub output: PathBuf,
+    /// The mods that would be downloaded, sorted by path.
+    pub mods: Vec<PlannedMod>,
+    /// The mods of the manifest that would be left out, sorted by path.
+    pub skipped: Vec<SkippedPlannedMod>,
+    /// The mods that would fail to download.
+    pub unresolved: Vec<UnresolvedMod>,
+    /// The local jars of the rules that would be copied, sorted by path.
+    pub local: Vec<PlannedFile>,
+    /// The rules that would change the mods of the pack.
+    pub rules: Vec<AppliedRule>,
+    /// The override files that would be written, sorted by path.
+    pub overrides: Vec<PlannedFile>,
+}
+
+impl InstallPlan {
+    /// Resolves every mod of a manifest with two batch requests.
+    ///
+    /// # Parameters
+    ///
+    /// - `manifest`: The manifest of the pack.
+    /// - `output`: Where the pack would be written.
+    /// - `overrides`: The override files of the pack archive.
+    /// - `optional_mods`: What happens to the mods the manifest doesn't mark as required.
+    /// - `rules`: The rules that exclude, replace or add mods.
+    /// - `paths`: Where the files of each project class are installed.
+    /// - `client`: The client to use for sending requests.
+    ///
+    /// # Returns
+    ///
+    /// The plan, or an error if a request fails or a local jar can't be read.
+    pub async fn resolve(
+        manifest: &Manifest,
+        output: PathBuf,
+        mut overrides: Vec<PlannedFile>,
+        optional_mods: &OptionalModsPolicy,
+        rules: &ModRules,
+        paths: &ModPaths,
+        client: &CurseforgeClient,
+    ) -> Result<Self, Box<dyn Error>> {
+        let metadata = ModMetadata::fetch(manifest, rules, client).await?;
+        let jobs = ModJobs::new(manifest, optional_mods, rules, &metadata);
+
+        let mut mods = Vec::new();
+        let mut unresolved = Vec::new();
+        for download in &jobs.downloads {
+            match planned_mod(&metadata, paths, download.project_id, download.file_id) {
+                Ok(mut planned) => {
+                    planned.required = download.required;
+                    if download.disabled {
+                        planned.path = disabled_path(&planned.path);
+                    }
+                    mods.push(planned);
+                }
+                Err(reason) => unresolved.push(UnresolvedMod {
+                    project_id: download.project_id,
+                    file_id: download.file_id,
+                    reason,
+                }),
+            }
+        }
+
+        // Mods that would be skipped are only listed if they resolve, it doesn't matter otherwise
+        let required = manifest
+            .files
+            .iter()
+            .map(|item| ((item.project_id as u64, item.file_id as u64), item.required))
+            .collect::<HashMap<_, _>>();
+        let mut skipped = jobs
+            .skipped
+            .iter()
+            .filter_map(|skipped| {
+                let mut planned =
+                    planned_mod(&metadata, paths, skipped.project_id, skipped.file_id).ok()?;
+                planned.required = required
+                    .get(&(skipped.project_id, skipped.file_id))
+                    .copied()
+                    .unwrap_or(true);
+                Some(SkippedPlannedMod {
+                    planned,
+                    reason: skipped.reason.clone(),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut local = Vec::new();
+        for local_mod in &jobs.local {
+            let file_name = local_mod.path.file_name().ok_or_else(|| {
+                format!("Local mod {} has no file name", local_mod.path.display())
+            })?;
+            let mut path = paths.path(&ModType::Mod).join(file_name);
+            if local_mod.disabled {
+                path = disabled_path(&path);
+            }
+            local.push(PlannedFile {
+                path,
+                size: fs::metadata(&local_mod.path)?.len(),
+            });
+        }
+
+        mods.sort_by(|a, b| a.path.cmp(&b.path));
+        skipped.sort_by(|a, b| a.planned.path.cmp(&b.planned.path));
+        local.sort_by(|a, b| a.path.cmp(&b.path));
+        overrides.sort_by(|a, b| a.path.cmp(&b.path));
+
+        Ok(Self {
+            name: manifest.name.clone(),
+            version: manifest.version.clone(),
+            author: manifest.author.clone(),
+            output,
+            mods,
+            skipped,
+            unresolved,
+            local,
+            rules: jobs.applied,
+            overrides,
+        })
+    }
+
+    /// The optional mods of the pack, whether or not they would be installed.
+    pub fn optional_mods(&self) -> impl Iterator<Item = &PlannedMod> {
+        self.mods
+            .iter()
+            .chain(self.skipped.iter().map(|skipped| &skipped.planned))
+            .filter(|planned| !planned.required)
+    }
+
+    /// The total size of the mods that would be downloaded, mods of unknown size count as 0.
+    pub fn download_size(&self) -> u64 {
+        self.mods.iter().filter_map(|planned| planned.size).sum()
+    }
+
+    /// The mods that would have to be downloaded by hand.
+    pub fn manual_downloads(&self) -> impl Iterator<Item = &PlannedMod> {
+        self.mods
+            .iter()
+            .filter(|planned| planned.manual_download_url.is_some())
+    }
+
+    /// The total size of the local jars that would be copied.
+    pub fn local_size(&self) -> u64 {
+        self.local.iter().map(|file| file.size).sum()
+    }
+
+    /// The total size of the override files that would be written.
+    pub fn overrides_size(&self) -> u64 {
+        self.overrides.iter().map(|file| file.size).sum()
+    }
+}
+
+/// Looks up a mod in the fetched details, the error is the reason it can't be installed.
+fn planned_mod(
+    metadata: &ModMetadata,
+    paths: &ModPaths,
+    project_id: u64,
+    file_id: u64,
+) -> Result<PlannedMod, String> {
+    let (project, file) = match (
+        metadata.projects.get(&project_id),
+        metadata.files.get(&file_id),
+    ) {
+        (Some(project), Some(file)) => (project, file),
+        (None, _) => return Err("Project not found".to_string()),
+        (_, None) => return Err("File not found".to_string()),
+    };
+    // Checked the same way the download checks it
+    let file_name = sanitize_file_name(&file.file_name).map_err(|err| err.to_string())?;
+
+    Ok(PlannedMod {
+        project_id,
+        file_id,
+        name: project
+            .name
+            .clone()
+            .unwrap_or_else(|| file_name.to_string()),
+        path: paths
+            .path(project.class_id.as_ref().unwrap_or(&ModType::Mod))
+            .join(file_name),
+        size: file
+            .file_length
+            .and_then(|length| u64::try_from(length).ok())
+            .filter(|length| *length > 0),
+        required: true,
+        uses_fallback_url: file.download_url.is_none(),
+        manual_download_url: is_distribution_blocked(project, file)
+            .then(|| website_url(project, file_id)),
+    })
+}
@@ -0,0 +1,505 @@
+use crate::curseforge_api::{bytes_to_hex_string, md5_file};
+use crate::output_sink::OutputSink;
+use crate::pack_manifest::Manifest;
+use crate::safe_path::sanitize_entry_name;
+use md5::{Digest, Md5};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fmt::Display;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+/// The name of the install record at the root of every install.
+pub const INSTALL_RECORD_FILE_NAME: &str = ".curseforge-pack.json";
+
+/// A file written by an install.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct InstalledFile {
+    /// The `/` separated path relative to the install root.
+    pub path: String,
+    /// The size in bytes.
+    pub size: u64,
+    /// The MD5 hash as a lowercase hex string.
+    pub md5: String,
+}
+
+/// Everything an install wrote, stored as `INSTALL_RECORD_FILE_NAME` in the install.
+///
+/// Used to check an install for missing or modified files and to compare installs.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct InstallRecord {
+    pub name: String,
+    pub version: Option<String>,
+    pub author: Option<String>,
+    /// The installed files, sorted by path.
+    pub files: Vec<InstalledFile>,
+}
+
+/// A difference between an install record and the files on disk, or between two records.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileChange {
+    /// The file is on disk (or in the other record) but not in the record.
+    Added(String),
+    /// The file is in the record but missing on disk (or in the other record).
+    Removed(String),
+    /// The file exists in both places, but its contents differ.
+    Modified(String),
+}
+
+impl Display for FileChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FileChange::Added(path) => write!(f, "+ {}", path),
+            FileChange::Removed(path) => write!(f, "- {}", path),
+            FileChange::Modified(path) => write!(f, "~ {}", path),
+        }
+    }
+}
+
+impl InstallRecord {
+    /// Creates an empty record for the pack described by `manifest`.
+    fn new(manifest: &Manifest) -> Self {
+        Self {
+            name: manifest.name.clone(),
+            version: manifest.version.clone(),
+            author: manifest.author.clone(),
+            files: Vec::new(),
+        }
+    }
+
+    /// Reads the record of an install.
+    ///
+    /// # Parameters
+    ///
+    /// - `install_dir`: The root directory of the install.
+    ///
+    /// # Returns
+    ///
+    /// The record, or an error if the directory has no readable record.
+    pub fn read(install_dir: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        let path = install_dir.as_ref().join(INSTALL_RECORD_FILE_NAME);
+        let file = File::open(&path)
+            .map_err(|err| format!("Unable to read install record {}: {}", path.display(), err))?;
+        Ok(serde_json::from_reader(file)?)
+    }
+
+    /// Checks the files of an install against the record.
+    ///
+    /// The size of every file is compared first, the hash is only calculated if the sizes match.
+    ///
+    /// # Parameters
+    ///
+    /// - `install_dir`: The root directory of the install.
+    /// - `include_untracked`: Also report files on disk that aren't in the record as `Added`.
+    ///
+    /// # Returns
+    ///
+    /// Every difference, sorted by path. An empty list means the install is intact.
+    pub fn verify(
+        &self,
+        install_dir: impl AsRef<Path>,
+        include_untracked: bool,
+    ) -> Result<Vec<FileChange>, Box<dyn Error>> {
+        let install_dir = install_dir.as_ref();
+        let mut changes = Vec::new();
+
+        for file in &self.files {
+            // The record is a plain file in the install, so don't trust its paths either
+            let path = install_dir.join(sanitize_entry_name(&file.path)?);
+            let metadata = match path.metadata() {
+                Ok(metadata) if metadata.is_file() => metadata,
+                _ => {
+                    changes.push(FileChange::Removed(file.path.clone()));
+                    continue;
+                }
+            };
+            if metadata.len() != file.size || md5_file(&path)? != file.md5 {
+                changes.push(FileChange::Modified(file.path.clone()));
+            }
+        }
+
+        if include_untracked {
+            let tracked = self
+                .files
+                .iter()
+                .map(|file| file.path.as_str())
+                .collect::<BTreeSet<_>>();
+            for path in list_files(install_dir)? {
+                if path != INSTALL_RECORD_FILE_NAME && !tracked.contains(path.as_str()) {
+                    changes.push(FileChange::Added(path));
+                }
+            }
+        }
+
+        changes.sort_by(|a, b| change_path(a).cmp(change_path(b)));
+        Ok(changes)
+    }
+
+    /// Compares this record with the record of another install.
+    ///
+    /// # Parameters
+    ///
+    /// - `other`: The record to compare against, e.g. of a newer pack version.
+    ///
+    /// # Returns
+    ///
+    /// The files `other` adds, removes or modifies compared to this record, sorted by path.
+    pub fn diff(&self, other: &InstallRecord) -> Vec<FileChange> {
+        let ours = self
+            .files
+            .iter()
+            .map(|file| (file.path.as_str(), file))
+            .collect::<BTreeMap<_, _>>();
+        let theirs = other
+            .files
+            .iter()
+            .map(|file| (file.path.as_str(), file))
+            .collect::<BTreeMap<_, _>>();
+
+        let mut changes = Vec::new();
+        for (path, file) in &ours {
+            match theirs.get(path) {
+                None => changes.push(FileChange::Removed(path.to_string())),
+                Some(other) if other.md5 != file.md5 || other.size != file.size => {
+                    changes.push(FileChange::Modified(path.to_string()))
+                }
+                Some(_) => {}
+            }
+        }
+        for path in theirs.keys() {
+            if !ours.contains_key(path) {
+                changes.push(FileChange::Added(path.to_string()));
+            }
+        }
+
+        changes.sort_by(|a, b| change_path(a).cmp(change_path(b)));
+        changes
+    }
+}
+
+/// The path a change refers to.
+fn change_path(change: &FileChange) -> &str {
+    match change {
+        FileChange::Added(path) | FileChange::Removed(path) | FileChange::Modified(path) => path,
+    }
+}
+
+/// Converts a relative path to the `/` separated form used in the record.
+fn record_path(relative: &Path) -> String {
+    relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Lists every file below `root` as `/` separated relative paths.
+fn list_files(root: &Path) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut files = Vec::new();
+    let mut pending = vec![root.to_path_buf()];
+
+    while let Some(directory) = pending.pop() {
+        for entry in fs::read_dir(&directory)? {
+            let entry = entry?;
+            let path = entry.path();
+            if entry.file_type()?.is_dir() {
+                pending.push(path);
+            } else {
+                files.push(record_path(path.strip_prefix(root)?));
+            }
+        }
+    }
+
+    Ok(files)
+}
+
+/// Hashes everything that is read through it.
+struct HashingReader<'a> {
+    inner: &'a mut dyn Read,
+    hasher: Md5,
+}
+
+impl Read for HashingReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let bytes_read = self.inner.read(buf)?;
+        self.hasher.update(&buf[..bytes_read]);
+        Ok(bytes_read)
+    }
+}
+
+/// Wraps another sink and writes an `InstallRecord` of every file into the output on finish.
+pub(crate) struct RecordingSink {
+    inner: Box<dyn OutputSink>,
+    record: InstallRecord,
+}
+
+impl RecordingSink {
+    /// Creates a recording sink for the pack described by `manifest`.
+    pub(crate) fn new(inner: Box<dyn OutputSink>, manifest: &Manifest) -> Self {
+        Self {
+            inner,
+            record: InstallRecord::new(manifest),
+        }
+    }
+}
+
+impl OutputSink for RecordingSink {
+    fn write_file(
+        &mut self,
+        relative: &Path,
+        reader: &mut dyn Read,
+    ) -> Result<u64, Box<dyn Error>> {
+        let mut reader = HashingReader {
+            inner: reader,
+            hasher: Md5::new(),
+        };
+        let size = self.inner.write_file(relative, &mut reader)?;
+        self.record.files.push(InstalledFile {
+            path: record_path(relative),
+            size,
+            md5: bytes_to_hex_string(&reader.hasher.finalize()),
+        });
+        Ok(size)
+    }
+
+    fn add_file(
+        &mut self,
+        relative: &Path,
+        source: &Path,
+        known_md5: Option<&str>,
+    ) -> Result<u64, Box<dyn Error>> {
+        // Hash before adding, the source is moved away
+        let md5 = match known_md5 {
+            Some(known_md5) => known_md5.to_ascii_lowercase(),
+            None => md5_file(source)?,
+        };
+        let size = self.inner.add_file(relative, source, known_md5)?;
+        self.record.files.push(InstalledFile {
+            path: record_path(relative),
+            size,
+            md5,
+        });
+        Ok(size)
+    }
+
+    fn create_dir(&mut self, relative: &Path) -> Result<(), Box<dyn Error>> {
+        self.inner.create_dir(relative)
+    }
+
+    fn existing_file(&self, relative: &Path) -> Option<PathBuf> {
+        self.inner.existing_file(relative)
+    }
+
+    fn merged_file(&self, relative: &Path) -> Option<PathBuf> {
+        self.inner.merged_file(relative)
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<PathBuf, Box<dyn Error>> {
+        // Sort for a deterministic record, if a path was written twice the last write wins
+        let mut files = BTreeMap::new();
+        for file in std::mem::take(&mut self.record.files) {
+            files.insert(file.path.clone(), file);
+        }
+        self.record.files = files.into_values().collect();
+
+        let record = serde_json::to_vec_pretty(&self.record)?;
+        self.inner
+            .write_file(Path::new(INSTALL_RECORD_FILE_NAME), &mut record.as_slice())?;
+        self.inner.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output_sink::{DirectorySink, OutputPolicy};
+
+    /// A fresh directory below the system temp directory, unique per test.
+    fn test_directory(name: &str) -> Result<PathBuf, Box<dyn Error>> {
+        let directory =
+            std::env::temp_dir().join(format!("install-record-{}-{}", name, std::process::id()));
+        if directory.exists() {
+            fs::remove_dir_all(&directory)?;
+        }
+        fs::create_dir_all(&directory)?;
+        Ok(directory)
+    }
+
+    fn installed_file(path: &str, contents: &[u8]) -> InstalledFile {
+        InstalledFile {
+            path: path.to_string(),
+            size: contents.len() as u64,
+            md5: bytes_to_hex_string(&Md5::digest(contents)),
+        }
+    }
+
+    fn record(files: Vec<InstalledFile>) -> InstallRecord {
+        InstallRecord {
+            name: "Test".to_string(),
+            version: None,
+            author: None,
+            files,
+        }
+    }
+
+    #[test]
+    fn verify_reports_removed_modified_and_untracked_files() -> Result<(), Box<dyn Error>> {
+        let install = test_directory("verify")?;
+        fs::create_dir_all(install.join("mods"))?;
+        fs::write(install.join("mods").join("unchanged.jar"), b"unchanged")?;
+        fs::write(
+            install.join("mods").join("resized.jar"),
+            b"longer than before",
+        )?;
+        fs::write(install.join("mods").join("edited.jar"), b"edited")?;
+        fs::write(install.join("mods").join("untracked.jar"), b"untracked")?;
+        fs::write(install.join(INSTALL_RECORD_FILE_NAME), b"{}")?;
+
+        let record = record(vec![
+            installed_file("mods/edited.jar", b"origin"),
+            installed_file("mods/removed.jar", b"removed"),
+            installed_file("mods/resized.jar", b"short"),
+            installed_file("mods/unchanged.jar", b"unchanged"),
+        ]);
+        let tracked = record.verify(&install, false);
+        let all = record.verify(&install, true);
+        fs::remove_dir_all(&install)?;
+
+        let expected = vec![
+            FileChange::Modified("mods/edited.jar".to_string()),
+            FileChange::Removed("mods/removed.jar".to_string()),
+            FileChange::Modified("mods/resized.jar".to_string()),
+        ];
+        assert_eq!(tracked?, expected);
+        let mut expected = expected;
+        expected.push(FileChange::Added("mods/untracked.jar".to_string()));
+        assert_eq!(all?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn verify_accepts_an_intact_install() -> Result<(), Box<dyn Error>> {
+        let install = test_directory("intact")?;
+        fs::create_dir_all(install.join("config"))?;
+        fs::write(install.join("config").join("a.toml"), b"a = 1")?;
+
+        let changes =
+            record(vec![installed_file("config/a.toml", b"a = 1")]).verify(&install, true);
+        fs::remove_dir_all(&install)?;
+
+        assert_eq!(changes?, Vec::new());
+        Ok(())
+    }
+
+    #[test]
+    fn verify_rejects_unsafe_record_paths() -> Result<(), Box<dyn Error>> {
+        let install = test_directory("unsafe")?;
+        let changes = record(vec![installed_file("../outside.jar", b"")]).verify(&install, false);
+        fs::remove_dir_all(&install)?;
+
+        assert!(changes.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn diff_compares_two_records() {
+        let old = record(vec![
+            installed_file("config/a.toml", b"a = 1"),
+            installed_file("mods/kept.jar", b"kept"),
+            installed_file("mods/old.jar", b"old"),
+            installed_file("mods/updated.jar", b"version 1"),
+        ]);
+        let new = record(vec![
+            installed_file("config/a.toml", b"a = 1"),
+            installed_file("mods/kept.jar", b"kept"),
+            installed_file("mods/new.jar", b"new"),
+            installed_file("mods/updated.jar", b"version 2"),
+        ]);
+
+        assert_eq!(
+            old.diff(&new),
+            vec![
+                FileChange::Added("mods/new.jar".to_string()),
+                FileChange::Removed("mods/old.jar".to_string()),
+                FileChange::Modified("mods/updated.jar".to_string()),
+            ]
+        );
+        assert_eq!(
+            new.diff(&old),
+            vec![
+                FileChange::Removed("mods/new.jar".to_string()),
+                FileChange::Added("mods/old.jar".to_string()),
+                FileChange::Modified("mods/updated.jar".to_string()),
+            ]
+        );
+        assert_eq!(old.diff(&old), Vec::new());
+    }
+
+    #[test]
+    fn recording_sink_reuses_known_hashes() -> Result<(), Box<dyn Error>> {
+        let directory = test_directory("known-md5")?;
+        let target = directory.join("install");
+        let manifest = Manifest::from_reader(r#"{"name":"Test","files":[]}"#.as_bytes())?;
+        let sink = DirectorySink::new(&target, OutputPolicy::FailIfExists)?;
+        let mut sink = Box::new(RecordingSink::new(Box::new(sink), &manifest));
+
+        let known = directory.join("known.jar");
+        let unknown = directory.join("unknown.jar");
+        fs::write(&known, b"known")?;
+        fs::write(&unknown, b"unknown")?;
+        let known_md5 = installed_file("", b"known").md5.to_ascii_uppercase();
+        sink.add_file(Path::new("mods/known.jar"), &known, Some(&known_md5))?;
+        sink.add_file(Path::new("mods/unknown.jar"), &unknown, None)?;
+        sink.finish()?;
+
+        let record = InstallRecord::read(&target);
+        fs::remove_dir_all(&directory)?;
+
+        assert_eq!(
+            record?.files,
+            vec![
+                installed_file("mods/known.jar", b"known"),
+                installed_file("mods/unknown.jar", b"unknown"),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn recording_sink_keeps_the_last_write_of_a_path() -> Result<(), Box<dyn Error>> {
+        let directory = test_directory("recording")?;
+        let target = directory.join("install");
+        let manifest = Manifest::from_reader(r#"{"name":"Test","files":[]}"#.as_bytes())?;
+        let sink = DirectorySink::new(&target, OutputPolicy::FailIfExists)?;
+        let mut sink = Box::new(RecordingSink::new(Box::new(sink), &manifest));
+
+        let path = Path::new("server.properties");
+        sink.write_file(path, &mut b"motd=first".as_slice())?;
+        sink.write_file(Path::new("b.txt"), &mut b"b".as_slice())?;
+        sink.write_file(path, &mut b"motd=second".as_slice())?;
+        sink.finish()?;
+
+        let record = InstallRecord::read(&target);
+        let contents = fs::read(target.join(path));
+        let changes = record
+            .as_ref()
+            .map_err(|err| err.to_string())
+            .and_then(|record| record.verify(&target, true).map_err(|err| err.to_string()));
+        fs::remove_dir_all(&directory)?;
+
+        assert_eq!(
+            record?.files,
+            vec![
+                installed_file("b.txt", b"b"),
+                installed_file("server.properties", b"motd=second"),
+            ]
+        );
+        assert_eq!(contents?, b"motd=second");
+        assert_eq!(changes?, Vec::new());
+        Ok(())
+    }
+}
@@ -3,23 +3,61 @@
 #![deny(clippy::panic)]
 #![deny(unused_must_use)]
 
+use crate::cancellation::{cancellable, CancellationToken};
+use crate::cleanup_guard::CleanupGuard;
 use crate::curseforge_api::get_project;
-use crate::mod_type::ModType;
+use crate::curseforge_client::CurseforgeClient;
+use crate::download_cache::DownloadCache;
+use crate::download_options::DownloadOptions;
+use crate::download_sources::CdnMirror;
+use crate::install_plan::InstallPlan;
+use crate::manual_downloads::ManualDownloads;
+use crate::mod_rules::ModRules;
+use crate::mod_type::{ModPaths, ModType};
+use crate::modpack_version_file::{PackVersionSelector, ReleaseType};
+use crate::optional_mods::OptionalModsPolicy;
+use crate::output_sink::{OutputKind, OutputPolicy};
+use crate::pack_identifier::PackIdentifier;
 use crate::pack_manifest::Manifest;
+use crate::process_handle::ProcessHandle;
+use crate::process_result::ProcessResult;
+use crate::progress::{ProcessEvent, ProgressReporter};
+use crate::safe_path::{sanitize_file_name, UnsafePathError};
 use log::{error, info};
-use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use std::error::Error;
 use std::fmt::Display;
 use std::path::{Path, PathBuf};
 
+pub mod cancellation;
+pub mod cleanup_guard;
+pub mod content_handler;
 pub mod curseforge_api;
+pub mod curseforge_client;
+pub mod download_cache;
+pub mod download_options;
+pub mod download_sources;
+pub mod fingerprint;
+pub mod install_plan;
+pub mod install_record;
+pub mod manual_downloads;
 pub mod mod_file;
+pub mod mod_rules;
 pub mod mod_type;
+pub mod optional_mods;
+pub mod output_sink;
 pub mod pack_archive;
+pub mod pack_identifier;
 pub mod pack_manifest;
+pub mod process_handle;
+pub mod process_result;
+pub mod progress;
 pub mod project_structure;
 pub mod modpack_version_file;
+pub mod response_validation;
+pub mod safe_path;
+pub mod search;
+pub mod server_properties;
 
 /// This module contains types and functionalities related to processing and downloading
 /// Curseforge mod packs. It includes a structure `CurseforgePackDownloader` which
@@ -37,8 +75,13 @@ pub struct CurseforgePackDownloader {
     /// - **PACK_AUTHOR** *(the primary author of the modpack)*
     /// - **TIME** *(the current time in ms - this can be great for creating unique paths)*
     output_dir: PathBuf,
-    /// Where the temporary files will be stored during processing
-    /// This file will be removed once the program finishes
+    /// Whether the pack is written as a plain directory, a zip archive or a zstd tarball.
+    /// Archive extensions are appended to the output path if missing.
+    output_kind: OutputKind,
+    /// What happens when something already exists at the resolved output path.
+    output_policy: OutputPolicy,
+    /// Where in-flight downloads will be stored during processing
+    /// This directory will be removed once the program finishes, even if processing fails
     temp_directory: PathBuf,
 
     /// This will validate the downloaded mods based on the provided hash. (Note: this can take significantly longer)
@@ -51,18 +94,50 @@ pub struct CurseforgePackDownloader {
 
     /// This will only attempt to validate files where the file size is less than this value (in bytes)
     validate_if_size_less_than: Option<u64>,
-    pack_version: Option<u64>,
+    /// Which version of the pack `process_id` downloads.
+    pack_version: PackVersionSelector,
+    /// The least stable release channel the latest version may be picked from, `None` for any.
+    release_channel: Option<ReleaseType>,
+    /// What happens to the mods the manifest doesn't mark as required.
+    optional_mods: OptionalModsPolicy,
+    /// Rules that exclude, replace or add mods.
+    rules: ModRules,
+    /// Where the files of each project class are installed, relative to the output root.
+    paths: ModPaths,
+    /// Whether the pack is installed as a dedicated server.
+    server: bool,
+    /// Where downloaded files are cached between runs, `None` to always download.
+    cache: Option<DownloadCache>,
+    /// Whether intact files of an existing install at the output path are reused.
+    incremental: bool,
+    /// Where mods without third-party distribution are picked up from, `None` to try the CDN mirrors.
+    manual_downloads: Option<ManualDownloads>,
+    /// The mirrors tried when the API withholds a download URL or its download fails.
+    cdn_mirrors: Vec<CdnMirror>,
+
+    /// Cancelling this token aborts in-flight requests, stops scheduling new downloads
+    /// and makes `process_id`/`process_file` return a `Cancelled` error.
+    cancellation_token: CancellationToken,
+
+    /// The client used for every API request and download.
+    /// Defaults to the key in the `CURSEFORGE_API_KEY` environment variable, see `set_api_key`.
+    client: CurseforgeClient,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct ProcessProgressResponse {
+    /// The stage the process is currently in.
     pub stage: ProcessStage,
+    /// The overall progress, from 0 to 1.
     pub progress: f32,
+    /// A human readable description of the event.
     pub message: String,
+    /// The event that caused this update.
+    pub event: ProcessEvent,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
 pub enum ProcessStage {
     ExtractingArchive,
@@ -91,20 +166,31 @@ impl Default for CurseforgePackDownloader {
 }
 
 impl CurseforgePackDownloader {
-    /// Creates a new `CurseforgePackDownloader` instance with the specified API key.
-    ///
-    /// # Parameters
+    /// Creates a new `CurseforgePackDownloader` instance.
     ///
-    /// - `curseforge_api_key`: A string slice representing the API key to authenticate requests
-    ///   to the Curseforge API.
+    /// The API key is read from the `CURSEFORGE_API_KEY` environment variable if it is set.
+    /// Use `set_api_key` or `set_client` to pass the key explicitly.
     pub fn new() -> Self {
         Self {
             output_dir: PathBuf::from("./"),
+            output_kind: OutputKind::Directory,
+            output_policy: OutputPolicy::FailIfExists,
             temp_directory: PathBuf::from("./temp"),
             validate: false,
             parallel_downloads: 16,
             validate_if_size_less_than: None,
-            pack_version: None,
+            pack_version: PackVersionSelector::Latest,
+            release_channel: None,
+            optional_mods: OptionalModsPolicy::IncludeAll,
+            rules: ModRules::default(),
+            paths: ModPaths::default(),
+            server: false,
+            cache: None,
+            incremental: false,
+            manual_downloads: None,
+            cdn_mirrors: CdnMirror::defaults(),
+            cancellation_token: CancellationToken::new(),
+            client: CurseforgeClient::from_env(),
         }
     }
 
@@ -141,6 +227,40 @@ impl CurseforgePackDownloader {
         self
     }
 
+    /// Sets the kind of artifact the finalized pack is written as.
+    ///
+    /// Archive outputs are deterministic, packing the same pack version twice
+    /// yields a byte-identical artifact.
+    ///
+    /// # Parameters
+    ///
+    /// - `output_kind`: A directory, a zip archive or a zstd compressed tarball.
+    ///
+    /// # Returns
+    ///
+    /// Returns a mutable reference to the current `CurseforgePackDownloader` instance.
+    pub fn set_output_kind(&mut self, output_kind: OutputKind) -> &mut Self {
+        self.output_kind = output_kind;
+        self
+    }
+
+    /// Sets what happens when something already exists at the output path.
+    ///
+    /// The policy is checked against the resolved path, after the variables of the
+    /// output directory are replaced, and before anything is downloaded.
+    ///
+    /// # Parameters
+    ///
+    /// - `output_policy`: Fail (the default), overwrite, merge into or back up the existing output.
+    ///
+    /// # Returns
+    ///
+    /// Returns a mutable reference to the current `CurseforgePackDownloader` instance.
+    pub fn set_output_policy(&mut self, output_policy: OutputPolicy) -> &mut Self {
+        self.output_policy = output_policy;
+        self
+    }
+
     /// Configures whether to validate the downloaded mods based on their hash.
     ///
     /// # Parameters
@@ -183,8 +303,222 @@ impl CurseforgePackDownloader {
         self
     }
 
+    /// Sets the file ID of the pack version `process_id` downloads.
+    ///
+    /// # Parameters
+    ///
+    /// - `pack_version`: The file ID of the pack version.
+    ///
+    /// # Returns
+    ///
+    /// Returns a mutable reference to the current `CurseforgePackDownloader` instance.
     pub fn set_pack_version(&mut self, pack_version: u64) -> &mut Self {
-        self.pack_version = Some(pack_version);
+        self.pack_version = PackVersionSelector::FileId(pack_version);
+        self
+    }
+
+    /// Sets which version of the pack `process_id` downloads, by file ID, display name or
+    /// as the latest (release) version. Defaults to the latest version.
+    ///
+    /// # Parameters
+    ///
+    /// - `pack_version`: The version to download.
+    ///
+    /// # Returns
+    ///
+    /// Returns a mutable reference to the current `CurseforgePackDownloader` instance.
+    pub fn set_pack_version_selector(&mut self, pack_version: PackVersionSelector) -> &mut Self {
+        self.pack_version = pack_version;
+        self
+    }
+
+    /// Sets the least stable release channel the latest version may be picked from,
+    /// e.g. `ReleaseType::Beta` allows release and beta versions.
+    ///
+    /// # Parameters
+    ///
+    /// - `release_channel`: The channel, or `None` to allow every channel.
+    ///
+    /// # Returns
+    ///
+    /// Returns a mutable reference to the current `CurseforgePackDownloader` instance.
+    pub fn set_release_channel(&mut self, release_channel: Option<ReleaseType>) -> &mut Self {
+        self.release_channel = release_channel;
+        self
+    }
+
+    /// Sets what happens to the mods the manifest doesn't mark as required.
+    /// By default every optional mod is installed.
+    ///
+    /// # Parameters
+    ///
+    /// - `optional_mods`: The policy for optional mods, required mods are always installed.
+    ///
+    /// # Returns
+    ///
+    /// Returns a mutable reference to the current `CurseforgePackDownloader` instance.
+    pub fn set_optional_mods(&mut self, optional_mods: OptionalModsPolicy) -> &mut Self {
+        self.optional_mods = optional_mods;
+        self
+    }
+
+    /// Sets rules that exclude, replace or add mods, e.g. read with `ModRules::from_file`.
+    /// Every rule that is applied is reported with a `ProcessEvent::RuleApplied` event.
+    ///
+    /// # Parameters
+    ///
+    /// - `rules`: The rules, applied after the optional mods policy.
+    ///
+    /// # Returns
+    ///
+    /// Returns a mutable reference to the current `CurseforgePackDownloader` instance.
+    pub fn set_mod_rules(&mut self, rules: ModRules) -> &mut Self {
+        self.rules = rules;
+        self
+    }
+
+    /// Sets where the files of each project class are installed, e.g. `plugins` for Bukkit
+    /// plugins. Classes without a configured directory use `ModTypeExt::to_path`.
+    ///
+    /// # Parameters
+    ///
+    /// - `paths`: The directories relative to the output root.
+    ///
+    /// # Returns
+    ///
+    /// Returns a mutable reference to the current `CurseforgePackDownloader` instance.
+    pub fn set_mod_paths(&mut self, paths: ModPaths) -> &mut Self {
+        self.paths = paths;
+        self
+    }
+
+    /// Sets whether the pack is installed as a dedicated server.
+    /// A world of the pack is then set as the `level-name` of `server.properties`.
+    ///
+    /// # Parameters
+    ///
+    /// - `server`: Whether to install as a server.
+    ///
+    /// # Returns
+    ///
+    /// Returns a mutable reference to the current `CurseforgePackDownloader` instance.
+    pub fn set_server_mode(&mut self, server: bool) -> &mut Self {
+        self.server = server;
+        self
+    }
+
+    /// Sets the cache downloads are shared through, e.g. `DownloadCache::default_location`.
+    /// Mods and pack archives that are in the cache aren't downloaded again.
+    ///
+    /// # Parameters
+    ///
+    /// - `cache`: The cache, `None` to always download.
+    ///
+    /// # Returns
+    ///
+    /// Returns a mutable reference to the current `CurseforgePackDownloader` instance.
+    pub fn set_download_cache(&mut self, cache: Option<DownloadCache>) -> &mut Self {
+        self.cache = cache;
+        self
+    }
+
+    /// Sets whether the files of an existing install at the output path are reused.
+    /// Files whose size and hash match are reported with a `ProcessEvent::ModSkipped` event
+    /// instead of being downloaded again.
+    ///
+    /// # Parameters
+    ///
+    /// - `incremental`: Whether to reuse intact files.
+    ///
+    /// # Returns
+    ///
+    /// Returns a mutable reference to the current `CurseforgePackDownloader` instance.
+    pub fn set_incremental(&mut self, incremental: bool) -> &mut Self {
+        self.incremental = incremental;
+        self
+    }
+
+    /// Sets where mods whose authors disabled third-party distribution are picked up from.
+    ///
+    /// Such mods are reported with a `ProcessEvent::ManualDownloadRequired` event that links to
+    /// their CurseForge page. The files the user downloads into the watch directory are
+    /// recognized by their fingerprint, while the other mods are downloaded.
+    /// Without it, those mods are downloaded from the CDN mirrors, which may fail.
+    ///
+    /// # Parameters
+    ///
+    /// - `manual_downloads`: The watch directory and timeout, `None` to try the CDN mirrors.
+    ///
+    /// # Returns
+    ///
+    /// Returns a mutable reference to the current `CurseforgePackDownloader` instance.
+    pub fn set_manual_downloads(&mut self, manual_downloads: Option<ManualDownloads>) -> &mut Self {
+        self.manual_downloads = manual_downloads;
+        self
+    }
+
+    /// Sets the CDN mirrors a mod file is downloaded from when the API withholds its download URL
+    /// or the download from that URL fails.
+    ///
+    /// The mirrors are tried in order and a file is only accepted if it matches the hash the API
+    /// reports. The mirror every file was taken from is part of the `ProcessResult`.
+    /// Defaults to `CdnMirror::defaults()`.
+    ///
+    /// # Parameters
+    ///
+    /// - `cdn_mirrors`: The mirror URL templates, empty to only use the URL of the API.
+    ///
+    /// # Returns
+    ///
+    /// Returns a mutable reference to the current `CurseforgePackDownloader` instance.
+    pub fn set_cdn_mirrors(&mut self, cdn_mirrors: Vec<CdnMirror>) -> &mut Self {
+        self.cdn_mirrors = cdn_mirrors;
+        self
+    }
+
+    /// Sets the token used to cancel processing.
+    ///
+    /// Once cancelled, in-flight requests are aborted, no new downloads are scheduled,
+    /// partial files are removed and the running `process_id`/`process_file` call
+    /// returns a `Cancelled` error. Any previous install is left untouched.
+    ///
+    /// # Parameters
+    ///
+    /// - `cancellation_token`: The token to watch.
+    ///
+    /// # Returns
+    ///
+    /// Returns a mutable reference to the current `CurseforgePackDownloader` instance.
+    pub fn set_cancellation_token(&mut self, cancellation_token: CancellationToken) -> &mut Self {
+        self.cancellation_token = cancellation_token;
+        self
+    }
+
+    /// Sets the CurseForge API key used to authenticate requests.
+    ///
+    /// # Parameters
+    ///
+    /// - `api_key`: The API key, see `curseforge_client::resolve_api_key` for resolving it at runtime.
+    ///
+    /// # Returns
+    ///
+    /// Returns a mutable reference to the current `CurseforgePackDownloader` instance.
+    pub fn set_api_key(&mut self, api_key: impl Into<String>) -> &mut Self {
+        self.client = CurseforgeClient::new(api_key);
+        self
+    }
+
+    /// Sets the client used for API requests and downloads, e.g. to share a connection pool.
+    ///
+    /// # Parameters
+    ///
+    /// - `client`: The client to use.
+    ///
+    /// # Returns
+    ///
+    /// Returns a mutable reference to the current `CurseforgePackDownloader` instance.
+    pub fn set_client(&mut self, client: CurseforgeClient) -> &mut Self {
+        self.client = client;
         self
     }
 
@@ -193,38 +527,62 @@ impl CurseforgePackDownloader {
     /// # Parameters
     ///
     /// - `id`: The ID of the mod pack to process.
+    /// - `on_progress`: Called for every `ProcessEvent`, together with the overall progress.
     ///
     /// # Returns
     ///
-    /// Returns a `Result` containing the `Manifest` of the processed pack or an error if
-    /// processing fails.
-    pub async fn process_id<F>(&self, id: u64, on_progress: F) -> Result<Manifest, Box<dyn Error>>
+    /// Returns a `Result` containing the `ProcessResult` of the run, e.g. the resolved output
+    /// path and every mod, or an error if processing fails. If the cancellation token is cancelled, the error is `Cancelled`.
+    pub async fn process_id<F>(
+        &self,
+        id: u64,
+        on_progress: F,
+    ) -> Result<ProcessResult, Box<dyn Error>>
     where
         F: Fn(ProcessProgressResponse) + 'static + Send + Sync,
     {
-        on_progress(ProcessProgressResponse {
+        let reporter = ProgressReporter::new(on_progress);
+        reporter.emit(ProcessEvent::StageStarted {
             stage: ProcessStage::DownloadingArchive,
-            progress: 0.0,
-            message: format!("Downloading Archive from curseforge for project: {}", id),
         });
-        let project = get_project(id, &Client::new()).await?;
-        if !project
-            .data
-            .class_id
-            .unwrap_or(ModType::Mod)
-            .eq(&ModType::ModPack)
-        {
-            error!("The project is not a modpack");
-            return Err("The project is not a modpack".into());
-        }
+        self.process_project(id, &self.pack_version, &reporter)
+            .await
+    }
 
-        let file = curseforge_api::download_latest_pack_archive(
-            id,
-            self.pack_version,
-            &self.temp_directory,
-        )
-        .await?;
-        self.process_file(file, on_progress).await
+    /// Downloads and processes a mod pack given by ID, slug or CurseForge URL.
+    ///
+    /// A `/files/<fileId>` link selects that version of the pack,
+    /// otherwise the version is chosen like in `process_id`.
+    ///
+    /// # Parameters
+    ///
+    /// - `identifier`: The mod pack to process.
+    /// - `on_progress`: Called for every `ProcessEvent`, together with the overall progress.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result` containing the `ProcessResult` of the run, e.g. the resolved output
+    /// path and every mod, or an error if processing fails. If the cancellation token is cancelled, the error is `Cancelled`.
+    pub async fn process_identifier<F>(
+        &self,
+        identifier: &PackIdentifier,
+        on_progress: F,
+    ) -> Result<ProcessResult, Box<dyn Error>>
+    where
+        F: Fn(ProcessProgressResponse) + 'static + Send + Sync,
+    {
+        let reporter = ProgressReporter::new(on_progress);
+        reporter.emit(ProcessEvent::StageStarted {
+            stage: ProcessStage::DownloadingArchive,
+        });
+        let (id, file_id) =
+            cancellable(&self.cancellation_token, identifier.resolve(&self.client)).await??;
+        let pack_version = match file_id {
+            Some(file_id) => PackVersionSelector::FileId(file_id),
+            None => self.pack_version.clone(),
+        };
+
+        self.process_project(id, &pack_version, &reporter).await
     }
 
     /// Processes a mod pack archive from the given file path.
@@ -232,74 +590,233 @@ impl CurseforgePackDownloader {
     /// # Parameters
     ///
     /// - `file`: A reference to the path of the file to process.
+    /// - `on_progress`: Called for every `ProcessEvent`, together with the overall progress.
     ///
     /// # Returns
     ///
-    /// Returns a `Result` containing the `Manifest` of the processed pack or an error if
-    /// processing fails.
+    /// Returns a `Result` containing the `ProcessResult` of the run, e.g. the resolved output
+    /// path and every mod, or an error if processing fails. If the cancellation token is cancelled, the error is `Cancelled`.
     pub async fn process_file<F>(
         &self,
         file: impl AsRef<Path>,
         on_progress: F,
-    ) -> Result<Manifest, Box<dyn Error>>
+    ) -> Result<ProcessResult, Box<dyn Error>>
     where
         F: Fn(ProcessProgressResponse) + 'static + Send + Sync,
     {
-        // Initiate processing of the archive file
-        // This function extracts the archive, validates the contents, and downloads needed mods
-        let (tmp, manifest) = match pack_archive::process_archive(
+        self.process_file_with_reporter(file, &ProgressReporter::new(on_progress))
+            .await
+    }
+
+    /// Like `process_id`, but returns a handle instead of taking a progress callback.
+    ///
+    /// Subscribe to the handle for a `Stream` of progress events, then await
+    /// `ProcessHandle::result` to run the processing.
+    ///
+    /// # Parameters
+    ///
+    /// - `id`: The ID of the mod pack to process.
+    ///
+    /// # Returns
+    ///
+    /// A `ProcessHandle` for the run. Nothing happens until its result is awaited.
+    pub fn process_id_stream(&self, id: u64) -> ProcessHandle<'_> {
+        ProcessHandle::new(move |on_progress| self.process_id(id, on_progress))
+    }
+
+    /// Plans the install of a mod pack given by ID, slug or CurseForge URL, without installing it.
+    ///
+    /// Only the pack archive is downloaded, into the temp directory, to read its manifest.
+    /// The mods are resolved with the API but not downloaded, and nothing is written to the output.
+    ///
+    /// # Parameters
+    ///
+    /// - `identifier`: The mod pack to plan.
+    ///
+    /// # Returns
+    ///
+    /// The `InstallPlan` of the pack, or an error if the pack or the API can't be reached.
+    pub async fn plan_identifier(
+        &self,
+        identifier: &PackIdentifier,
+    ) -> Result<InstallPlan, Box<dyn Error>> {
+        let (id, file_id) =
+            cancellable(&self.cancellation_token, identifier.resolve(&self.client)).await??;
+        let pack_version = match file_id {
+            Some(file_id) => PackVersionSelector::FileId(file_id),
+            None => self.pack_version.clone(),
+        };
+
+        let _temp_guard = CleanupGuard::new(&self.temp_directory);
+        let (file, _) = self.download_project_archive(id, &pack_version).await?;
+        self.plan_file(file).await
+    }
+
+    /// Plans the install of a mod pack archive, without installing it.
+    ///
+    /// The mods are resolved with the API but not downloaded, and nothing is written to the output.
+    ///
+    /// # Parameters
+    ///
+    /// - `file`: The path of the pack archive.
+    ///
+    /// # Returns
+    ///
+    /// The `InstallPlan` of the pack, or an error if the archive or the API can't be read.
+    pub async fn plan_file(&self, file: impl AsRef<Path>) -> Result<InstallPlan, Box<dyn Error>> {
+        pack_archive::plan_archive(
             file,
-            self.parallel_downloads,
-            self.validate,
-            self.validate_if_size_less_than,
-            &self.temp_directory,
-            on_progress,
+            |manifest| {
+                Ok(self
+                    .output_kind
+                    .target_path(Self::get_parsed_path(&self.output_dir, manifest)?))
+            },
+            &self.download_options(),
         )
         .await
-        {
-            // Continue if processing is successful, store results
-            Ok(output) => output,
-            // Return error if processing fails
-            Err(err) => {
-                return Err(err);
-            }
-        };
+    }
 
-        // Parse the output directory path using the manifest data
-        let output = Self::get_parsed_path(&self.output_dir, &manifest);
+    /// Like `process_identifier`, but returns a handle instead of taking a progress callback.
+    ///
+    /// Subscribe to the handle for a `Stream` of progress events, then await
+    /// `ProcessHandle::result` to run the processing.
+    ///
+    /// # Parameters
+    ///
+    /// - `identifier`: The mod pack to process.
+    ///
+    /// # Returns
+    ///
+    /// A `ProcessHandle` for the run. Nothing happens until its result is awaited.
+    pub fn process_identifier_stream(&self, identifier: PackIdentifier) -> ProcessHandle<'_> {
+        ProcessHandle::new(move |on_progress| async move {
+            self.process_identifier(&identifier, on_progress).await
+        })
+    }
 
-        // Define paths for 'mods' and 'overrides' directories within temporary files
-        let overrides = tmp.join("overrides");
+    /// Like `process_file`, but returns a handle instead of taking a progress callback.
+    ///
+    /// Subscribe to the handle for a `Stream` of progress events, then await
+    /// `ProcessHandle::result` to run the processing.
+    ///
+    /// # Parameters
+    ///
+    /// - `file`: The path of the file to process.
+    ///
+    /// # Returns
+    ///
+    /// A `ProcessHandle` for the run. Nothing happens until its result is awaited.
+    pub fn process_file_stream(&self, file: impl AsRef<Path>) -> ProcessHandle<'_> {
+        let file = file.as_ref().to_path_buf();
+        ProcessHandle::new(move |on_progress| self.process_file(file, on_progress))
+    }
 
-        // Copy contents from 'mods' and 'overrides' directories to the final output location
-        match pack_archive::copy_to_output(overrides, output) {
-            // Log successful copy operation
-            Ok(output) => {
-                info!("Pack copied to {}", output.display());
-            }
-            // Log and return error if copying fails
-            Err(err) => {
-                error!("Unable to copy pack to output: {}", err);
-                return Err(err);
-            }
-        };
+    /// Downloads the archive of a mod pack project and processes it, reporting progress to
+    /// an existing reporter. The `DownloadingArchive` stage must already be started.
+    async fn process_project(
+        &self,
+        id: u64,
+        pack_version: &PackVersionSelector,
+        reporter: &ProgressReporter,
+    ) -> Result<ProcessResult, Box<dyn Error>> {
+        // Remove the downloaded archive even if processing never starts
+        let _temp_guard = CleanupGuard::new(&self.temp_directory);
+
+        let (file, file_id) = self.download_project_archive(id, pack_version).await?;
+        reporter.set_pack_file(id, file_id);
+        reporter.emit(ProcessEvent::StageFinished {
+            stage: ProcessStage::DownloadingArchive,
+        });
 
-        // Attempt to remove the temporary directory used during processing
-        match std::fs::remove_dir_all(tmp) {
-            // Log successful removal of the temporary directory
-            Ok(_) => {
-                info!("Temp directory removed");
-            }
-            // Log and return error if removal fails
-            Err(err) => {
-                error!("Unable to remove temp directory: {}", err);
-                // Return detailed error message for failure to clean up
-                return Err(format!("Unable to remove temp directory: {}", err).into());
-            }
+        self.process_file_with_reporter(file, reporter).await
+    }
+
+    /// Checks that a project is a mod pack and downloads the archive of the selected version
+    /// into the temp directory.
+    async fn download_project_archive(
+        &self,
+        id: u64,
+        pack_version: &PackVersionSelector,
+    ) -> Result<(PathBuf, Option<u64>), Box<dyn Error>> {
+        let project =
+            cancellable(&self.cancellation_token, get_project(id, &self.client)).await??;
+        if !project
+            .data
+            .class_id
+            .unwrap_or(ModType::Mod)
+            .eq(&ModType::ModPack)
+        {
+            error!("The project is not a modpack");
+            return Err("The project is not a modpack".into());
         }
 
-        // Return the manifest object, indicating successful completion of the process
-        Ok(manifest)
+        curseforge_api::download_pack_archive(
+            id,
+            pack_version,
+            self.release_channel,
+            &self.temp_directory,
+            &self.client,
+            self.cache.as_ref(),
+            &self.cancellation_token,
+        )
+        .await
+    }
+
+    /// Processes a mod pack archive, reporting progress to an existing reporter.
+    /// This lets `process_id` report the archive download and the processing as one run.
+    async fn process_file_with_reporter(
+        &self,
+        file: impl AsRef<Path>,
+        reporter: &ProgressReporter,
+    ) -> Result<ProcessResult, Box<dyn Error>> {
+        // The temp directory only holds in-flight downloads and is removed on every exit path
+        let _temp_guard = CleanupGuard::new(&self.temp_directory);
+
+        // Initiate processing of the archive file
+        // This function reads the manifest, streams the overrides into the output directory,
+        // and downloads needed mods
+        let output_dir = self.output_dir.clone();
+        let output_kind = self.output_kind;
+        let output_policy = self.output_policy;
+        let (output, manifest) = pack_archive::process_archive(
+            file,
+            // Parse the output directory path using the manifest data
+            move |manifest| {
+                output_sink::create_sink(
+                    output_kind,
+                    Self::get_parsed_path(&output_dir, manifest)?,
+                    output_policy,
+                )
+            },
+            &self.download_options(),
+            reporter,
+        )
+        .await?;
+
+        info!("Pack written to {}", output.display());
+
+        // Combine the output, the manifest and everything reported during the run
+        reporter.finish(output, manifest)
+    }
+
+    /// Builds the download settings shared by every download of a run.
+    fn download_options(&self) -> DownloadOptions {
+        DownloadOptions {
+            client: self.client.clone(),
+            parallel: self.parallel_downloads,
+            validate: self.validate,
+            validate_if_size_less_than: self.validate_if_size_less_than,
+            temp_dir: self.temp_directory.clone(),
+            cancellation_token: self.cancellation_token.clone(),
+            optional_mods: self.optional_mods.clone(),
+            rules: self.rules.clone(),
+            paths: self.paths.clone(),
+            server: self.server,
+            cache: self.cache.clone(),
+            incremental: self.incremental,
+            manual_downloads: self.manual_downloads.clone(),
+            cdn_mirrors: self.cdn_mirrors.clone(),
+        }
     }
 
     /// Parses the `output_dir` path by replacing placeholders with metadata from a specified manifest.
@@ -321,8 +838,13 @@ impl CurseforgePackDownloader {
     /// A `PathBuf` with placeholders in the `output_dir` replaced by their corresponding values from the manifest data.
     ///
     /// # Errors
-    /// Errors are logged, and default values are used to handle them gracefully.
-    fn get_parsed_path(path: impl AsRef<Path>, manifest: &Manifest) -> PathBuf {
+    /// Returns an `UnsafePathError` if a manifest value that is used isn't a plain file name,
+    /// e.g. a pack named `../..`, since the manifest comes from an untrusted pack.
+    /// Other errors are logged, and default values are used to handle them gracefully.
+    fn get_parsed_path(
+        path: impl AsRef<Path>,
+        manifest: &Manifest,
+    ) -> Result<PathBuf, UnsafePathError> {
         use log::error;
         use std::time::{Duration, SystemTime};
 
@@ -357,12 +879,66 @@ impl CurseforgePackDownloader {
             .as_millis();
 
         // Replace placeholders in the path string with actual values from the manifest.
-        path_string = path_string.replace("%PACK_NAME%", pack_name.as_str());
-        path_string = path_string.replace("%PACK_VERSION%", pack_version.as_str());
-        path_string = path_string.replace("%PACK_AUTHOR%", author.as_str());
+        // The values come from the pack, so each has to stay a single path component
+        for (placeholder, value) in [
+            ("%PACK_NAME%", pack_name),
+            ("%PACK_VERSION%", pack_version),
+            ("%PACK_AUTHOR%", author),
+        ] {
+            if path_string.contains(placeholder) {
+                let value = if value.is_empty() {
+                    value.as_str()
+                } else {
+                    sanitize_file_name(value)?
+                };
+                path_string = path_string.replace(placeholder, value);
+            }
+        }
         path_string = path_string.replace("%TIME%", &time.to_string());
 
         // Convert the final string back to a PathBuf for use in the program.
-        PathBuf::from(path_string)
+        Ok(PathBuf::from(path_string))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(name: &str, version: &str) -> Result<Manifest, Box<dyn Error>> {
+        let json = serde_json::json!({ "name": name, "version": version, "files": [] });
+        Manifest::from_reader(json.to_string().as_bytes())
+    }
+
+    #[test]
+    fn substitutes_pack_metadata_into_the_output_path() -> Result<(), Box<dyn Error>> {
+        let path = CurseforgePackDownloader::get_parsed_path(
+            Path::new("packs").join("%PACK_NAME% %PACK_VERSION%%PACK_AUTHOR%"),
+            &manifest("My Pack", "1.2.3")?,
+        )?;
+        assert_eq!(path, Path::new("packs").join("My Pack 1.2.3"));
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_pack_metadata_that_leaves_the_output_directory() -> Result<(), Box<dyn Error>> {
+        let output = Path::new("packs").join("%PACK_NAME%");
+        for name in ["..", "../..", "a/../../b", "C:\\Windows", "/etc", "CON"] {
+            assert!(
+                CurseforgePackDownloader::get_parsed_path(&output, &manifest(name, "1")?).is_err(),
+                "{}",
+                name
+            );
+        }
+        let versioned = Path::new("packs").join("pack-%PACK_VERSION%");
+        assert!(
+            CurseforgePackDownloader::get_parsed_path(&versioned, &manifest("Pack", "../1")?)
+                .is_err()
+        );
+        // Values that aren't used don't matter
+        assert!(
+            CurseforgePackDownloader::get_parsed_path(&versioned, &manifest("../..", "1")?).is_ok()
+        );
+        Ok(())
     }
 }
@@ -0,0 +1,160 @@
+use crate::fingerprint::file_fingerprint;
+use crate::mod_file::ModFileItem;
+use crate::project_structure::ProjectData;
+use log::{info, warn};
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// How often the watch directory is scanned for new files.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Picks up the mods whose authors disabled third-party distribution from a directory the user
+/// downloads them into by hand, e.g. `~/Downloads`.
+///
+/// The API returns no download URL for such mods. Without `ManualDownloads`, the downloader
+/// guesses a CDN URL instead, which may fail.
+#[derive(Clone, Debug)]
+pub struct ManualDownloads {
+    /// The directory that is watched for the downloaded files, subdirectories are ignored.
+    pub watch_dir: PathBuf,
+    /// How long to wait for a file before the mod fails, `None` to wait until cancelled.
+    pub timeout: Option<Duration>,
+}
+
+/// A mod that has to be downloaded by hand.
+pub(crate) struct ManualDownload<'a> {
+    pub(crate) file_name: &'a str,
+    pub(crate) size: Option<u64>,
+    pub(crate) fingerprint: u32,
+}
+
+impl ManualDownloads {
+    /// Creates a `ManualDownloads` that watches `watch_dir` without a timeout.
+    pub fn new(watch_dir: impl AsRef<Path>) -> Self {
+        Self {
+            watch_dir: watch_dir.as_ref().to_path_buf(),
+            timeout: None,
+        }
+    }
+
+    /// Waits until a file with the fingerprint of `download` shows up in the watch directory
+    /// and copies it to `target`. The file in the watch directory is left in place.
+    ///
+    /// Files that already are in the watch directory count, so mods downloaded for an
+    /// earlier run are picked up right away.
+    ///
+    /// # Parameters
+    ///
+    /// - `download`: The mod to wait for.
+    /// - `target`: Where the file is copied to, e.g. the temp directory.
+    ///
+    /// # Returns
+    ///
+    /// The path of the file in the watch directory, or an error if the timeout passed.
+    pub(crate) async fn wait_for(
+        &self,
+        download: &ManualDownload<'_>,
+        target: &Path,
+    ) -> Result<PathBuf, Box<dyn Error>> {
+        let started = Instant::now();
+        // Files that didn't match, keyed with their size and modification time,
+        // so a file that changes is checked again
+        let mut checked = HashSet::new();
+        loop {
+            if let Some(found) = self.find(download, &mut checked) {
+                info!("Found {} at {}", download.file_name, found.display());
+                fs::copy(&found, target)?;
+                return Ok(found);
+            }
+            if self
+                .timeout
+                .is_some_and(|timeout| started.elapsed() >= timeout)
+            {
+                return Err(format!(
+                    "{} wasn't downloaded into {} in time",
+                    download.file_name,
+                    self.watch_dir.display()
+                )
+                .into());
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    /// Scans the watch directory once for a file matching `download`.
+    fn find(
+        &self,
+        download: &ManualDownload<'_>,
+        checked: &mut HashSet<(PathBuf, u64, SystemTime)>,
+    ) -> Option<PathBuf> {
+        let entries = match fs::read_dir(&self.watch_dir) {
+            Ok(entries) => entries,
+            Err(err) => {
+                warn!("Unable to read {}: {}", self.watch_dir.display(), err);
+                return None;
+            }
+        };
+
+        for entry in entries.flatten() {
+            let metadata = match entry.metadata() {
+                Ok(metadata) if metadata.is_file() => metadata,
+                _ => continue,
+            };
+            // Only fingerprint files of the right size, e.g. not a download that is still running
+            if download.size.is_some_and(|size| size != metadata.len()) {
+                continue;
+            }
+            let key = (
+                entry.path(),
+                metadata.len(),
+                metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+            );
+            if checked.contains(&key) {
+                continue;
+            }
+            match file_fingerprint(entry.path()) {
+                Ok(fingerprint) if fingerprint == download.fingerprint => {
+                    return Some(entry.path())
+                }
+                Ok(_) => {
+                    checked.insert(key);
+                }
+                Err(err) => warn!("Unable to read {}: {}", entry.path().display(), err),
+            }
+        }
+        None
+    }
+}
+
+/// Whether the author of a mod disabled third-party distribution, so the API withholds
+/// the download URL of its files.
+pub fn is_distribution_blocked(project: &ProjectData, file: &ModFileItem) -> bool {
+    project.allow_mod_distribution == Some(false) || file.download_url.is_none()
+}
+
+/// The CurseForge page a mod file can be downloaded from by hand.
+///
+/// # Parameters
+///
+/// - `project`: The project of the mod.
+/// - `file_id`: The file to download.
+///
+/// # Returns
+///
+/// The URL of the file page, or of the project page if the API didn't report a website URL.
+pub fn website_url(project: &ProjectData, file_id: u64) -> String {
+    match project
+        .links
+        .as_ref()
+        .and_then(|links| links.website_url.as_deref())
+    {
+        Some(website_url) => format!("{}/files/{}", website_url.trim_end_matches('/'), file_id),
+        None => format!(
+            "https://www.curseforge.com/projects/{}",
+            project.id.unwrap_or_default()
+        ),
+    }
+}
@@ -0,0 +1,841 @@
+use crate::curseforge_api::{get_mod_files, get_projects};
+use crate::curseforge_client::CurseforgeClient;
+use crate::manual_downloads::is_distribution_blocked;
+use crate::mod_file::ModFileItem;
+use crate::optional_mods::{ModAction, OptionalModsPolicy};
+use crate::pack_manifest::Manifest;
+use crate::project_structure::ProjectData;
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::Display;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+/// Rules that change which mods of a pack are installed, read from a JSON file.
+///
+/// ```json
+/// {
+///   "exclude": [{ "slug": "xaeros-minimap" }, { "fileName": "*optifine*.jar" }],
+///   "replace": [
+///     { "match": { "projectId": 238222 }, "with": { "projectId": 123, "fileId": 456 } },
+///     { "match": { "slug": "some-mod" }, "with": { "path": "forks/some-mod-fork.jar" } }
+///   ],
+///   "add": [{ "projectId": 789, "fileId": 1011 }, { "path": "extra/server-utils.jar" }]
+/// }
+/// ```
+///
+/// Exclusions are checked before replacements, the first matching rule wins.
+/// Local jars are installed into `mods`.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ModRules {
+    /// Mods of the pack that are not installed.
+    #[serde(default)]
+    pub exclude: Vec<ModMatcher>,
+    /// Mods of the pack that are installed from somewhere else.
+    #[serde(default)]
+    pub replace: Vec<ModReplacement>,
+    /// Mods that are installed on top of the pack.
+    #[serde(default)]
+    pub add: Vec<ModSource>,
+}
+
+/// Matches mods of a pack, every field that is set must match.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ModMatcher {
+    pub project_id: Option<u64>,
+    /// The slug of the project, compared case-insensitively.
+    pub slug: Option<String>,
+    /// A glob for the file name, `*` matches any text and `?` any single character.
+    pub file_name: Option<String>,
+}
+
+/// Installs a different file in place of the mods that match.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ModReplacement {
+    #[serde(rename = "match")]
+    pub matcher: ModMatcher,
+    pub with: ModSource,
+}
+
+/// Where a replacement or added mod comes from.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum ModSource {
+    /// A file on CurseForge.
+    #[serde(rename_all = "camelCase")]
+    CurseForge { project_id: u64, file_id: u64 },
+    /// A jar on disk, relative paths are relative to the rules file.
+    Local { path: PathBuf },
+}
+
+/// A rule that changed the mods of an install.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum AppliedRule {
+    /// A mod of the pack was not installed.
+    Excluded {
+        project_id: u64,
+        file_id: u64,
+        rule: String,
+    },
+    /// A mod of the pack was installed from somewhere else.
+    Replaced {
+        project_id: u64,
+        file_id: u64,
+        rule: String,
+        with: String,
+    },
+    /// A mod was installed on top of the pack.
+    Added { source: String },
+}
+
+impl Display for ModMatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut parts = Vec::new();
+        if let Some(project_id) = self.project_id {
+            parts.push(format!("project {}", project_id));
+        }
+        if let Some(slug) = &self.slug {
+            parts.push(format!("slug '{}'", slug));
+        }
+        if let Some(file_name) = &self.file_name {
+            parts.push(format!("file name '{}'", file_name));
+        }
+        write!(f, "{}", parts.join(" and "))
+    }
+}
+
+impl Display for ModSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ModSource::CurseForge {
+                project_id,
+                file_id,
+            } => write!(f, "project {} file {}", project_id, file_id),
+            ModSource::Local { path } => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+impl Display for AppliedRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AppliedRule::Excluded {
+                project_id, rule, ..
+            } => write!(f, "Excluded project {} (matched {})", project_id, rule),
+            AppliedRule::Replaced {
+                project_id,
+                rule,
+                with,
+                ..
+            } => write!(
+                f,
+                "Replaced project {} with {} (matched {})",
+                project_id, with, rule
+            ),
+            AppliedRule::Added { source } => write!(f, "Added {}", source),
+        }
+    }
+}
+
+/// What the rules know about a mod of the pack.
+struct ModInfo<'a> {
+    project_id: u64,
+    slug: Option<&'a str>,
+    file_name: Option<&'a str>,
+}
+
+impl ModMatcher {
+    /// Checks whether a mod matches, a matcher without any field matches nothing.
+    fn matches(&self, info: &ModInfo) -> bool {
+        if self.project_id.is_none() && self.slug.is_none() && self.file_name.is_none() {
+            return false;
+        }
+        self.project_id
+            .is_none_or(|project_id| project_id == info.project_id)
+            && self.slug.as_deref().is_none_or(|slug| {
+                info.slug
+                    .is_some_and(|info_slug| info_slug.eq_ignore_ascii_case(slug))
+            })
+            && self.file_name.as_deref().is_none_or(|pattern| {
+                info.file_name
+                    .is_some_and(|file_name| glob_matches(pattern, file_name))
+            })
+    }
+
+    /// Whether matching needs the project or file details from the API.
+    fn needs_metadata(&self) -> bool {
+        self.slug.is_some() || self.file_name.is_some()
+    }
+}
+
+impl ModRules {
+    /// Reads rules from a JSON file.
+    ///
+    /// Relative paths of local jars are resolved against the directory of the file.
+    ///
+    /// # Parameters
+    ///
+    /// - `path`: The path of the rules file.
+    ///
+    /// # Returns
+    ///
+    /// The rules, or an error if the file can't be read, is malformed, has a rule without any
+    /// condition or refers to a local jar that doesn't exist.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        let path = path.as_ref();
+        let file = File::open(path)
+            .map_err(|err| format!("Unable to read rules file {}: {}", path.display(), err))?;
+        let mut rules: ModRules = serde_json::from_reader(file)
+            .map_err(|err| format!("Invalid rules file {}: {}", path.display(), err))?;
+
+        let matchers = rules
+            .exclude
+            .iter()
+            .chain(rules.replace.iter().map(|replacement| &replacement.matcher));
+        for matcher in matchers {
+            if matcher.project_id.is_none() && matcher.slug.is_none() && matcher.file_name.is_none()
+            {
+                return Err(format!(
+                    "Invalid rules file {}: every rule needs a projectId, slug or fileName",
+                    path.display()
+                )
+                .into());
+            }
+        }
+
+        let base = path.parent().unwrap_or(Path::new(""));
+        let sources = rules
+            .replace
+            .iter_mut()
+            .map(|replacement| &mut replacement.with)
+            .chain(rules.add.iter_mut());
+        for source in sources {
+            if let ModSource::Local { path: jar } = source {
+                *jar = base.join(&jar);
+                if !jar.is_file() {
+                    return Err(format!("Local mod {} not found", jar.display()).into());
+                }
+            }
+        }
+
+        Ok(rules)
+    }
+
+    /// Whether there are no rules at all.
+    pub fn is_empty(&self) -> bool {
+        self.exclude.is_empty() && self.replace.is_empty() && self.add.is_empty()
+    }
+
+    /// Whether any rule matches by slug or file name, which needs the details from the API.
+    pub(crate) fn needs_metadata(&self) -> bool {
+        self.exclude.iter().any(ModMatcher::needs_metadata)
+            || self
+                .replace
+                .iter()
+                .any(|replacement| replacement.matcher.needs_metadata())
+    }
+
+    /// The CurseForge files the rules install, as `(project_id, file_id)`.
+    fn curseforge_sources(&self) -> impl Iterator<Item = (u64, u64)> + '_ {
+        self.replace
+            .iter()
+            .map(|replacement| &replacement.with)
+            .chain(&self.add)
+            .filter_map(|source| match source {
+                ModSource::CurseForge {
+                    project_id,
+                    file_id,
+                } => Some((*project_id, *file_id)),
+                ModSource::Local { .. } => None,
+            })
+    }
+}
+
+/// The project and file details of mods, fetched with the batch endpoints.
+#[derive(Default)]
+pub(crate) struct ModMetadata {
+    pub(crate) projects: HashMap<u64, ProjectData>,
+    pub(crate) files: HashMap<u64, ModFileItem>,
+}
+
+impl ModMetadata {
+    /// Fetches the details of every mod of the manifest and every CurseForge file of the rules.
+    pub(crate) async fn fetch(
+        manifest: &Manifest,
+        rules: &ModRules,
+        client: &CurseforgeClient,
+    ) -> Result<Self, Box<dyn Error>> {
+        let ids = manifest
+            .files
+            .iter()
+            .map(|file| (file.project_id as u64, file.file_id as u64))
+            .chain(rules.curseforge_sources())
+            .collect::<Vec<_>>();
+        let project_ids = ids
+            .iter()
+            .map(|(project_id, _)| *project_id)
+            .collect::<Vec<_>>();
+        let file_ids = ids.iter().map(|(_, file_id)| *file_id).collect::<Vec<_>>();
+
+        let projects = get_projects(&project_ids, client)
+            .await?
+            .into_iter()
+            .filter_map(|project| Some((u64::try_from(project.id?).ok()?, project)))
+            .collect();
+        let files = get_mod_files(&file_ids, client)
+            .await?
+            .into_iter()
+            .filter_map(|file| Some((u64::try_from(file.id?).ok()?, file)))
+            .collect();
+        Ok(Self { projects, files })
+    }
+
+    /// Whether a mod has to be downloaded by hand, `false` if its details weren't fetched.
+    pub(crate) fn is_distribution_blocked(&self, download: &ModDownload) -> bool {
+        match (
+            self.projects.get(&download.project_id),
+            self.files.get(&download.file_id),
+        ) {
+            (Some(project), Some(file)) => is_distribution_blocked(project, file),
+            _ => false,
+        }
+    }
+}
+
+/// A CurseForge mod that will be downloaded.
+pub(crate) struct ModDownload {
+    pub(crate) project_id: u64,
+    pub(crate) file_id: u64,
+    pub(crate) required: bool,
+    /// Install with `DISABLED_SUFFIX` appended to the file name.
+    pub(crate) disabled: bool,
+}
+
+/// A local jar of the rules that will be copied into `mods`.
+pub(crate) struct LocalMod {
+    pub(crate) path: PathBuf,
+    /// Install with `DISABLED_SUFFIX` appended to the file name.
+    pub(crate) disabled: bool,
+}
+
+/// A mod of the pack that will not be installed.
+pub(crate) struct SkippedMod {
+    pub(crate) project_id: u64,
+    pub(crate) file_id: u64,
+    pub(crate) reason: String,
+}
+
+/// Every mod an install will download, skip or copy, after the optional mods policy and the
+/// rules have been applied.
+#[derive(Default)]
+pub(crate) struct ModJobs {
+    pub(crate) downloads: Vec<ModDownload>,
+    pub(crate) skipped: Vec<SkippedMod>,
+    /// Local jars that are copied into `mods`.
+    pub(crate) local: Vec<LocalMod>,
+    pub(crate) applied: Vec<AppliedRule>,
+}
+
+impl ModJobs {
+    /// Applies the optional mods policy and the rules to the mods of a manifest.
+    ///
+    /// # Parameters
+    ///
+    /// - `manifest`: The manifest of the pack.
+    /// - `optional_mods`: What happens to the mods the manifest doesn't mark as required.
+    /// - `rules`: The rules, exclusions are checked before replacements.
+    /// - `metadata`: The details rules matching by slug or file name are checked against.
+    pub(crate) fn new(
+        manifest: &Manifest,
+        optional_mods: &OptionalModsPolicy,
+        rules: &ModRules,
+        metadata: &ModMetadata,
+    ) -> Self {
+        let mut jobs = ModJobs::default();
+
+        for item in &manifest.files {
+            let project_id = item.project_id as u64;
+            let file_id = item.file_id as u64;
+
+            let disabled = match optional_mods.action(item) {
+                ModAction::Install => false,
+                ModAction::InstallDisabled => true,
+                ModAction::Skip => {
+                    jobs.skipped.push(SkippedMod {
+                        project_id,
+                        file_id,
+                        reason: "Optional mod excluded".to_string(),
+                    });
+                    continue;
+                }
+            };
+
+            let info = ModInfo {
+                project_id,
+                slug: metadata
+                    .projects
+                    .get(&project_id)
+                    .and_then(|project| project.slug.as_deref()),
+                file_name: metadata
+                    .files
+                    .get(&file_id)
+                    .map(|file| file.file_name.as_str()),
+            };
+
+            if let Some(matcher) = rules.exclude.iter().find(|matcher| matcher.matches(&info)) {
+                jobs.applied.push(AppliedRule::Excluded {
+                    project_id,
+                    file_id,
+                    rule: matcher.to_string(),
+                });
+                jobs.skipped.push(SkippedMod {
+                    project_id,
+                    file_id,
+                    reason: format!("Excluded by rule ({})", matcher),
+                });
+                continue;
+            }
+
+            let replacement = rules
+                .replace
+                .iter()
+                .find(|replacement| replacement.matcher.matches(&info));
+            match replacement {
+                None => jobs.downloads.push(ModDownload {
+                    project_id,
+                    file_id,
+                    required: item.required,
+                    disabled,
+                }),
+                Some(replacement) => {
+                    jobs.applied.push(AppliedRule::Replaced {
+                        project_id,
+                        file_id,
+                        rule: replacement.matcher.to_string(),
+                        with: replacement.with.to_string(),
+                    });
+                    jobs.push_source(&replacement.with, item.required, disabled);
+                }
+            }
+        }
+
+        for source in &rules.add {
+            jobs.applied.push(AppliedRule::Added {
+                source: source.to_string(),
+            });
+            jobs.push_source(source, true, false);
+        }
+
+        for rule in &jobs.applied {
+            info!("{}", rule);
+        }
+        jobs
+    }
+
+    /// Queues a mod from a replacement or an added mod.
+    fn push_source(&mut self, source: &ModSource, required: bool, disabled: bool) {
+        match source {
+            ModSource::CurseForge {
+                project_id,
+                file_id,
+            } => self.downloads.push(ModDownload {
+                project_id: *project_id,
+                file_id: *file_id,
+                required,
+                disabled,
+            }),
+            ModSource::Local { path } => self.local.push(LocalMod {
+                path: path.clone(),
+                disabled,
+            }),
+        }
+    }
+}
+
+/// Checks whether `text` matches a glob `pattern`, ignoring ASCII case.
+///
+/// `*` matches any text, including none, and `?` matches any single character.
+fn glob_matches(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.to_ascii_lowercase().chars().collect::<Vec<_>>();
+    let text = text.to_ascii_lowercase().chars().collect::<Vec<_>>();
+
+    // Greedy matching that backtracks to the last `*`
+    let (mut p, mut t) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = star {
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pack_manifest::ModItem;
+    use serde_json::json;
+    use std::collections::BTreeSet;
+    use std::fs;
+
+    /// A fresh directory below the system temp directory, unique per test.
+    fn test_directory(name: &str) -> Result<PathBuf, Box<dyn Error>> {
+        let directory =
+            std::env::temp_dir().join(format!("mod-rules-{}-{}", name, std::process::id()));
+        if directory.exists() {
+            fs::remove_dir_all(&directory)?;
+        }
+        fs::create_dir_all(&directory)?;
+        Ok(directory)
+    }
+
+    /// A manifest with the given `(project_id, file_id, required)` mods.
+    fn manifest(files: &[(i64, i64, bool)]) -> Manifest {
+        Manifest {
+            name: "Test pack".to_string(),
+            version: None,
+            author: None,
+            minecraft: None,
+            files: files
+                .iter()
+                .map(|(project_id, file_id, required)| ModItem {
+                    project_id: *project_id,
+                    file_id: *file_id,
+                    required: *required,
+                })
+                .collect(),
+            overrides: None,
+        }
+    }
+
+    /// Metadata with the given `(project_id, slug, file_id, file_name)` mods.
+    fn metadata(mods: &[(u64, &str, u64, &str)]) -> Result<ModMetadata, Box<dyn Error>> {
+        let mut metadata = ModMetadata::default();
+        for (project_id, slug, file_id, file_name) in mods {
+            metadata.projects.insert(
+                *project_id,
+                serde_json::from_value(json!({ "id": project_id, "slug": slug }))?,
+            );
+            metadata.files.insert(
+                *file_id,
+                serde_json::from_value(
+                    json!({ "id": file_id, "fileName": file_name, "hashes": [] }),
+                )?,
+            );
+        }
+        Ok(metadata)
+    }
+
+    /// Parses rules from JSON without touching the disk.
+    fn rules(value: serde_json::Value) -> Result<ModRules, Box<dyn Error>> {
+        Ok(serde_json::from_value(value)?)
+    }
+
+    fn download_ids(jobs: &ModJobs) -> Vec<(u64, u64, bool)> {
+        jobs.downloads
+            .iter()
+            .map(|download| (download.project_id, download.file_id, download.disabled))
+            .collect()
+    }
+
+    fn skipped_ids(jobs: &ModJobs) -> Vec<u64> {
+        jobs.skipped
+            .iter()
+            .map(|skipped| skipped.project_id)
+            .collect()
+    }
+
+    #[test]
+    fn glob_matches_literal_text_ignoring_case() {
+        assert!(glob_matches("jei.jar", "jei.jar"));
+        assert!(glob_matches("JEI.jar", "jei.JAR"));
+        assert!(!glob_matches("jei.jar", "jei.jar.disabled"));
+        assert!(!glob_matches("jei.jar", "jei"));
+        assert!(glob_matches("", ""));
+        assert!(!glob_matches("", "jei.jar"));
+    }
+
+    #[test]
+    fn glob_star_matches_any_text() {
+        assert!(glob_matches("*", ""));
+        assert!(glob_matches("*", "anything.jar"));
+        assert!(glob_matches(
+            "*optifine*.jar",
+            "OptiFine_1.20.1_HD_U_I6.jar"
+        ));
+        assert!(glob_matches("*optifine*.jar", "optifine.jar"));
+        assert!(!glob_matches("*optifine*.jar", "optifine.zip"));
+        assert!(glob_matches("a*b*c", "a-b-b-c"));
+        assert!(glob_matches("a**c", "abc"));
+        assert!(!glob_matches("a*b*c", "a-c-b"));
+    }
+
+    #[test]
+    fn glob_question_mark_matches_one_character() {
+        assert!(glob_matches("jei-?.jar", "jei-1.jar"));
+        assert!(!glob_matches("jei-?.jar", "jei-.jar"));
+        assert!(!glob_matches("jei-?.jar", "jei-12.jar"));
+        assert!(glob_matches("jei-?*.jar", "jei-12.jar"));
+    }
+
+    #[test]
+    fn matcher_requires_every_field_that_is_set() -> Result<(), Box<dyn Error>> {
+        let info = ModInfo {
+            project_id: 1,
+            slug: Some("jei"),
+            file_name: Some("jei-1.20.jar"),
+        };
+        let matcher =
+            |value| -> Result<ModMatcher, Box<dyn Error>> { Ok(serde_json::from_value(value)?) };
+
+        assert!(matcher(json!({ "projectId": 1 }))?.matches(&info));
+        assert!(matcher(json!({ "slug": "JEI" }))?.matches(&info));
+        assert!(matcher(json!({ "projectId": 1, "fileName": "jei-*" }))?.matches(&info));
+        assert!(!matcher(json!({ "projectId": 1, "slug": "other" }))?.matches(&info));
+        assert!(!matcher(json!({}))?.matches(&info));
+
+        // Without metadata, slug and file name rules can't match
+        let bare = ModInfo {
+            project_id: 1,
+            slug: None,
+            file_name: None,
+        };
+        assert!(matcher(json!({ "projectId": 1 }))?.matches(&bare));
+        assert!(!matcher(json!({ "slug": "jei" }))?.matches(&bare));
+        assert!(!matcher(json!({ "fileName": "*" }))?.matches(&bare));
+        Ok(())
+    }
+
+    #[test]
+    fn exclusions_win_over_replacements() -> Result<(), Box<dyn Error>> {
+        let manifest = manifest(&[(1, 10, true), (2, 20, true)]);
+        let rules = rules(json!({
+            "exclude": [{ "slug": "jei" }],
+            "replace": [
+                { "match": { "projectId": 1 }, "with": { "projectId": 100, "fileId": 1000 } },
+                { "match": { "projectId": 2 }, "with": { "projectId": 200, "fileId": 2000 } }
+            ]
+        }))?;
+        let metadata = metadata(&[(1, "jei", 10, "jei.jar"), (2, "jade", 20, "jade.jar")])?;
+
+        let jobs = ModJobs::new(
+            &manifest,
+            &OptionalModsPolicy::IncludeAll,
+            &rules,
+            &metadata,
+        );
+
+        assert_eq!(skipped_ids(&jobs), vec![1]);
+        assert_eq!(download_ids(&jobs), vec![(200, 2000, false)]);
+        assert_eq!(
+            jobs.applied,
+            vec![
+                AppliedRule::Excluded {
+                    project_id: 1,
+                    file_id: 10,
+                    rule: "slug 'jei'".to_string(),
+                },
+                AppliedRule::Replaced {
+                    project_id: 2,
+                    file_id: 20,
+                    rule: "project 2".to_string(),
+                    with: "project 200 file 2000".to_string(),
+                },
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn first_matching_replacement_wins() -> Result<(), Box<dyn Error>> {
+        let manifest = manifest(&[(1, 10, true)]);
+        let rules = rules(json!({
+            "replace": [
+                { "match": { "fileName": "*.jar" }, "with": { "projectId": 100, "fileId": 1000 } },
+                { "match": { "projectId": 1 }, "with": { "projectId": 200, "fileId": 2000 } }
+            ]
+        }))?;
+        let metadata = metadata(&[(1, "jei", 10, "jei.jar")])?;
+
+        let jobs = ModJobs::new(
+            &manifest,
+            &OptionalModsPolicy::IncludeAll,
+            &rules,
+            &metadata,
+        );
+
+        assert_eq!(download_ids(&jobs), vec![(100, 1000, false)]);
+        Ok(())
+    }
+
+    #[test]
+    fn optional_mods_policy_applies_before_the_rules() -> Result<(), Box<dyn Error>> {
+        let manifest = manifest(&[(1, 10, false), (2, 20, false), (3, 30, true)]);
+        let rules = rules(json!({
+            "exclude": [{ "projectId": 1 }],
+            "add": [{ "projectId": 400, "fileId": 4000 }]
+        }))?;
+        let policy = OptionalModsPolicy::Allowlist(BTreeSet::from([2]));
+
+        let jobs = ModJobs::new(&manifest, &policy, &rules, &ModMetadata::default());
+
+        // The optional mod 1 is skipped by the policy, so the exclusion never applies
+        assert_eq!(skipped_ids(&jobs), vec![1]);
+        assert_eq!(jobs.skipped[0].reason, "Optional mod excluded");
+        assert_eq!(
+            download_ids(&jobs),
+            vec![(2, 20, false), (3, 30, false), (400, 4000, false)]
+        );
+        assert_eq!(
+            jobs.applied,
+            vec![AppliedRule::Added {
+                source: "project 400 file 4000".to_string(),
+            }]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn replacements_of_disabled_mods_stay_disabled() -> Result<(), Box<dyn Error>> {
+        let manifest = manifest(&[(1, 10, false), (2, 20, false), (3, 30, true)]);
+        let rules = rules(json!({
+            "replace": [
+                { "match": { "projectId": 1 }, "with": { "projectId": 100, "fileId": 1000 } },
+                { "match": { "projectId": 2 }, "with": { "path": "fork.jar" } },
+                { "match": { "projectId": 3 }, "with": { "path": "required.jar" } }
+            ],
+            "add": [{ "path": "extra.jar" }]
+        }))?;
+
+        let jobs = ModJobs::new(
+            &manifest,
+            &OptionalModsPolicy::Disabled,
+            &rules,
+            &ModMetadata::default(),
+        );
+
+        assert_eq!(download_ids(&jobs), vec![(100, 1000, true)]);
+        let local = jobs
+            .local
+            .iter()
+            .map(|local_mod| (local_mod.path.clone(), local_mod.disabled))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            local,
+            vec![
+                (PathBuf::from("fork.jar"), true),
+                (PathBuf::from("required.jar"), false),
+                (PathBuf::from("extra.jar"), false),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn needs_metadata_only_for_slug_and_file_name_rules() -> Result<(), Box<dyn Error>> {
+        assert!(!rules(json!({ "exclude": [{ "projectId": 1 }] }))?.needs_metadata());
+        assert!(rules(json!({ "exclude": [{ "slug": "jei" }] }))?.needs_metadata());
+        assert!(rules(json!({
+            "replace": [{ "match": { "fileName": "*" }, "with": { "path": "a.jar" } }]
+        }))?
+        .needs_metadata());
+        assert!(ModRules::default().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn from_file_resolves_local_jars_against_the_rules_file() -> Result<(), Box<dyn Error>> {
+        let directory = test_directory("resolve")?;
+        fs::create_dir_all(directory.join("forks"))?;
+        fs::write(directory.join("forks").join("fork.jar"), b"jar")?;
+        fs::write(directory.join("extra.jar"), b"jar")?;
+        let path = directory.join("rules.json");
+        fs::write(
+            &path,
+            json!({
+                "exclude": [{ "fileName": "*optifine*" }],
+                "replace": [{ "match": { "slug": "jei" }, "with": { "path": "forks/fork.jar" } }],
+                "add": [{ "path": "extra.jar" }, { "projectId": 1, "fileId": 2 }]
+            })
+            .to_string(),
+        )?;
+
+        let rules = ModRules::from_file(&path);
+        fs::remove_dir_all(&directory)?;
+        let rules = rules?;
+
+        assert_eq!(rules.exclude.len(), 1);
+        assert_eq!(
+            rules.replace[0].with,
+            ModSource::Local {
+                path: directory.join("forks").join("fork.jar"),
+            }
+        );
+        assert_eq!(
+            rules.add,
+            vec![
+                ModSource::Local {
+                    path: directory.join("extra.jar"),
+                },
+                ModSource::CurseForge {
+                    project_id: 1,
+                    file_id: 2,
+                },
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn from_file_rejects_invalid_rules() -> Result<(), Box<dyn Error>> {
+        let directory = test_directory("invalid")?;
+        let cases = [
+            ("malformed", "{".to_string()),
+            ("unknown-field", json!({ "remove": [] }).to_string()),
+            ("empty-matcher", json!({ "exclude": [{}] }).to_string()),
+            (
+                "missing-jar",
+                json!({ "add": [{ "path": "missing.jar" }] }).to_string(),
+            ),
+        ];
+        let mut results = Vec::new();
+        for (name, contents) in &cases {
+            let path = directory.join(format!("{}.json", name));
+            fs::write(&path, contents)?;
+            results.push((
+                *name,
+                ModRules::from_file(&path).map_err(|err| err.to_string()),
+            ));
+        }
+        let missing_file = ModRules::from_file(directory.join("absent.json"));
+        fs::remove_dir_all(&directory)?;
+
+        for (name, result) in &results {
+            assert!(result.is_err(), "{} was accepted", name);
+        }
+        assert!(matches!(&results[2].1, Err(err) if err.contains("projectId, slug or fileName")));
+        assert!(matches!(&results[3].1, Err(err) if err.contains("missing.jar")));
+        assert!(missing_file.is_err());
+        Ok(())
+    }
+}
@@ -1,26 +1,44 @@
+use crate::safe_path::{sanitize_entry_name, UnsafePathError};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashMap;
 use std::convert::TryFrom;
+use std::fmt::Display;
 use std::path::PathBuf;
+use std::str::FromStr;
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+/// The class of a CurseForge project, the `classId` of the API.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum ModType {
     Mod,
     ResourcePack,
     ShaderPack,
     ModPack,
+    World,
+    DataPack,
+    Customization,
+    Addon,
+    BukkitPlugin,
+    /// A class this crate doesn't know, installed like a mod.
+    Unknown(i64),
 }
 
 impl TryFrom<i64> for ModType {
     type Error = &'static str;
 
+    /// Converts a class ID, IDs of unknown classes become `ModType::Unknown` instead of failing.
     fn try_from(value: i64) -> Result<Self, Self::Error> {
-        match value {
-            6 => Ok(ModType::Mod),
-            12 => Ok(ModType::ResourcePack),
-            6552 => Ok(ModType::ShaderPack),
-            4471 => Ok(ModType::ModPack),
-            _ => Err("Invalid value for ModType"),
-        }
+        Ok(match value {
+            6 => ModType::Mod,
+            12 => ModType::ResourcePack,
+            6552 => ModType::ShaderPack,
+            4471 => ModType::ModPack,
+            17 => ModType::World,
+            6945 => ModType::DataPack,
+            4546 => ModType::Customization,
+            4559 => ModType::Addon,
+            5 => ModType::BukkitPlugin,
+            _ => ModType::Unknown(value),
+        })
     }
 }
 
@@ -41,6 +59,12 @@ impl From<ModType> for i64 {
             ModType::ResourcePack => 12,
             ModType::ShaderPack => 6552,
             ModType::ModPack => 4471,
+            ModType::World => 17,
+            ModType::DataPack => 6945,
+            ModType::Customization => 4546,
+            ModType::Addon => 4559,
+            ModType::BukkitPlugin => 5,
+            ModType::Unknown(value) => value,
         }
     }
 }
@@ -55,17 +79,200 @@ impl Serialize for ModType {
     }
 }
 
+impl Display for ModType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ModType::Mod => write!(f, "mod"),
+            ModType::ResourcePack => write!(f, "resourcepack"),
+            ModType::ShaderPack => write!(f, "shaderpack"),
+            ModType::ModPack => write!(f, "modpack"),
+            ModType::World => write!(f, "world"),
+            ModType::DataPack => write!(f, "datapack"),
+            ModType::Customization => write!(f, "customization"),
+            ModType::Addon => write!(f, "addon"),
+            ModType::BukkitPlugin => write!(f, "plugin"),
+            ModType::Unknown(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+impl FromStr for ModType {
+    type Err = String;
+
+    /// Parses the name of a class, e.g. `datapack`, or a class ID.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "mod" => Ok(ModType::Mod),
+            "resourcepack" => Ok(ModType::ResourcePack),
+            "shaderpack" => Ok(ModType::ShaderPack),
+            "modpack" => Ok(ModType::ModPack),
+            "world" => Ok(ModType::World),
+            "datapack" => Ok(ModType::DataPack),
+            "customization" => Ok(ModType::Customization),
+            "addon" => Ok(ModType::Addon),
+            "plugin" | "bukkitplugin" => Ok(ModType::BukkitPlugin),
+            other => {
+                let class_id = other.parse::<i64>().map_err(|_| {
+                    format!(
+                        "Unknown project class '{}', expected mod, resourcepack, shaderpack, modpack, world, datapack, customization, addon, plugin or a class ID",
+                        s
+                    )
+                })?;
+                ModType::try_from(class_id).map_err(str::to_string)
+            }
+        }
+    }
+}
+
 pub trait ModTypeExt {
     fn to_path(&self) -> PathBuf;
 }
 
 impl ModTypeExt for ModType {
+    /// The default directory of the class, relative to the output root.
     fn to_path(&self) -> PathBuf {
         match self {
             ModType::Mod => PathBuf::from("mods"),
             ModType::ResourcePack => PathBuf::from("resourcepacks"),
             ModType::ShaderPack => PathBuf::from("shaderpacks"),
             ModType::ModPack => PathBuf::from("modpacks"),
+            ModType::World => PathBuf::from("saves"),
+            ModType::DataPack => PathBuf::from("datapacks"),
+            ModType::Customization => PathBuf::from("customization"),
+            ModType::Addon => PathBuf::from("addons"),
+            ModType::BukkitPlugin => PathBuf::from("plugins"),
+            ModType::Unknown(_) => PathBuf::from("mods"),
         }
     }
 }
+
+/// Where the files of each project class are installed, relative to the output root.
+///
+/// Classes without a configured directory use `ModTypeExt::to_path`.
+#[derive(Clone, Debug, Default)]
+pub struct ModPaths {
+    paths: HashMap<ModType, PathBuf>,
+}
+
+impl ModPaths {
+    /// Configures the directory of a class.
+    ///
+    /// # Parameters
+    ///
+    /// - `mod_type`: The class of the projects.
+    /// - `path`: The directory relative to the output root, `/` separated. Empty for the root.
+    ///
+    /// # Returns
+    ///
+    /// An error if the path is absolute or leaves the output root.
+    pub fn set(&mut self, mod_type: ModType, path: &str) -> Result<&mut Self, UnsafePathError> {
+        let path = match sanitize_entry_name(path) {
+            Err(UnsafePathError::Empty) => PathBuf::new(),
+            path => path?,
+        };
+        self.paths.insert(mod_type, path);
+        Ok(self)
+    }
+
+    /// The directory of a class, relative to the output root.
+    pub fn path(&self, mod_type: &ModType) -> PathBuf {
+        self.paths
+            .get(mod_type)
+            .cloned()
+            .unwrap_or_else(|| mod_type.to_path())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project_structure::ProjectData;
+    use serde_json::json;
+    use std::error::Error;
+
+    const KNOWN: [(i64, ModType); 9] = [
+        (6, ModType::Mod),
+        (12, ModType::ResourcePack),
+        (6552, ModType::ShaderPack),
+        (4471, ModType::ModPack),
+        (17, ModType::World),
+        (6945, ModType::DataPack),
+        (4546, ModType::Customization),
+        (4559, ModType::Addon),
+        (5, ModType::BukkitPlugin),
+    ];
+
+    #[test]
+    fn class_ids_round_trip() -> Result<(), Box<dyn Error>> {
+        for (class_id, mod_type) in KNOWN {
+            assert_eq!(ModType::try_from(class_id)?, mod_type);
+            assert_eq!(i64::from(mod_type), class_id);
+        }
+        assert_eq!(ModType::try_from(123456)?, ModType::Unknown(123456));
+        assert_eq!(i64::from(ModType::Unknown(123456)), 123456);
+        Ok(())
+    }
+
+    #[test]
+    fn serializes_as_the_class_id() -> Result<(), Box<dyn Error>> {
+        for mod_type in [ModType::DataPack, ModType::Unknown(-3)] {
+            let value = serde_json::to_value(&mod_type)?;
+            assert_eq!(value, json!(i64::from(mod_type.clone())));
+            assert_eq!(serde_json::from_value::<ModType>(value)?, mod_type);
+        }
+        assert!(serde_json::from_value::<ModType>(json!("mod")).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parses_names_and_class_ids() -> Result<(), Box<dyn Error>> {
+        for (_, mod_type) in KNOWN {
+            assert_eq!(mod_type.to_string().parse::<ModType>()?, mod_type);
+        }
+        assert_eq!(" DataPack ".parse::<ModType>()?, ModType::DataPack);
+        assert_eq!("bukkitplugin".parse::<ModType>()?, ModType::BukkitPlugin);
+        assert_eq!("6945".parse::<ModType>()?, ModType::DataPack);
+        assert_eq!("777".parse::<ModType>()?, ModType::Unknown(777));
+        assert_eq!(
+            ModType::Unknown(777).to_string().parse::<ModType>()?,
+            ModType::Unknown(777)
+        );
+        assert!("texturepack".parse::<ModType>().is_err());
+        assert!("".parse::<ModType>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn deserializes_the_class_of_projects() -> Result<(), Box<dyn Error>> {
+        let class_of = |class_id: i64| -> Result<Option<ModType>, Box<dyn Error>> {
+            let project: ProjectData =
+                serde_json::from_value(json!({ "id": 1, "classId": class_id }))?;
+            Ok(project.class_id)
+        };
+        assert_eq!(class_of(17)?, Some(ModType::World));
+        assert_eq!(class_of(6945)?, Some(ModType::DataPack));
+        assert_eq!(class_of(5)?, Some(ModType::BukkitPlugin));
+        assert_eq!(class_of(9999)?, Some(ModType::Unknown(9999)));
+
+        let project: ProjectData = serde_json::from_value(json!({ "id": 1 }))?;
+        assert_eq!(project.class_id, None);
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_classes_install_like_mods() -> Result<(), Box<dyn Error>> {
+        let mut paths = ModPaths::default();
+        paths.set(ModType::DataPack, "world/datapacks")?;
+        paths.set(ModType::Customization, "")?;
+
+        assert_eq!(paths.path(&ModType::Unknown(9999)), PathBuf::from("mods"));
+        assert_eq!(paths.path(&ModType::World), PathBuf::from("saves"));
+        assert_eq!(
+            paths.path(&ModType::DataPack),
+            PathBuf::from("world").join("datapacks")
+        );
+        assert_eq!(paths.path(&ModType::Customization), PathBuf::new());
+        assert!(paths.set(ModType::Mod, "../mods").is_err());
+        Ok(())
+    }
+}
@@ -1,4 +1,6 @@
 use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::str::FromStr;
 
 #[derive(Serialize, Deserialize)]
 pub struct SortableGameVersion {
@@ -59,3 +61,161 @@ pub struct ModpackVersionFile {
     #[serde(rename = "serverPackFileId")]
     pub server_pack_file_id: Option<i64>,
 }
+
+/// The release channel of a file.
+///
+/// Ordered from most to least stable, so `channel <= ReleaseType::Beta` means beta or better.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ReleaseType {
+    Release,
+    Beta,
+    Alpha,
+}
+
+impl TryFrom<i64> for ReleaseType {
+    type Error = &'static str;
+
+    fn try_from(value: i64) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(ReleaseType::Release),
+            2 => Ok(ReleaseType::Beta),
+            3 => Ok(ReleaseType::Alpha),
+            _ => Err("Invalid value for ReleaseType"),
+        }
+    }
+}
+
+impl Display for ReleaseType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            ReleaseType::Release => "release",
+            ReleaseType::Beta => "beta",
+            ReleaseType::Alpha => "alpha",
+        };
+        write!(f, "{}", str)
+    }
+}
+
+impl FromStr for ReleaseType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "release" => Ok(ReleaseType::Release),
+            "beta" => Ok(ReleaseType::Beta),
+            "alpha" => Ok(ReleaseType::Alpha),
+            _ => Err(format!(
+                "Unknown release type '{}', expected release, beta or alpha",
+                s
+            )),
+        }
+    }
+}
+
+impl ModpackVersionFile {
+    /// The release channel of this version, `None` if the API returned an unknown value.
+    pub fn release_channel(&self) -> Option<ReleaseType> {
+        self.release_type
+            .and_then(|release_type| ReleaseType::try_from(release_type).ok())
+    }
+}
+
+/// Which version of a pack to install.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum PackVersionSelector {
+    /// The newest version by `fileDate`, within the release channel if one is set.
+    #[default]
+    Latest,
+    /// The newest version with the `release` channel.
+    LatestRelease,
+    /// The version with this file ID.
+    FileId(u64),
+    /// The version with this display name, compared case-insensitively.
+    DisplayName(String),
+}
+
+impl Display for PackVersionSelector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PackVersionSelector::Latest => write!(f, "latest"),
+            PackVersionSelector::LatestRelease => write!(f, "latest-release"),
+            PackVersionSelector::FileId(file_id) => write!(f, "{}", file_id),
+            PackVersionSelector::DisplayName(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+impl FromStr for PackVersionSelector {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err("The version must not be empty".to_string());
+        }
+        Ok(match s.to_ascii_lowercase().as_str() {
+            "latest" => PackVersionSelector::Latest,
+            "latest-release" => PackVersionSelector::LatestRelease,
+            _ => match s.parse::<u64>() {
+                Ok(file_id) => PackVersionSelector::FileId(file_id),
+                Err(_) => PackVersionSelector::DisplayName(s.to_string()),
+            },
+        })
+    }
+}
+
+impl PackVersionSelector {
+    /// Picks the matching version from the versions of a pack.
+    ///
+    /// The order of `versions` doesn't matter, "newest" always means the latest `fileDate`.
+    /// A file ID that doesn't match any file is also tried as a display name,
+    /// so packs with purely numeric version names can still be selected by name.
+    ///
+    /// # Parameters
+    ///
+    /// - `versions`: Every version of the pack, e.g. from `curseforge_api::get_pack_versions`.
+    /// - `channel`: The least stable release channel `Latest` may pick, e.g. `Beta` allows
+    ///   release and beta versions. `None` allows every channel.
+    ///
+    /// # Returns
+    ///
+    /// The selected version, or `None` if nothing matches.
+    pub fn select<'a>(
+        &self,
+        versions: &'a [ModpackVersionFile],
+        channel: Option<ReleaseType>,
+    ) -> Option<&'a ModpackVersionFile> {
+        let newest = |filter: &dyn Fn(&ModpackVersionFile) -> bool| {
+            versions
+                .iter()
+                .filter(|version| filter(version))
+                // ISO 8601 dates compare chronologically as strings
+                .max_by(|a, b| a.file_date.cmp(&b.file_date))
+        };
+        let by_name = |name: &str| {
+            newest(&|version| {
+                version
+                    .display_name
+                    .as_deref()
+                    .is_some_and(|display_name| display_name.eq_ignore_ascii_case(name))
+            })
+        };
+
+        match self {
+            PackVersionSelector::Latest => newest(&|version| match channel {
+                Some(channel) => version
+                    .release_channel()
+                    .is_some_and(|release_channel| release_channel <= channel),
+                None => true,
+            }),
+            PackVersionSelector::LatestRelease => {
+                newest(&|version| version.release_channel() == Some(ReleaseType::Release))
+            }
+            PackVersionSelector::FileId(file_id) => versions
+                .iter()
+                .find(|version| version.id == Some(*file_id as i64))
+                .or_else(|| by_name(&file_id.to_string())),
+            PackVersionSelector::DisplayName(name) => by_name(name),
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use crate::pack_manifest::ModItem;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// The suffix launchers append to the file name of a mod to disable it.
+pub const DISABLED_SUFFIX: &str = ".disabled";
+
+/// What happens to the mods a manifest doesn't mark as `required`.
+///
+/// Required mods are always installed.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(tag = "type", content = "projectIds", rename_all = "camelCase")]
+pub enum OptionalModsPolicy {
+    /// Install every optional mod.
+    #[default]
+    IncludeAll,
+    /// Install no optional mod.
+    ExcludeAll,
+    /// Install only the optional mods with these project IDs.
+    Allowlist(BTreeSet<u64>),
+    /// Install every optional mod disabled, with `DISABLED_SUFFIX` appended to the file name.
+    /// Disabled worlds are installed as their archive instead of being extracted.
+    Disabled,
+}
+
+/// What happens to a single mod of the manifest.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ModAction {
+    Install,
+    /// Install the mod with `DISABLED_SUFFIX` appended to the file name.
+    InstallDisabled,
+    Skip,
+}
+
+impl OptionalModsPolicy {
+    /// Decides what happens to a mod of the manifest.
+    pub fn action(&self, item: &ModItem) -> ModAction {
+        if item.required {
+            return ModAction::Install;
+        }
+        match self {
+            OptionalModsPolicy::IncludeAll => ModAction::Install,
+            OptionalModsPolicy::ExcludeAll => ModAction::Skip,
+            OptionalModsPolicy::Allowlist(project_ids) => {
+                if project_ids.contains(&(item.project_id as u64)) {
+                    ModAction::Install
+                } else {
+                    ModAction::Skip
+                }
+            }
+            OptionalModsPolicy::Disabled => ModAction::InstallDisabled,
+        }
+    }
+}
+
+impl Display for OptionalModsPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OptionalModsPolicy::IncludeAll => write!(f, "include"),
+            OptionalModsPolicy::ExcludeAll => write!(f, "exclude"),
+            OptionalModsPolicy::Allowlist(project_ids) => write!(
+                f,
+                "{}",
+                project_ids
+                    .iter()
+                    .map(|project_id| project_id.to_string())
+                    .collect::<Vec<_>>()
+                    .join(",")
+            ),
+            OptionalModsPolicy::Disabled => write!(f, "disable"),
+        }
+    }
+}
+
+impl FromStr for OptionalModsPolicy {
+    type Err = String;
+
+    /// Parses `include`, `exclude`, `disable` or a comma separated list of project IDs.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "include" | "all" => Ok(OptionalModsPolicy::IncludeAll),
+            "exclude" | "none" => Ok(OptionalModsPolicy::ExcludeAll),
+            "disable" | "disabled" => Ok(OptionalModsPolicy::Disabled),
+            list => list
+                .split(',')
+                .map(|project_id| project_id.trim().parse::<u64>())
+                .collect::<Result<BTreeSet<_>, _>>()
+                .map(OptionalModsPolicy::Allowlist)
+                .map_err(|_| {
+                    format!(
+                        "Unknown optional mods policy '{}', expected include, exclude, disable or a comma separated list of project IDs",
+                        s
+                    )
+                }),
+        }
+    }
+}
+
+/// Appends `DISABLED_SUFFIX` to the file name of `path`.
+pub fn disabled_path(path: impl AsRef<Path>) -> PathBuf {
+    let mut path = path.as_ref().as_os_str().to_os_string();
+    path.push(DISABLED_SUFFIX);
+    PathBuf::from(path)
+}
@@ -0,0 +1,1099 @@
+use crate::cleanup_guard::CleanupGuard;
+use crate::safe_path::safe_join;
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt::Display;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::SystemTime;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, DateTime, ZipWriter};
+
+/// The zstd compression level used for `.tar.zst` outputs.
+const ZSTD_COMPRESSION_LEVEL: i32 = 19;
+
+/// The kind of artifact the finalized pack is written as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputKind {
+    /// A plain directory, ready to be launched.
+    #[default]
+    Directory,
+    /// A `.zip` archive.
+    Zip,
+    /// A zstd compressed tarball (`.tar.zst`).
+    TarZst,
+}
+
+impl OutputKind {
+    /// The file extension of the artifact, if it has one.
+    pub fn extension(&self) -> Option<&'static str> {
+        match self {
+            OutputKind::Directory => None,
+            OutputKind::Zip => Some(".zip"),
+            OutputKind::TarZst => Some(".tar.zst"),
+        }
+    }
+
+    /// Appends the extension of the artifact to `path` unless it is already there.
+    ///
+    /// # Parameters
+    ///
+    /// - `path`: The resolved output path.
+    ///
+    /// # Returns
+    ///
+    /// The path the artifact will be written to.
+    pub fn target_path(&self, path: impl AsRef<Path>) -> PathBuf {
+        let path = path.as_ref();
+        match self.extension() {
+            Some(extension) if !path.to_string_lossy().ends_with(extension) => {
+                let mut path = path.as_os_str().to_os_string();
+                path.push(extension);
+                PathBuf::from(path)
+            }
+            _ => path.to_path_buf(),
+        }
+    }
+}
+
+impl Display for OutputKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            OutputKind::Directory => "directory",
+            OutputKind::Zip => "zip",
+            OutputKind::TarZst => "tar-zst",
+        };
+        write!(f, "{}", str)
+    }
+}
+
+impl FromStr for OutputKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "directory" | "dir" => Ok(OutputKind::Directory),
+            "zip" => Ok(OutputKind::Zip),
+            "tar-zst" | "tar.zst" | "tzst" => Ok(OutputKind::TarZst),
+            _ => Err(format!(
+                "Invalid output kind '{}', expected one of: directory, zip, tar-zst",
+                s
+            )),
+        }
+    }
+}
+
+/// What happens when something already exists at the resolved output path.
+/// An empty directory doesn't count, so a freshly created folder can be installed into.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum OutputPolicy {
+    /// Stop before anything is downloaded or written.
+    #[default]
+    FailIfExists,
+    /// Replace the existing output once the new one is complete.
+    Overwrite,
+    /// Write the pack into the existing directory, files the pack doesn't have are kept.
+    /// Only supported for directory outputs.
+    Merge,
+    /// Rename the existing output with a timestamp once the new one is complete,
+    /// e.g. `packs/My Pack.backup-1734000000`.
+    Backup,
+}
+
+impl OutputPolicy {
+    /// Checks the policy against the output before any work is done.
+    ///
+    /// # Parameters
+    ///
+    /// - `kind`: The kind of artifact that will be written.
+    /// - `target`: The resolved output path, including the archive extension.
+    ///
+    /// # Returns
+    ///
+    /// `Ok` if the output can be written with this policy.
+    pub fn check(&self, kind: OutputKind, target: &Path) -> Result<(), Box<dyn Error>> {
+        match self {
+            OutputPolicy::FailIfExists if is_taken(target) => Err(format!(
+                "Output {} already exists, choose another output or an output policy",
+                target.display()
+            )
+            .into()),
+            OutputPolicy::Merge if kind != OutputKind::Directory => {
+                Err(format!("A {} output can't be merged into", kind).into())
+            }
+            OutputPolicy::Merge if target.exists() && !target.is_dir() => Err(format!(
+                "Unable to merge into {}, it is not a directory",
+                target.display()
+            )
+            .into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Display for OutputPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            OutputPolicy::FailIfExists => "fail-if-exists",
+            OutputPolicy::Overwrite => "overwrite",
+            OutputPolicy::Merge => "merge",
+            OutputPolicy::Backup => "backup",
+        };
+        write!(f, "{}", str)
+    }
+}
+
+impl FromStr for OutputPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "fail-if-exists" | "fail" => Ok(OutputPolicy::FailIfExists),
+            "overwrite" => Ok(OutputPolicy::Overwrite),
+            "merge" => Ok(OutputPolicy::Merge),
+            "backup" => Ok(OutputPolicy::Backup),
+            _ => Err(format!(
+                "Invalid output policy '{}', expected one of: fail-if-exists, overwrite, merge, backup",
+                s
+            )),
+        }
+    }
+}
+
+/// A destination the processing pipeline writes the finalized pack through.
+///
+/// All paths passed to a sink are relative to the root of the pack and are checked with
+/// `safe_join`, so nothing can be written outside of the output.
+pub trait OutputSink: Send {
+    /// Writes the contents of `reader` to `relative`.
+    ///
+    /// # Returns
+    ///
+    /// The number of bytes written.
+    fn write_file(&mut self, relative: &Path, reader: &mut dyn Read)
+        -> Result<u64, Box<dyn Error>>;
+
+    /// Moves an already completed file, e.g. a finished download, to `relative`.
+    /// `known_md5` is the MD5 hash of `source` if the caller already calculated it.
+    ///
+    /// # Returns
+    ///
+    /// The size of the file in bytes.
+    fn add_file(
+        &mut self,
+        relative: &Path,
+        source: &Path,
+        known_md5: Option<&str>,
+    ) -> Result<u64, Box<dyn Error>>;
+
+    /// Creates an (empty) directory at `relative`.
+    fn create_dir(&mut self, relative: &Path) -> Result<(), Box<dyn Error>>;
+
+    /// The file at `relative` in the install this sink replaces, if there is one.
+    ///
+    /// Used to reuse the intact files of an earlier run instead of downloading them again.
+    fn existing_file(&self, _relative: &Path) -> Option<PathBuf> {
+        None
+    }
+
+    /// The file at `relative` in the install the output is merged into, if there is one.
+    ///
+    /// Unlike `existing_file`, this file is kept unless the pack writes the same path, so a
+    /// write can be combined with it, e.g. the `server.properties` of an existing server.
+    fn merged_file(&self, _relative: &Path) -> Option<PathBuf> {
+        None
+    }
+
+    /// Finalizes the output.
+    ///
+    /// # Returns
+    ///
+    /// The path of the finished directory or archive.
+    fn finish(self: Box<Self>) -> Result<PathBuf, Box<dyn Error>>;
+}
+
+/// Creates the sink for the given output kind.
+///
+/// # Parameters
+///
+/// - `kind`: The kind of artifact to produce.
+/// - `target`: The resolved output path, the archive extension is appended if missing.
+/// - `policy`: What happens to an existing output at that path.
+///
+/// # Returns
+///
+/// A boxed `OutputSink`, or an error if the output location can't be prepared or
+/// the policy doesn't allow writing to it.
+pub fn create_sink(
+    kind: OutputKind,
+    target: impl AsRef<Path>,
+    policy: OutputPolicy,
+) -> Result<Box<dyn OutputSink>, Box<dyn Error>> {
+    let target = kind.target_path(target);
+    policy.check(kind, &target)?;
+    Ok(match kind {
+        OutputKind::Directory => Box::new(DirectorySink::new(target, policy)?),
+        OutputKind::Zip | OutputKind::TarZst => Box::new(ArchiveSink::new(kind, target, policy)?),
+    })
+}
+
+/// A directory next to the final output that the pack is assembled in.
+///
+/// The directory is removed when dropped, so a failed or cancelled run never leaves
+/// half-written output behind and never touches a previous install.
+struct StagingDirectory {
+    guard: CleanupGuard,
+}
+
+impl StagingDirectory {
+    /// Creates the staging directory for `target`, replacing leftovers of a crashed run.
+    fn new(target: &Path) -> Result<Self, Box<dyn Error>> {
+        let guard = CleanupGuard::new(sibling_path(target, "staging"));
+        if guard.path().exists() {
+            warn!(
+                "Removing stale staging directory {}",
+                guard.path().display()
+            );
+            fs::remove_dir_all(guard.path())?;
+        }
+        fs::create_dir_all(guard.path())?;
+        Ok(Self { guard })
+    }
+
+    fn root(&self) -> &Path {
+        self.guard.path()
+    }
+
+    /// Resolves `relative` inside the root and creates its parent directories.
+    fn prepare(&self, relative: &Path) -> Result<PathBuf, Box<dyn Error>> {
+        let target = safe_join(self.root(), relative)?;
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        Ok(target)
+    }
+
+    fn write_file(
+        &mut self,
+        relative: &Path,
+        reader: &mut dyn Read,
+    ) -> Result<u64, Box<dyn Error>> {
+        let target = self.prepare(relative)?;
+        let mut file = File::create(&target)?;
+        Ok(io::copy(reader, &mut file)?)
+    }
+
+    fn add_file(&mut self, relative: &Path, source: &Path) -> Result<u64, Box<dyn Error>> {
+        let target = self.prepare(relative)?;
+        move_file(source, &target)?;
+        Ok(target.metadata()?.len())
+    }
+
+    fn create_dir(&mut self, relative: &Path) -> Result<(), Box<dyn Error>> {
+        fs::create_dir_all(safe_join(self.root(), relative)?)?;
+        Ok(())
+    }
+}
+
+/// Writes the pack into a plain directory.
+///
+/// The pack is assembled in a sibling staging directory and renamed into place once
+/// everything succeeded. A previous install at the same path is only replaced, backed up
+/// or merged into at that point, depending on the `OutputPolicy`.
+pub struct DirectorySink {
+    staging: StagingDirectory,
+    target: PathBuf,
+    policy: OutputPolicy,
+}
+
+impl DirectorySink {
+    /// Creates a new `DirectorySink` that stages files next to `target`.
+    pub fn new(target: impl AsRef<Path>, policy: OutputPolicy) -> Result<Self, Box<dyn Error>> {
+        let target = target.as_ref().to_path_buf();
+        Ok(Self {
+            staging: StagingDirectory::new(&target)?,
+            target,
+            policy,
+        })
+    }
+}
+
+impl OutputSink for DirectorySink {
+    fn write_file(
+        &mut self,
+        relative: &Path,
+        reader: &mut dyn Read,
+    ) -> Result<u64, Box<dyn Error>> {
+        self.staging.write_file(relative, reader)
+    }
+
+    fn add_file(
+        &mut self,
+        relative: &Path,
+        source: &Path,
+        _known_md5: Option<&str>,
+    ) -> Result<u64, Box<dyn Error>> {
+        self.staging.add_file(relative, source)
+    }
+
+    fn create_dir(&mut self, relative: &Path) -> Result<(), Box<dyn Error>> {
+        self.staging.create_dir(relative)
+    }
+
+    fn existing_file(&self, relative: &Path) -> Option<PathBuf> {
+        safe_join(&self.target, relative)
+            .ok()
+            .filter(|path| path.is_file())
+    }
+
+    fn merged_file(&self, relative: &Path) -> Option<PathBuf> {
+        match self.policy {
+            OutputPolicy::Merge => self.existing_file(relative),
+            _ => None,
+        }
+    }
+
+    fn finish(self: Box<Self>) -> Result<PathBuf, Box<dyn Error>> {
+        let staging = self.staging.guard.disarm();
+        place_output(&staging, &self.target, self.policy).inspect_err(|_| {
+            // Never leave a half-finished staging directory behind
+            if let Err(err) = fs::remove_dir_all(&staging) {
+                error!("Unable to remove staging directory: {}", err);
+            }
+        })?;
+        info!("Pack installed to {}", self.target.display());
+        Ok(self.target)
+    }
+}
+
+/// Writes the pack into a `.zip` or `.tar.zst` archive.
+///
+/// Files are collected in a staging directory next to the target and packed once the
+/// pipeline finishes. Entries are written in sorted order with fixed timestamps and
+/// permissions, so packing the same pack version twice yields a byte-identical archive.
+/// The archive is written to a partial file first and renamed over the target when complete.
+pub struct ArchiveSink {
+    kind: OutputKind,
+    staging: StagingDirectory,
+    target: PathBuf,
+    policy: OutputPolicy,
+}
+
+impl ArchiveSink {
+    /// Creates a new `ArchiveSink` that stages files next to `target`.
+    pub fn new(
+        kind: OutputKind,
+        target: impl AsRef<Path>,
+        policy: OutputPolicy,
+    ) -> Result<Self, Box<dyn Error>> {
+        let target = target.as_ref().to_path_buf();
+        Ok(Self {
+            kind,
+            staging: StagingDirectory::new(&target)?,
+            target,
+            policy,
+        })
+    }
+
+    /// Packs the staging directory into `file`.
+    fn pack(&self, file: &Path) -> Result<(), Box<dyn Error>> {
+        let entries = collect_entries(self.staging.root())?;
+        let file = File::create(file)?;
+
+        match self.kind {
+            OutputKind::Zip => write_zip(&entries, file),
+            OutputKind::TarZst => write_tar_zst(&entries, file),
+            OutputKind::Directory => Err("A directory is not an archive".into()),
+        }
+    }
+}
+
+impl OutputSink for ArchiveSink {
+    fn write_file(
+        &mut self,
+        relative: &Path,
+        reader: &mut dyn Read,
+    ) -> Result<u64, Box<dyn Error>> {
+        self.staging.write_file(relative, reader)
+    }
+
+    fn add_file(
+        &mut self,
+        relative: &Path,
+        source: &Path,
+        _known_md5: Option<&str>,
+    ) -> Result<u64, Box<dyn Error>> {
+        self.staging.add_file(relative, source)
+    }
+
+    fn create_dir(&mut self, relative: &Path) -> Result<(), Box<dyn Error>> {
+        self.staging.create_dir(relative)
+    }
+
+    fn finish(self: Box<Self>) -> Result<PathBuf, Box<dyn Error>> {
+        info!("Packing {} archive {}", self.kind, self.target.display());
+
+        // The partial archive is removed unless it made it into place
+        let partial = CleanupGuard::new(sibling_path(&self.target, "partial"));
+        self.pack(partial.path())?;
+        place_output(partial.path(), &self.target, self.policy)?;
+        partial.disarm();
+
+        Ok(self.target)
+    }
+}
+
+/// Builds a hidden path next to `target`, e.g. `packs/.My Pack.staging` for `packs/My Pack`.
+fn sibling_path(target: &Path, suffix: &str) -> PathBuf {
+    let name = target
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or("output".to_string());
+    target.with_file_name(format!(".{}.{}", name, suffix))
+}
+
+/// Moves a finished output from `staging` to `target`, handling an existing output
+/// according to `policy`.
+fn place_output(staging: &Path, target: &Path, policy: OutputPolicy) -> Result<(), Box<dyn Error>> {
+    // Something may have appeared at the target while the pack was processed
+    if !is_taken(target) {
+        return swap_into_place(staging, target);
+    }
+    match policy {
+        OutputPolicy::FailIfExists => Err(format!(
+            "Output {} was created while the pack was processed",
+            target.display()
+        )
+        .into()),
+        OutputPolicy::Overwrite => swap_into_place(staging, target),
+        OutputPolicy::Merge => {
+            merge_into(staging, target)?;
+            fs::remove_dir_all(staging)?;
+            Ok(())
+        }
+        OutputPolicy::Backup => {
+            let backup = backup_path(target);
+            info!("Moving previous output to {}", backup.display());
+            fs::rename(target, &backup)?;
+            if let Err(err) = fs::rename(staging, target) {
+                error!(
+                    "Unable to move pack into place, restoring previous output: {}",
+                    err
+                );
+                fs::rename(&backup, target)?;
+                return Err(err.into());
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Whether something that would be lost is at `target`, an empty directory is not.
+fn is_taken(target: &Path) -> bool {
+    match fs::read_dir(target) {
+        Ok(mut entries) => entries.next().is_some(),
+        Err(_) => target.exists(),
+    }
+}
+
+/// Moves every file of the `source` directory into `target`, replacing files that exist in both.
+fn merge_into(source: &Path, target: &Path) -> Result<(), Box<dyn Error>> {
+    for entry in fs::read_dir(source)? {
+        let entry = entry?;
+        let destination = target.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            if destination.exists() && !destination.is_dir() {
+                fs::remove_file(&destination)?;
+            }
+            fs::create_dir_all(&destination)?;
+            merge_into(&entry.path(), &destination)?;
+        } else {
+            if destination.is_dir() {
+                fs::remove_dir_all(&destination)?;
+            }
+            move_file(entry.path(), &destination)?;
+        }
+    }
+    Ok(())
+}
+
+/// Builds a visible path next to `target` for a backup, e.g. `packs/My Pack.backup-1734000000`.
+fn backup_path(target: &Path) -> PathBuf {
+    let name = target
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or("output".to_string());
+    let time = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+    target.with_file_name(format!("{}.backup-{}", name, time))
+}
+
+/// Renames `staging` to `target`, replacing a previous install if there is one.
+///
+/// The previous install is moved aside first and only deleted once the new one is in place.
+/// If the final rename fails, the previous install is restored.
+fn swap_into_place(staging: &Path, target: &Path) -> Result<(), Box<dyn Error>> {
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    if !target.exists() {
+        fs::rename(staging, target)?;
+        return Ok(());
+    }
+
+    // Clear out leftovers of a crashed run before moving the previous install aside
+    let previous = sibling_path(target, "previous");
+    drop(CleanupGuard::new(&previous));
+    fs::rename(target, &previous)?;
+
+    if let Err(err) = fs::rename(staging, target) {
+        error!(
+            "Unable to move pack into place, restoring previous install: {}",
+            err
+        );
+        fs::rename(&previous, target)?;
+        return Err(err.into());
+    }
+
+    // The new install is in place, the previous one can go
+    drop(CleanupGuard::new(previous));
+    Ok(())
+}
+
+/// A single file or directory that will be written to an archive.
+struct ArchiveEntry {
+    /// The `/` separated path inside the archive.
+    name: String,
+    /// The path of the entry on disk.
+    path: PathBuf,
+    is_dir: bool,
+}
+
+/// Walks `root` and returns every entry sorted by its archive name.
+fn collect_entries(root: &Path) -> Result<Vec<ArchiveEntry>, Box<dyn Error>> {
+    let mut entries = Vec::new();
+    let mut pending = vec![root.to_path_buf()];
+
+    while let Some(directory) = pending.pop() {
+        for entry in fs::read_dir(&directory)? {
+            let entry = entry?;
+            let path = entry.path();
+            let is_dir = entry.file_type()?.is_dir();
+            let name = path
+                .strip_prefix(root)?
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+
+            if is_dir {
+                pending.push(path.clone());
+            }
+            entries.push(ArchiveEntry { name, path, is_dir });
+        }
+    }
+
+    // Sorting makes the archive independent of the order the file system returns entries in
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(entries)
+}
+
+/// Writes the entries to a zip archive with fixed timestamps and permissions.
+fn write_zip(entries: &[ArchiveEntry], file: File) -> Result<(), Box<dyn Error>> {
+    let mut zip = ZipWriter::new(file);
+    let options = SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Deflated)
+        .last_modified_time(DateTime::default());
+
+    for entry in entries {
+        if entry.is_dir {
+            zip.add_directory(entry.name.as_str(), options.unix_permissions(0o755))?;
+        } else {
+            zip.start_file(entry.name.as_str(), options.unix_permissions(0o644))?;
+            io::copy(&mut File::open(&entry.path)?, &mut zip)?;
+        }
+    }
+
+    zip.finish()?.flush()?;
+    Ok(())
+}
+
+/// Writes the entries to a zstd compressed tarball with fixed timestamps, owners and permissions.
+fn write_tar_zst(entries: &[ArchiveEntry], file: File) -> Result<(), Box<dyn Error>> {
+    let encoder = zstd::Encoder::new(file, ZSTD_COMPRESSION_LEVEL)?;
+    let mut tar = tar::Builder::new(encoder);
+
+    for entry in entries {
+        let mut header = tar::Header::new_gnu();
+        header.set_mtime(0);
+        header.set_uid(0);
+        header.set_gid(0);
+
+        if entry.is_dir {
+            header.set_entry_type(tar::EntryType::Directory);
+            header.set_mode(0o755);
+            header.set_size(0);
+            tar.append_data(&mut header, format!("{}/", entry.name), io::empty())?;
+        } else {
+            let file = File::open(&entry.path)?;
+            header.set_entry_type(tar::EntryType::Regular);
+            header.set_mode(0o644);
+            header.set_size(file.metadata()?.len());
+            tar.append_data(&mut header, &entry.name, file)?;
+        }
+    }
+
+    tar.into_inner()?.finish()?.flush()?;
+    Ok(())
+}
+
+/// Moves a file, falling back to copy and delete when a rename isn't possible,
+/// e.g. when the source and destination are on different file systems.
+///
+/// # Parameters
+///
+/// - `from`: The file to move.
+/// - `to`: The destination path.
+///
+/// # Returns
+///
+/// `Ok` if the file was moved.
+pub(crate) fn move_file(from: impl AsRef<Path>, to: impl AsRef<Path>) -> io::Result<()> {
+    if fs::rename(&from, &to).is_ok() {
+        return Ok(());
+    }
+    fs::copy(&from, &to)?;
+    fs::remove_file(from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::time::Duration;
+
+    /// A fresh directory below the system temp directory, unique per test.
+    fn test_directory(name: &str) -> Result<PathBuf, Box<dyn Error>> {
+        let directory =
+            std::env::temp_dir().join(format!("output-sink-{}-{}", name, std::process::id()));
+        if directory.exists() {
+            fs::remove_dir_all(&directory)?;
+        }
+        fs::create_dir_all(&directory)?;
+        Ok(directory)
+    }
+
+    /// Writes `files` through a new sink and finishes it.
+    fn write_output(
+        kind: OutputKind,
+        target: &Path,
+        policy: OutputPolicy,
+        files: &[(&str, &str)],
+    ) -> Result<PathBuf, Box<dyn Error>> {
+        let mut sink = create_sink(kind, target, policy)?;
+        for (path, contents) in files {
+            sink.write_file(Path::new(path), &mut contents.as_bytes())?;
+        }
+        sink.finish()
+    }
+
+    /// Every file below `root` with its contents, keyed by its `/` separated relative path.
+    fn read_tree(root: &Path) -> Result<BTreeMap<String, String>, Box<dyn Error>> {
+        let mut files = BTreeMap::new();
+        for entry in collect_entries(root)? {
+            if !entry.is_dir {
+                files.insert(entry.name, fs::read_to_string(entry.path)?);
+            }
+        }
+        Ok(files)
+    }
+
+    /// Creates a directory with the given files.
+    fn create_tree(root: &Path, files: &[(&str, &str)]) -> Result<(), Box<dyn Error>> {
+        fs::create_dir_all(root)?;
+        for (path, contents) in files {
+            let path = root.join(path);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(path, contents)?;
+        }
+        Ok(())
+    }
+
+    fn tree(files: &[(&str, &str)]) -> BTreeMap<String, String> {
+        files
+            .iter()
+            .map(|(path, contents)| (path.to_string(), contents.to_string()))
+            .collect()
+    }
+
+    /// Builds the same archive twice, with the files written in a different order and at a
+    /// different time, and returns both.
+    fn build_twice(kind: OutputKind, name: &str) -> Result<(Vec<u8>, Vec<u8>), Box<dyn Error>> {
+        let directory = test_directory(name)?;
+        let files = [
+            ("mods/a.jar", "a"),
+            ("config/b.toml", "b = 1"),
+            ("mods/c.jar", "c"),
+            ("options.txt", "fov:1"),
+        ];
+        let first = write_output(
+            kind,
+            &directory.join("first"),
+            OutputPolicy::FailIfExists,
+            &files,
+        );
+        // Zip timestamps have a resolution of two seconds
+        std::thread::sleep(Duration::from_millis(2100));
+        let mut reversed = files;
+        reversed.reverse();
+        let second = write_output(
+            kind,
+            &directory.join("second"),
+            OutputPolicy::FailIfExists,
+            &reversed,
+        );
+        let result = (|| Ok((fs::read(first?)?, fs::read(second?)?)))();
+        fs::remove_dir_all(&directory)?;
+        result
+    }
+
+    #[test]
+    fn zip_archives_are_reproducible() -> Result<(), Box<dyn Error>> {
+        let (first, second) = build_twice(OutputKind::Zip, "zip")?;
+        assert_eq!(first, second);
+
+        let mut archive = zip::ZipArchive::new(io::Cursor::new(first))?;
+        let names = archive.file_names().map(str::to_string).collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![
+                "config/",
+                "config/b.toml",
+                "mods/",
+                "mods/a.jar",
+                "mods/c.jar",
+                "options.txt"
+            ]
+        );
+        let entry = archive.by_name("mods/a.jar")?;
+        assert_eq!(entry.last_modified(), Some(DateTime::default()));
+        assert_eq!(entry.unix_mode(), Some(0o100644));
+        Ok(())
+    }
+
+    #[test]
+    fn tar_zst_archives_are_reproducible() -> Result<(), Box<dyn Error>> {
+        let (first, second) = build_twice(OutputKind::TarZst, "tar-zst")?;
+        assert_eq!(first, second);
+
+        let mut archive = tar::Archive::new(zstd::Decoder::new(first.as_slice())?);
+        let mut names = Vec::new();
+        for entry in archive.entries()? {
+            let entry = entry?;
+            assert_eq!(entry.header().mtime()?, 0);
+            assert_eq!((entry.header().uid()?, entry.header().gid()?), (0, 0));
+            names.push(entry.path()?.to_string_lossy().to_string());
+        }
+        assert_eq!(
+            names,
+            vec![
+                "config/",
+                "config/b.toml",
+                "mods/",
+                "mods/a.jar",
+                "mods/c.jar",
+                "options.txt"
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn archive_targets_get_their_extension() {
+        assert_eq!(
+            OutputKind::Zip.target_path("packs/My Pack"),
+            PathBuf::from("packs/My Pack.zip")
+        );
+        assert_eq!(
+            OutputKind::TarZst.target_path("packs/My Pack.tar.zst"),
+            PathBuf::from("packs/My Pack.tar.zst")
+        );
+        assert_eq!(
+            OutputKind::Directory.target_path("packs/My Pack"),
+            PathBuf::from("packs/My Pack")
+        );
+    }
+
+    #[test]
+    fn unfinished_output_is_rolled_back() -> Result<(), Box<dyn Error>> {
+        let directory = test_directory("rollback")?;
+        let target = directory.join("pack");
+        create_tree(&target, &[("mods/old.jar", "old")])?;
+
+        let mut sink = create_sink(OutputKind::Directory, &target, OutputPolicy::Overwrite)?;
+        sink.write_file(Path::new("mods/new.jar"), &mut "new".as_bytes())?;
+        let staged = sibling_path(&target, "staging")
+            .join("mods")
+            .join("new.jar")
+            .exists();
+        // A failed or cancelled run drops the sink without finishing it
+        drop(sink);
+
+        let staging_left = sibling_path(&target, "staging").exists();
+        let files = read_tree(&target);
+        fs::remove_dir_all(&directory)?;
+
+        assert!(staged);
+        assert!(!staging_left);
+        assert_eq!(files?, tree(&[("mods/old.jar", "old")]));
+        Ok(())
+    }
+
+    #[test]
+    fn failed_finish_is_rolled_back() -> Result<(), Box<dyn Error>> {
+        let directory = test_directory("failed-finish")?;
+        let target = directory.join("pack");
+        let archive = directory.join("pack.zip");
+
+        let mut sink = create_sink(OutputKind::Directory, &target, OutputPolicy::FailIfExists)?;
+        let mut archive_sink = create_sink(OutputKind::Zip, &target, OutputPolicy::FailIfExists)?;
+        sink.write_file(Path::new("mods/new.jar"), &mut "new".as_bytes())?;
+        archive_sink.write_file(Path::new("mods/new.jar"), &mut "new".as_bytes())?;
+        // Something else creates the outputs while the pack is processed
+        create_tree(&target, &[("mods/other.jar", "other")])?;
+        fs::write(&archive, "other")?;
+        let finished = sink.finish();
+        let archive_finished = archive_sink.finish();
+
+        let leftovers = [
+            sibling_path(&target, "staging"),
+            sibling_path(&archive, "staging"),
+            sibling_path(&archive, "partial"),
+        ]
+        .iter()
+        .any(|path| path.exists());
+        let files = read_tree(&target);
+        let archive_contents = fs::read_to_string(&archive);
+        fs::remove_dir_all(&directory)?;
+
+        assert!(finished.is_err() && archive_finished.is_err());
+        assert!(!leftovers);
+        assert_eq!(files?, tree(&[("mods/other.jar", "other")]));
+        assert_eq!(archive_contents?, "other");
+        Ok(())
+    }
+
+    #[test]
+    fn swap_restores_the_previous_install_if_the_new_one_cant_be_moved(
+    ) -> Result<(), Box<dyn Error>> {
+        let directory = test_directory("swap-fallback")?;
+        let target = directory.join("pack");
+        create_tree(&target, &[("mods/old.jar", "old")])?;
+
+        // A staging directory that is gone makes the final rename fail
+        let swapped = swap_into_place(&directory.join("missing"), &target);
+        let previous_left = sibling_path(&target, "previous").exists();
+        let files = read_tree(&target);
+        fs::remove_dir_all(&directory)?;
+
+        assert!(swapped.is_err());
+        assert!(!previous_left);
+        assert_eq!(files?, tree(&[("mods/old.jar", "old")]));
+        Ok(())
+    }
+
+    #[test]
+    fn swap_replaces_the_previous_install() -> Result<(), Box<dyn Error>> {
+        let directory = test_directory("swap")?;
+        let target = directory.join("pack");
+        let staging = directory.join("staging");
+        create_tree(&target, &[("mods/old.jar", "old")])?;
+        create_tree(&staging, &[("mods/new.jar", "new")])?;
+        // Leftovers of a crashed run
+        create_tree(
+            &sibling_path(&target, "previous"),
+            &[("stale.txt", "stale")],
+        )?;
+
+        swap_into_place(&staging, &target)?;
+        let leftovers = staging.exists() || sibling_path(&target, "previous").exists();
+        let files = read_tree(&target);
+        fs::remove_dir_all(&directory)?;
+
+        assert!(!leftovers);
+        assert_eq!(files?, tree(&[("mods/new.jar", "new")]));
+        Ok(())
+    }
+
+    /// The files of every output in a directory, keyed by the name of the output.
+    type Outputs = BTreeMap<String, BTreeMap<String, String>>;
+
+    /// Installs a pack over an existing install with `policy`.
+    ///
+    /// # Returns
+    ///
+    /// Whether the install succeeded and every output next to the install afterwards.
+    fn install_over_existing(
+        name: &str,
+        policy: OutputPolicy,
+    ) -> Result<(bool, Outputs), Box<dyn Error>> {
+        let directory = test_directory(name)?;
+        let target = directory.join("pack");
+        create_tree(
+            &target,
+            &[("mods/a.jar", "old a"), ("saves/world/level.dat", "world")],
+        )?;
+
+        let installed = write_output(
+            OutputKind::Directory,
+            &target,
+            policy,
+            &[("mods/a.jar", "new a"), ("mods/b.jar", "new b")],
+        );
+        let mut outputs = BTreeMap::new();
+        for entry in fs::read_dir(&directory)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            // The timestamp of a backup changes from run to run
+            let name = match name.split_once(".backup-") {
+                Some((name, _)) => format!("{}.backup", name),
+                None => name,
+            };
+            outputs.insert(name, read_tree(&entry.path())?);
+        }
+        fs::remove_dir_all(&directory)?;
+        Ok((installed.is_ok(), outputs))
+    }
+
+    #[test]
+    fn fail_if_exists_keeps_the_existing_install() -> Result<(), Box<dyn Error>> {
+        let (installed, outputs) = install_over_existing("fail", OutputPolicy::FailIfExists)?;
+
+        assert!(!installed);
+        assert_eq!(
+            outputs,
+            BTreeMap::from([(
+                "pack".to_string(),
+                tree(&[("mods/a.jar", "old a"), ("saves/world/level.dat", "world")])
+            )])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn overwrite_replaces_the_existing_install() -> Result<(), Box<dyn Error>> {
+        let (installed, outputs) = install_over_existing("overwrite", OutputPolicy::Overwrite)?;
+
+        assert!(installed);
+        assert_eq!(
+            outputs,
+            BTreeMap::from([(
+                "pack".to_string(),
+                tree(&[("mods/a.jar", "new a"), ("mods/b.jar", "new b")])
+            )])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn merge_keeps_files_the_pack_doesnt_have() -> Result<(), Box<dyn Error>> {
+        let (installed, outputs) = install_over_existing("merge", OutputPolicy::Merge)?;
+
+        assert!(installed);
+        assert_eq!(
+            outputs,
+            BTreeMap::from([(
+                "pack".to_string(),
+                tree(&[
+                    ("mods/a.jar", "new a"),
+                    ("mods/b.jar", "new b"),
+                    ("saves/world/level.dat", "world")
+                ])
+            )])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn backup_moves_the_existing_install_aside() -> Result<(), Box<dyn Error>> {
+        let (installed, outputs) = install_over_existing("backup", OutputPolicy::Backup)?;
+
+        assert!(installed);
+        assert_eq!(
+            outputs,
+            BTreeMap::from([
+                (
+                    "pack".to_string(),
+                    tree(&[("mods/a.jar", "new a"), ("mods/b.jar", "new b")])
+                ),
+                (
+                    "pack.backup".to_string(),
+                    tree(&[("mods/a.jar", "old a"), ("saves/world/level.dat", "world")])
+                ),
+            ])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn an_empty_directory_is_not_an_existing_install() -> Result<(), Box<dyn Error>> {
+        let directory = test_directory("empty")?;
+        let target = directory.join("pack");
+        fs::create_dir_all(&target)?;
+
+        let installed = write_output(
+            OutputKind::Directory,
+            &target,
+            OutputPolicy::FailIfExists,
+            &[("mods/a.jar", "a")],
+        );
+        let files = read_tree(&target);
+        fs::remove_dir_all(&directory)?;
+
+        assert!(installed.is_ok());
+        assert_eq!(files?, tree(&[("mods/a.jar", "a")]));
+        Ok(())
+    }
+
+    #[test]
+    fn merge_is_only_supported_for_directories() -> Result<(), Box<dyn Error>> {
+        let directory = test_directory("merge-check")?;
+        let file = directory.join("pack.zip");
+        fs::write(&file, "zip")?;
+
+        let zip = OutputPolicy::Merge.check(OutputKind::Zip, &directory.join("other.zip"));
+        let not_a_directory = OutputPolicy::Merge.check(OutputKind::Directory, &file);
+        let missing = OutputPolicy::Merge.check(OutputKind::Directory, &directory.join("new"));
+        fs::remove_dir_all(&directory)?;
+
+        assert!(zip.is_err() && not_a_directory.is_err());
+        assert!(missing.is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn parses_output_policies() {
+        for policy in [
+            OutputPolicy::FailIfExists,
+            OutputPolicy::Overwrite,
+            OutputPolicy::Merge,
+            OutputPolicy::Backup,
+        ] {
+            assert_eq!(policy.to_string().parse::<OutputPolicy>(), Ok(policy));
+        }
+        assert_eq!(
+            "fail".parse::<OutputPolicy>(),
+            Ok(OutputPolicy::FailIfExists)
+        );
+        assert!("replace".parse::<OutputPolicy>().is_err());
+    }
+}
@@ -1,196 +1,406 @@
+use crate::cancellation::{cancellable, check_cancelled, CancellationToken};
+use crate::download_options::DownloadOptions;
+use crate::install_plan::{InstallPlan, PlannedFile};
+use crate::install_record::RecordingSink;
+use crate::output_sink::OutputSink;
 use crate::pack_manifest::Manifest;
-use crate::{ProcessProgressResponse, ProcessStage};
-use log::error;
+use crate::progress::{ProcessEvent, ProgressReporter};
+use crate::safe_path::{sanitize_entry_name, UnsafePathError};
+use crate::server_properties::ServerPropertiesSink;
+use crate::ProcessStage;
+use log::{error, info};
 use std::error::Error;
-use std::fs;
-use std::fs::remove_dir;
+use std::fs::File;
 use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use zip::ZipArchive;
 
-/// Processes a zip archive, extracts it, and downloads mods based on a manifest file.
+/// The name of the manifest file at the root of every CurseForge pack archive.
+const MANIFEST_FILE_NAME: &str = "manifest.json";
+
+/// The overrides folder used when the manifest doesn't specify one.
+const DEFAULT_OVERRIDES_DIRECTORY: &str = "overrides";
+
+/// What was written by `PackArchive::extract_overrides`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ExtractedOverrides {
+    /// The number of files written.
+    pub files: u64,
+    /// The total size of the files written, in bytes.
+    pub bytes: u64,
+}
+
+/// A CurseForge pack archive that is read in place, without extracting it to disk first.
+pub struct PackArchive {
+    archive: ZipArchive<File>,
+}
+
+impl PackArchive {
+    /// Opens a pack archive.
+    ///
+    /// # Parameters
+    /// - `zip_path`: The path to the pack archive.
+    ///
+    /// # Returns
+    /// A `Result` containing the opened `PackArchive` or an error if the file isn't a readable zip.
+    pub fn open(zip_path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        let zip_file = File::open(zip_path)?;
+        let archive = ZipArchive::new(zip_file)?;
+        Ok(Self { archive })
+    }
+
+    /// Reads the manifest directly out of the archive.
+    ///
+    /// # Returns
+    /// A `Result` containing the parsed `Manifest` or an error if it is missing or malformed.
+    pub fn read_manifest(&mut self) -> Result<Manifest, Box<dyn Error>> {
+        let manifest_file = self.archive.by_name(MANIFEST_FILE_NAME).map_err(|err| {
+            error!("Manifest file not found: {}", err);
+            "Manifest file not found"
+        })?;
+        Manifest::from_reader(manifest_file)
+    }
+
+    /// Streams the override entries straight into the output.
+    ///
+    /// The overrides prefix is stripped from every entry, so `overrides/config/foo.toml`
+    /// ends up as `config/foo.toml` in the output. Every entry name is sanitized before
+    /// anything is written, so entries with absolute paths, `..` components, reserved device
+    /// names or symbolic links are rejected with an `UnsafePathError`.
+    ///
+    /// # Parameters
+    /// - `overrides`: The name of the overrides folder inside the archive.
+    /// - `sink`: The output the overrides are written to.
+    /// - `cancellation_token`: Stops the extraction when cancelled.
+    ///
+    /// # Returns
+    /// A `Result` containing the number of files and bytes written, or an error.
+    pub fn extract_overrides(
+        &mut self,
+        overrides: impl AsRef<str>,
+        sink: &mut dyn OutputSink,
+        cancellation_token: &CancellationToken,
+    ) -> Result<ExtractedOverrides, Box<dyn Error>> {
+        let overrides = sanitize_entry_name(overrides)?;
+        let mut extracted = ExtractedOverrides::default();
+
+        for index in 0..self.archive.len() {
+            check_cancelled(cancellation_token)?;
+            let mut entry = self.archive.by_index(index)?;
+            let relative = match override_path(entry.name(), entry.is_symlink(), &overrides)? {
+                Some(relative) => relative,
+                None => continue,
+            };
+
+            if entry.is_dir() {
+                sink.create_dir(&relative)?;
+                continue;
+            }
+
+            extracted.bytes += sink.write_file(&relative, &mut entry)?;
+            extracted.files += 1;
+        }
+
+        Ok(extracted)
+    }
+
+    /// Lists the override files `extract_overrides` would write, without writing anything.
+    ///
+    /// Entries are checked the same way, so an unsafe archive fails here too.
+    ///
+    /// # Parameters
+    /// - `overrides`: The name of the overrides folder inside the archive.
+    ///
+    /// # Returns
+    /// A `Result` containing every override file with its path in the output and its size.
+    pub fn list_overrides(
+        &mut self,
+        overrides: impl AsRef<str>,
+    ) -> Result<Vec<PlannedFile>, Box<dyn Error>> {
+        let overrides = sanitize_entry_name(overrides)?;
+        let mut files = Vec::new();
+
+        for index in 0..self.archive.len() {
+            let entry = self.archive.by_index(index)?;
+            match override_path(entry.name(), entry.is_symlink(), &overrides)? {
+                Some(path) if !entry.is_dir() => files.push(PlannedFile {
+                    path,
+                    size: entry.size(),
+                }),
+                _ => {}
+            }
+        }
+
+        Ok(files)
+    }
+}
+
+/// Maps an archive entry to its path in the output.
 ///
-/// This asynchronous function first extracts a specified zip archive to a temporary directory.
-/// It verifies the presence of a manifest file within the extracted contents and subsequently
-/// utilizes this manifest to manage the download of various mods. If any stage encounters an
-/// error (like failing to extract, find the manifest, or download mods), the function logs the
-/// error and will terminate the process.
+/// # Returns
+/// The path relative to the output root, `None` for entries outside of the overrides,
+/// or an `UnsafePathError` for entries that must not be extracted.
+fn override_path(
+    name: &str,
+    is_symlink: bool,
+    overrides: &Path,
+) -> Result<Option<PathBuf>, Box<dyn Error>> {
+    let relative = sanitize_entry_name(name)?;
+
+    if is_symlink {
+        error!("Refusing to extract symbolic link '{}'", name);
+        return Err(UnsafePathError::Symlink(name.to_string()).into());
+    }
+
+    // Skip anything that isn't part of the overrides, like the manifest itself.
+    Ok(match relative.strip_prefix(overrides) {
+        Ok(relative) if !relative.as_os_str().is_empty() => Some(relative.to_path_buf()),
+        _ => None,
+    })
+}
+
+/// Processes a pack archive and downloads mods based on its manifest.
+///
+/// The manifest is read directly from the archive and the overrides are streamed straight into
+/// the output sink. Mods are downloaded into the temporary directory and moved into the
+/// output once complete, so the temporary directory only ever holds in-flight downloads.
+/// An `InstallRecord` of every written file is added to the output.
 ///
 /// # Parameters
 /// - `zip_path`: A reference to the path where the zip archive file is located.
-/// - `parallel`: The number of concurrent download operations for mods.
-/// - `validate`: A boolean flag indicating whether the downloaded mods should be validated.
-/// - `validate_if_size_less_than`: An optional size threshold (in bytes) under which files are validated.
-/// - `temp_dir`: A reference to the path of the temporary directory where the archive is extracted.
+/// - `create_output`: Creates the output sink from the manifest of the archive.
+/// - `options`: The download settings. Cancelling `options.cancellation_token` stops processing
+///   and nothing is written to the output.
+/// - `reporter`: Receives the stage and per-mod events.
 ///
 /// # Returns
 /// On success, returns a `Result` containing a tuple:
-/// - `PathBuf`: The path to the extracted contents.
-/// - `Manifest`: The manifest object created from the manifest file.
+/// - `PathBuf`: The path to the finished output directory or archive.
+/// - `Manifest`: The manifest object read from the archive.
 ///
 /// # Errors
-/// - Will log an error and exit the process if the zip extraction fails or if the manifest file is not found.
-/// - Will log an error, remove the mods directory, and exit the process if downloading mods fails.
-pub async fn process_archive<F>(
+/// Returns an error if the archive can't be read, the manifest is missing, an entry is unsafe,
+/// or downloading mods fails. Returns `Cancelled` if the token is cancelled.
+pub async fn process_archive<O>(
     zip_path: impl AsRef<Path>,
-    parallel: u8,
-    validate: bool,
-    validate_if_size_less_than: Option<u64>,
-    temp_dir: impl AsRef<Path>,
-    mut on_progress: F,
+    create_output: O,
+    options: &DownloadOptions,
+    reporter: &ProgressReporter,
 ) -> Result<(PathBuf, Manifest), Box<dyn Error>>
 where
-    F: FnMut(ProcessProgressResponse) + 'static + Send + Sync,
+    O: FnOnce(&Manifest) -> Result<Box<dyn OutputSink>, Box<dyn Error>>,
 {
-    on_progress(ProcessProgressResponse {
+    reporter.emit(ProcessEvent::StageStarted {
         stage: ProcessStage::ExtractingArchive,
-        progress: 0.1f32,
-        message: "Extracting pack archive".to_string(),
     });
 
-    // Attempt to extract the zip archive into a temporary directory.
-    // The `extract_zip` function returns a `Result` containing
-    // the path to the extracted files or an error if the extraction fails.
-    let path = match extract_zip(zip_path, temp_dir) {
-        // Successful extraction, store the resulting path.
-        Ok(path) => path,
-        // Log an error message and exit the process if extraction fails.
-        Err(err) => {
-            error!("Failed to extract zip archive: {}", err);
-            std::process::exit(1);
-        }
-    };
-
-    // Define the path to the manifest file within the extracted folder.
-    let manifest_file = path.join("manifest.json");
-
-    // Check for the existence of the manifest file.
-    // Log an error and exit if the file is not found.
-    if !manifest_file.exists() {
-        error!("Manifest file not found!");
-        std::process::exit(1);
+    let mut archive = PackArchive::open(zip_path).map_err(|err| {
+        error!("Failed to open pack archive: {}", err);
+        err
+    })?;
+    let manifest = archive.read_manifest()?;
+    // Record every file that is written, so the install can be verified later
+    let mut sink: Box<dyn OutputSink> =
+        Box::new(RecordingSink::new(create_output(&manifest)?, &manifest));
+    // A server gets the settings of the pack content, like the world, merged into its properties
+    if options.server {
+        sink = Box::new(ServerPropertiesSink::new(sink));
     }
 
-    // Attempt to create a new `Manifest` from the manifest file.
-    // The `Manifest::new` function can return a `Result`.
-    // The `?` operator is used to automatically handle the error scenario.
-    let manifest = Manifest::new(manifest_file)?;
+    // Stream the overrides into the output without touching the temp directory.
+    let overrides = manifest
+        .overrides
+        .clone()
+        .unwrap_or(DEFAULT_OVERRIDES_DIRECTORY.to_string());
+    let extracted = archive
+        .extract_overrides(&overrides, sink.as_mut(), &options.cancellation_token)
+        .map_err(|err| {
+            error!("Failed to extract overrides: {}", err);
+            err
+        })?;
+    info!(
+        "Extracted {} override files ({} bytes)",
+        extracted.files, extracted.bytes
+    );
+    reporter.emit(ProcessEvent::OverridesCopied {
+        files: extracted.files,
+        bytes: extracted.bytes,
+    });
+    reporter.emit(ProcessEvent::StageFinished {
+        stage: ProcessStage::ExtractingArchive,
+    });
 
     // Attempt to download mods based on the information in the manifest.
-    if let Err(e) = manifest
-        .download_mods(
-            &path,
-            parallel,
-            validate,
-            validate_if_size_less_than,
-            move |progress| {
-                let mods_downloaded_percentage: f32 =
-                    progress.downloaded as f32 / progress.total as f32;
-                on_progress(ProcessProgressResponse {
-                    stage: ProcessStage::DownloadingMods,
-                    message: format!(
-                        "Downloading {} of {} mods",
-                        progress.downloaded, progress.total
-                    ),
-                    progress: (0.25f32 + mods_downloaded_percentage) / 1.25f32,
-                })
-            },
-        )
+    reporter.emit(ProcessEvent::StageStarted {
+        stage: ProcessStage::DownloadingMods,
+    });
+    let sink = Mutex::new(sink);
+    manifest
+        .download_mods(&sink, options, reporter)
         .await
-    {
-        // Attempt to remove the mods directory if downloading fails.
-        if remove_dir(path).is_err() {
-            error!("Failed to remove mods directory after failure");
-        }
-        // Log an error and terminate if mod downloads fail.
-        error!("Failed to download mods: {}", e);
-        std::process::exit(1);
-    }
+        .map_err(|err| {
+            error!("Failed to download mods: {}", err);
+            err
+        })?;
+    reporter.emit(ProcessEvent::StageFinished {
+        stage: ProcessStage::DownloadingMods,
+    });
 
-    // Return a successful result containing the path and manifest.
-    Ok((path, manifest))
+    // Finalize the output, e.g. pack the archive
+    check_cancelled(&options.cancellation_token)?;
+    reporter.emit(ProcessEvent::StageStarted {
+        stage: ProcessStage::Finalizing,
+    });
+    let sink = sink.into_inner().map_err(|_| "Output sink lock poisoned")?;
+    let output = sink.finish().map_err(|err| {
+        error!("Failed to finalize output: {}", err);
+        err
+    })?;
+    reporter.emit(ProcessEvent::OutputWritten {
+        path: output.clone(),
+    });
+    reporter.emit(ProcessEvent::StageFinished {
+        stage: ProcessStage::Finalizing,
+    });
+
+    // Return a successful result containing the output path and manifest.
+    Ok((output, manifest))
 }
 
-/// Extracts a zip file to a temporary directory.
-/// Parameters:
-/// - `zip_path`: The path to the zip file to be extracted.
-/// - `temp_dir`: The directory where the contents of the zip will be extracted.
+/// Plans the install of a pack archive without downloading or writing anything.
+///
+/// The manifest and the override list are read from the archive, the mods are resolved with
+/// the batch endpoints of the API.
 ///
-/// Returns a `Result` with the path to the temporary directory or an error.
-fn extract_zip(
+/// # Parameters
+/// - `zip_path`: A reference to the path where the zip archive file is located.
+/// - `target`: Resolves the output path from the manifest of the archive.
+/// - `options`: The download settings, only the client, the optional mods policy and the
+///   cancellation token are used.
+///
+/// # Returns
+/// The `InstallPlan`, or an error if the archive can't be read or the API can't be reached.
+pub async fn plan_archive<T>(
     zip_path: impl AsRef<Path>,
-    temp_dir: impl AsRef<Path>,
-) -> Result<PathBuf, Box<dyn Error>> {
-    let temp_dir = temp_dir.as_ref();
+    target: T,
+    options: &DownloadOptions,
+) -> Result<InstallPlan, Box<dyn Error>>
+where
+    T: FnOnce(&Manifest) -> Result<PathBuf, Box<dyn Error>>,
+{
+    let mut archive = PackArchive::open(zip_path).map_err(|err| {
+        error!("Failed to open pack archive: {}", err);
+        err
+    })?;
+    let manifest = archive.read_manifest()?;
+    let overrides = archive.list_overrides(
+        manifest
+            .overrides
+            .as_deref()
+            .unwrap_or(DEFAULT_OVERRIDES_DIRECTORY),
+    )?;
 
-    // Open the zip file.
-    let zip_file = fs::File::open(zip_path)?;
+    let output = target(&manifest)?;
+    cancellable(
+        &options.cancellation_token,
+        InstallPlan::resolve(
+            &manifest,
+            output,
+            overrides,
+            &options.optional_mods,
+            &options.rules,
+            &options.paths,
+            &options.client,
+        ),
+    )
+    .await?
+}
 
-    // Create a ZipArchive from the file.
-    let mut zip_archive = zip::ZipArchive::new(zip_file)?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+    use zip::ZipWriter;
 
-    // Extract the contents of the zip archive into the temporary directory.
-    zip_archive.extract(temp_dir.join(""))?;
+    /// Writes a pack archive with the given files and symbolic links to a temporary file.
+    fn write_archive(
+        name: &str,
+        files: &[&str],
+        symlinks: &[(&str, &str)],
+    ) -> Result<PathBuf, Box<dyn Error>> {
+        let path = std::env::temp_dir().join(format!("{}-{}.zip", name, std::process::id()));
+        let mut writer = ZipWriter::new(File::create(&path)?);
+        for file in files {
+            writer.start_file(*file, SimpleFileOptions::default())?;
+            writer.write_all(b"content")?;
+        }
+        for (link, target) in symlinks {
+            writer.add_symlink(*link, *target, SimpleFileOptions::default())?;
+        }
+        writer.finish()?;
+        Ok(path)
+    }
 
-    // Return the path to the temporary directory.
-    Ok(temp_dir.to_path_buf())
-}
+    #[test]
+    fn lists_overrides_without_the_prefix() -> Result<(), Box<dyn Error>> {
+        let path = write_archive(
+            "pack-archive-overrides",
+            &["manifest.json", "overrides/config/a.toml", "other/b.txt"],
+            &[],
+        )?;
+        let files = PackArchive::open(&path)?.list_overrides("overrides");
+        std::fs::remove_file(&path)?;
 
-/// Copies files from mod and override directories to an output directory.
-/// Parameters:
-/// - `overrides_dir`: The directory containing override files.
-/// - `output_path`: The directory where files will be copied to.
-///
-/// Returns a `Result` with the path to the output directory or an error.
-pub fn copy_to_output(
-    overrides_dir: impl AsRef<Path>,
-    output_path: impl AsRef<Path>,
-) -> Result<PathBuf, Box<dyn Error>> {
-    // Ensure the output directory exists, creating it if necessary.
-    fs::create_dir_all(&output_path)?;
-
-    // Create a directory for mods inside the output directory.
-    let new_mods = output_path.as_ref().join("mods");
-    fs::create_dir_all(&new_mods)?;
-
-    // Recursively copy the overrides directory, if it exists.
-    if overrides_dir.as_ref().exists() {
-        copy_dir_recursive(overrides_dir, &output_path)?;
+        let paths: Vec<PathBuf> = files?.into_iter().map(|file| file.path).collect();
+        assert_eq!(paths, vec![Path::new("config").join("a.toml")]);
+        Ok(())
     }
 
-    // Return the path to the output directory.
-    Ok(output_path.as_ref().to_path_buf())
-}
+    #[test]
+    fn rejects_symlink_entries() -> Result<(), Box<dyn Error>> {
+        let path = write_archive(
+            "pack-archive-symlink",
+            &["overrides/config/a.toml"],
+            &[("overrides/mods", "/home")],
+        )?;
+        let files = PackArchive::open(&path)?.list_overrides("overrides");
+        std::fs::remove_file(&path)?;
 
-/// Recursively copies a directory and its contents to another location.
-/// Parameters:
-/// - `src`: The source directory to copy from.
-/// - `dest`: The destination directory to copy to.
-///
-/// Returns a `Result` indicating success or an I/O error.
-fn copy_dir_recursive(src: impl AsRef<Path>, dest: impl AsRef<Path>) -> std::io::Result<()> {
-    let src = src.as_ref();
-    let dest = dest.as_ref();
-
-    if src.is_dir() {
-        // Ensure the destination directory exists, creating it if necessary.
-        fs::create_dir_all(dest)?;
-
-        // Iterate over the entries in the source directory.
-        for entry in fs::read_dir(src)? {
-            let entry = entry?;
-            let file_type = entry.file_type()?;
-            let src_path = entry.path();
-            let dest_path = dest.join(entry.file_name());
-
-            // Recursively copy subdirectories.
-            if file_type.is_dir() {
-                copy_dir_recursive(&src_path, &dest_path)?;
-            } else {
-                // Copy files from the source to the destination.
-                fs::copy(&src_path, &dest_path)?;
-            }
-        }
+        let error = files.err().ok_or("symbolic link was accepted")?;
+        assert_eq!(
+            error.downcast_ref::<UnsafePathError>(),
+            Some(&UnsafePathError::Symlink("overrides/mods".to_string()))
+        );
+        Ok(())
     }
 
-    // Return success.
-    Ok(())
+    #[test]
+    fn rejects_overrides_prefix_with_parent_traversal() -> Result<(), Box<dyn Error>> {
+        let path = write_archive("pack-archive-prefix", &["overrides/config/a.toml"], &[])?;
+        let files = PackArchive::open(&path)?.list_overrides("overrides/../..");
+        std::fs::remove_file(&path)?;
+
+        let error = files.err().ok_or("unsafe overrides prefix was accepted")?;
+        assert!(matches!(
+            error.downcast_ref::<UnsafePathError>(),
+            Some(UnsafePathError::ParentTraversal(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn override_path_checks_the_entry_before_the_prefix() {
+        let overrides = Path::new("overrides");
+        assert!(override_path("../overrides/a.jar", false, overrides).is_err());
+        assert!(override_path("overrides/a.jar", true, overrides).is_err());
+        assert!(matches!(
+            override_path("manifest.json", false, overrides),
+            Ok(None)
+        ));
+        assert!(matches!(
+            override_path("overrides/", false, overrides),
+            Ok(None)
+        ));
+    }
 }
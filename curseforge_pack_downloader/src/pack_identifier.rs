@@ -0,0 +1,242 @@
+use crate::curseforge_api::search_projects;
+use crate::curseforge_client::CurseforgeClient;
+use crate::search::{SearchQuery, SearchResponse};
+use log::info;
+use std::error::Error;
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// A way to refer to a modpack: a project ID, a slug or a CurseForge URL.
+///
+/// Parsed from strings like:
+///
+/// - `925200`
+/// - `all-the-mods-10`
+/// - `https://www.curseforge.com/minecraft/modpacks/all-the-mods-10`
+/// - `https://www.curseforge.com/minecraft/modpacks/all-the-mods-10/files/5921373`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PackIdentifier {
+    /// A numeric project ID.
+    ProjectId(u64),
+    /// The slug of a project, the last part of its CurseForge URL.
+    Slug {
+        slug: String,
+        /// The file of a `/files/<fileId>` link, if any.
+        file_id: Option<u64>,
+    },
+}
+
+impl Display for PackIdentifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PackIdentifier::ProjectId(project_id) => write!(f, "{}", project_id),
+            PackIdentifier::Slug {
+                slug,
+                file_id: Some(file_id),
+            } => write!(f, "{} (file {})", slug, file_id),
+            PackIdentifier::Slug {
+                slug,
+                file_id: None,
+            } => write!(f, "{}", slug),
+        }
+    }
+}
+
+impl FromStr for PackIdentifier {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Ok(project_id) = s.parse::<u64>() {
+            return Ok(PackIdentifier::ProjectId(project_id));
+        }
+        if s.contains('/') {
+            return parse_url(s);
+        }
+        if is_slug(s) {
+            return Ok(PackIdentifier::Slug {
+                slug: s.to_ascii_lowercase(),
+                file_id: None,
+            });
+        }
+        Err(format!(
+            "'{}' is not a project ID, slug or CurseForge URL",
+            s
+        ))
+    }
+}
+
+impl PackIdentifier {
+    /// Looks up the project ID of the pack.
+    ///
+    /// Slugs are resolved with the `slug` filter of the search endpoint,
+    /// a project ID is returned without any request.
+    ///
+    /// # Parameters
+    ///
+    /// - `client`: The client to use for sending requests.
+    ///
+    /// # Returns
+    ///
+    /// The project ID and the file ID of a `/files/<fileId>` link, if any.
+    pub async fn resolve(
+        &self,
+        client: &CurseforgeClient,
+    ) -> Result<(u64, Option<u64>), Box<dyn Error>> {
+        let (slug, file_id) = match self {
+            PackIdentifier::ProjectId(project_id) => return Ok((*project_id, None)),
+            PackIdentifier::Slug { slug, file_id } => (slug, *file_id),
+        };
+
+        let query = SearchQuery {
+            slug: Some(slug.clone()),
+            ..SearchQuery::default()
+        };
+        let response: SearchResponse = search_projects(&query, client).await?;
+        let project_id = response
+            .data
+            .iter()
+            .filter(|project| project.slug.as_deref() == Some(slug.as_str()))
+            .find_map(|project| project.id)
+            .and_then(|id| u64::try_from(id).ok())
+            .ok_or(format!("No modpack found with the slug '{}'", slug))?;
+
+        info!("Resolved '{}' to project {}", slug, project_id);
+        Ok((project_id, file_id))
+    }
+}
+
+/// Parses a CurseForge project URL, the scheme and `www.` are optional.
+fn parse_url(url: &str) -> Result<PackIdentifier, String> {
+    let invalid = || format!("'{}' is not a CurseForge modpack URL", url);
+
+    let without_scheme = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))
+        .unwrap_or(url);
+    // Ignore the query and fragment, e.g. `?page=2` or `#files`
+    let without_query = without_scheme
+        .split(['?', '#'])
+        .next()
+        .unwrap_or(without_scheme);
+    let mut segments = without_query
+        .split('/')
+        .filter(|segment| !segment.is_empty());
+
+    let host = segments.next().ok_or_else(invalid)?.to_ascii_lowercase();
+    if host != "curseforge.com" && !host.ends_with(".curseforge.com") {
+        return Err(invalid());
+    }
+
+    match segments.collect::<Vec<_>>().as_slice() {
+        // Old style links, e.g. `curseforge.com/projects/925200`
+        ["projects", project_id, ..] => project_id
+            .parse::<u64>()
+            .map(PackIdentifier::ProjectId)
+            .map_err(|_| invalid()),
+        [_game, _class, slug, rest @ ..] if is_slug(slug) => {
+            let file_id = match rest {
+                [] => None,
+                // `/files/<fileId>` is the file page, `/download/<fileId>` its download page
+                ["files" | "download", file_id, ..] => {
+                    Some(file_id.parse::<u64>().map_err(|_| invalid())?)
+                }
+                // Other tabs of the project page, like `/files` or `/description`
+                [_] => None,
+                _ => return Err(invalid()),
+            };
+            Ok(PackIdentifier::Slug {
+                slug: slug.to_ascii_lowercase(),
+                file_id,
+            })
+        }
+        _ => Err(invalid()),
+    }
+}
+
+/// Checks that `s` only contains the characters CurseForge allows in slugs.
+fn is_slug(s: &str) -> bool {
+    !s.is_empty()
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn slug(slug: &str, file_id: Option<u64>) -> PackIdentifier {
+        PackIdentifier::Slug {
+            slug: slug.to_string(),
+            file_id,
+        }
+    }
+
+    #[test]
+    fn parses_project_ids() {
+        assert_eq!("925200".parse(), Ok(PackIdentifier::ProjectId(925200)));
+        assert_eq!(" 925200\n".parse(), Ok(PackIdentifier::ProjectId(925200)));
+    }
+
+    #[test]
+    fn parses_slugs() {
+        assert_eq!("all-the-mods-10".parse(), Ok(slug("all-the-mods-10", None)));
+        assert_eq!("All_The_Mods".parse(), Ok(slug("all_the_mods", None)));
+        assert!("".parse::<PackIdentifier>().is_err());
+        assert!("all the mods".parse::<PackIdentifier>().is_err());
+    }
+
+    #[test]
+    fn parses_project_urls() {
+        let expected = Ok(slug("all-the-mods-10", None));
+        for url in [
+            "https://www.curseforge.com/minecraft/modpacks/all-the-mods-10",
+            "http://www.curseforge.com/minecraft/modpacks/all-the-mods-10/",
+            "www.curseforge.com/minecraft/modpacks/all-the-mods-10",
+            "curseforge.com/minecraft/modpacks/All-The-Mods-10",
+            "https://WWW.CurseForge.com/minecraft/modpacks/all-the-mods-10?page=2#files",
+            "https://www.curseforge.com/minecraft/modpacks/all-the-mods-10/files",
+            "https://www.curseforge.com/minecraft/modpacks/all-the-mods-10/description",
+        ] {
+            assert_eq!(url.parse(), expected, "{}", url);
+        }
+        assert_eq!(
+            "https://www.curseforge.com/projects/925200".parse(),
+            Ok(PackIdentifier::ProjectId(925200))
+        );
+    }
+
+    #[test]
+    fn parses_file_urls() {
+        let expected = Ok(slug("all-the-mods-10", Some(5921373)));
+        for url in [
+            "https://www.curseforge.com/minecraft/modpacks/all-the-mods-10/files/5921373",
+            "https://www.curseforge.com/minecraft/modpacks/all-the-mods-10/download/5921373",
+            "https://www.curseforge.com/minecraft/modpacks/all-the-mods-10/files/5921373/",
+        ] {
+            assert_eq!(url.parse(), expected, "{}", url);
+        }
+    }
+
+    #[test]
+    fn rejects_other_urls() {
+        for url in [
+            "https://example.com/minecraft/modpacks/all-the-mods-10",
+            "https://curseforge.com.example.com/minecraft/modpacks/all-the-mods-10",
+            "https://www.curseforge.com/minecraft/modpacks",
+            "https://www.curseforge.com/minecraft/modpacks/all the mods",
+            "https://www.curseforge.com/minecraft/modpacks/all-the-mods-10/files/latest",
+            "https://www.curseforge.com/projects/all-the-mods-10",
+            "https://",
+        ] {
+            assert!(url.parse::<PackIdentifier>().is_err(), "{}", url);
+        }
+    }
+
+    #[test]
+    fn displays_the_identifier() {
+        assert_eq!(PackIdentifier::ProjectId(1).to_string(), "1");
+        assert_eq!(slug("atm", None).to_string(), "atm");
+        assert_eq!(slug("atm", Some(2)).to_string(), "atm (file 2)");
+    }
+}
@@ -1,9 +1,13 @@
-use crate::curseforge_api::ModDownloadProgressResponse;
+use crate::download_options::DownloadOptions;
+use crate::output_sink::OutputSink;
+use crate::progress::ProgressReporter;
 use serde::{Deserialize, Serialize};
 use std::error::Error;
+use std::io::Read;
 use std::path::Path;
+use std::sync::Mutex;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct ModItem {
     #[serde(rename = "projectID")]
     pub project_id: i64,
@@ -12,12 +16,41 @@ pub struct ModItem {
     pub required: bool,
 }
 
-#[derive(Serialize, Deserialize)]
+/// A mod loader the pack runs on, e.g. `forge-47.2.0`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ModLoaderItem {
+    pub id: String,
+    #[serde(default)]
+    pub primary: bool,
+}
+
+/// The Minecraft version and mod loaders of the pack.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MinecraftInfo {
+    pub version: String,
+    #[serde(default)]
+    pub mod_loaders: Vec<ModLoaderItem>,
+}
+
+impl MinecraftInfo {
+    /// The loader marked as primary, or the first one if none is.
+    pub fn primary_mod_loader(&self) -> Option<&ModLoaderItem> {
+        self.mod_loaders
+            .iter()
+            .find(|loader| loader.primary)
+            .or(self.mod_loaders.first())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
 pub struct Manifest {
     pub name: String,
     pub version: Option<String>,
     pub author: Option<String>,
+    pub minecraft: Option<MinecraftInfo>,
     pub files: Vec<ModItem>,
+    pub overrides: Option<String>,
 }
 
 impl Manifest {
@@ -36,48 +69,48 @@ impl Manifest {
         // Return an error if the file cannot be opened.
         let file = std::fs::File::open(path)?;
 
-        // Deserialize the contents of the file into a Manifest struct.
+        Self::from_reader(file)
+    }
+
+    /// Creates a new `Manifest` by reading it from any reader, e.g. an entry of a zip archive.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The reader containing the manifest json.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Self)` if the manifest was successfully deserialized.
+    /// * `Err` if the contents could not be deserialized.
+    pub fn from_reader(reader: impl Read) -> Result<Self, Box<dyn Error>> {
+        // Deserialize the contents of the reader into a Manifest struct.
         // Return an error if deserialization fails.
-        let manifest: Manifest = serde_json::from_reader(file)?;
+        let manifest: Manifest = serde_json::from_reader(reader)?;
 
         // Return the deserialized Manifest.
         Ok(manifest)
     }
 
-    /// Downloads mods listed in the manifest to the specified directory.
+    /// Downloads mods listed in the manifest to the specified output.
     ///
     /// # Arguments
     ///
-    /// * `directory` - The path to the directory where mods should be downloaded.
-    /// * `parallel` - The number of downloads that can occur simultaneously.
-    /// * `validate` - A boolean flag to indicate if mods should be validated after downloading.
-    /// * `validate_if_size_less_than` - An optional size limit below which mods should be validated.
+    /// * `sink` - The output the mods are placed in.
+    /// * `options` - The download settings, like parallelism, validation and cancellation.
+    /// * `reporter` - Receives an event for every step of every mod download.
     ///
     /// # Returns
     ///
     /// * `Ok(())` if mods were successfully downloaded.
     /// * `Err` if there was an error during the download or validation process.
-    pub async fn download_mods<F>(
+    pub async fn download_mods(
         &self,
-        directory: impl AsRef<Path>,
-        parallel: u8,
-        validate: bool,
-        validate_if_size_less_than: Option<u64>,
-        on_progress: F,
-    ) -> Result<(), Box<dyn Error>>
-    where
-        F: FnMut(ModDownloadProgressResponse) + 'static + Send + Sync,
-    {
+        sink: &Mutex<Box<dyn OutputSink>>,
+        options: &DownloadOptions,
+        reporter: &ProgressReporter,
+    ) -> Result<(), Box<dyn Error>> {
         // Call the function to download mods based on the current manifest.
         // This operation is performed asynchronously.
-        crate::curseforge_api::download_mods_from_manifest(
-            self,
-            directory,
-            parallel,
-            validate,
-            validate_if_size_less_than,
-            on_progress,
-        )
-        .await
+        crate::curseforge_api::download_mods_from_manifest(self, sink, options, reporter).await
     }
 }
@@ -0,0 +1,87 @@
+use crate::process_result::ProcessResult;
+use crate::ProcessProgressResponse;
+use futures::future::BoxFuture;
+use futures::Stream;
+use log::warn;
+use std::error::Error;
+use std::future::Future;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::broadcast::{self, Sender};
+
+/// How many progress events are buffered per subscriber.
+/// Subscribers that fall further behind skip the oldest events.
+const EVENT_CHANNEL_CAPACITY: usize = 1024;
+
+/// A pack that is about to be processed, returned by `CurseforgePackDownloader::process_id_stream`
+/// and `CurseforgePackDownloader::process_file_stream`.
+///
+/// Nothing happens until `result` is awaited, so every subscriber created before that receives
+/// every event. Any number of consumers (a UI, a log file, metrics) can subscribe to the same run.
+pub struct ProcessHandle<'a> {
+    sender: Sender<ProcessProgressResponse>,
+    result: BoxFuture<'a, Result<ProcessResult, Box<dyn Error>>>,
+}
+
+impl<'a> ProcessHandle<'a> {
+    /// Creates a handle around a processing future.
+    ///
+    /// # Parameters
+    ///
+    /// - `process`: Builds the processing future from the progress callback it should report to.
+    pub(crate) fn new<P, R>(process: P) -> Self
+    where
+        P: FnOnce(Box<dyn Fn(ProcessProgressResponse) + Send + Sync>) -> R,
+        R: Future<Output = Result<ProcessResult, Box<dyn Error>>> + Send + 'a,
+    {
+        let (sender, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+        let events = sender.clone();
+        let result = process(Box::new(move |progress| {
+            // Sending only fails if nobody is subscribed, which is fine
+            let _ = events.send(progress);
+        }));
+        Self {
+            sender,
+            result: Box::pin(result),
+        }
+    }
+
+    /// Subscribes to the progress events of this run.
+    ///
+    /// The stream ends once processing has finished, successfully or not.
+    ///
+    /// # Returns
+    ///
+    /// A `Stream` of every `ProcessProgressResponse` emitted after subscribing.
+    pub fn subscribe(&self) -> impl Stream<Item = ProcessProgressResponse> + Send + Unpin {
+        Box::pin(futures::stream::unfold(
+            self.sender.subscribe(),
+            |mut receiver| async move {
+                loop {
+                    match receiver.recv().await {
+                        Ok(progress) => return Some((progress, receiver)),
+                        Err(RecvError::Lagged(skipped)) => {
+                            warn!(
+                                "Progress subscriber fell behind, skipped {} events",
+                                skipped
+                            );
+                        }
+                        // Every sender is gone, so processing has finished
+                        Err(RecvError::Closed) => return None,
+                    }
+                }
+            },
+        ))
+    }
+
+    /// Runs the processing to completion.
+    ///
+    /// # Returns
+    ///
+    /// The same result `process_id`/`process_file` would have returned.
+    pub async fn result(self) -> Result<ProcessResult, Box<dyn Error>> {
+        // Drop our sender so the streams close as soon as the run drops the last one
+        let Self { sender, result } = self;
+        drop(sender);
+        result.await
+    }
+}
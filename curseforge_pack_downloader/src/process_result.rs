@@ -0,0 +1,296 @@
+use crate::download_sources::DownloadSource;
+use crate::mod_rules::AppliedRule;
+use crate::pack_manifest::Manifest;
+use crate::progress::ProcessEvent;
+use crate::ProcessStage;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Instant;
+
+/// The pack that was processed.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PackInfo {
+    pub name: String,
+    pub version: Option<String>,
+    pub author: Option<String>,
+    /// The CurseForge project of the pack, `None` for a local archive.
+    pub project_id: Option<u64>,
+    /// The file of the pack version that was installed, `None` for a local archive.
+    pub file_id: Option<u64>,
+    pub minecraft_version: Option<String>,
+    /// The primary mod loader, e.g. `forge-47.2.0`.
+    pub mod_loader: Option<String>,
+}
+
+/// How a single mod of the pack ended up.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ModStatus {
+    /// The mod was resolved, but never finished.
+    Pending,
+    Completed,
+    Failed,
+    Skipped,
+}
+
+/// The result of a single mod of the pack.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ModResult {
+    pub project_id: u64,
+    pub file_id: u64,
+    pub name: Option<String>,
+    pub file_name: Option<String>,
+    pub status: ModStatus,
+    /// The path relative to the output root.
+    pub path: Option<PathBuf>,
+    pub bytes: Option<u64>,
+    /// Whether the hash matched, `None` if the file wasn't validated.
+    pub validated: Option<bool>,
+    /// The error of a failed mod or the reason a mod was skipped.
+    pub message: Option<String>,
+    /// The CurseForge page of a mod that had to be downloaded by hand.
+    pub url: Option<String>,
+    /// Where the file was taken from, `None` if it wasn't installed.
+    pub source: Option<DownloadSource>,
+    pub duration_ms: Option<u64>,
+}
+
+/// How long a stage took.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct StageTiming {
+    pub stage: ProcessStage,
+    pub duration_ms: u64,
+}
+
+/// Everything a successful run did, returned by `CurseforgePackDownloader::process_id` and friends.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ProcessResult {
+    /// Where the pack was written, with the variables of the output path replaced,
+    /// e.g. the directory or the archive file.
+    pub output: PathBuf,
+    pub pack: PackInfo,
+    pub mods: Vec<ModResult>,
+    pub override_files: u64,
+    pub override_bytes: u64,
+    /// The bytes of every mod written to the output.
+    pub downloaded_bytes: u64,
+    /// Every rule of the `ModRules` that changed the install.
+    pub rules: Vec<AppliedRule>,
+    pub stages: Vec<StageTiming>,
+    pub duration_ms: u64,
+    /// The manifest read from the pack archive.
+    #[serde(skip)]
+    pub manifest: Manifest,
+}
+
+/// Builds a `ProcessResult` from the events of a run.
+pub(crate) struct ProcessRecorder {
+    started: Instant,
+    project_id: Option<u64>,
+    file_id: Option<u64>,
+    mods: Vec<ModResult>,
+    override_files: u64,
+    override_bytes: u64,
+    downloaded_bytes: u64,
+    rules: Vec<AppliedRule>,
+    stages: Vec<StageTiming>,
+    stages_started: Vec<(ProcessStage, Instant)>,
+    mod_indexes: HashMap<(u64, u64), usize>,
+    mods_started: HashMap<(u64, u64), Instant>,
+}
+
+impl ProcessRecorder {
+    /// Creates an empty recorder, the duration is measured from now.
+    pub(crate) fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            project_id: None,
+            file_id: None,
+            mods: Vec::new(),
+            override_files: 0,
+            override_bytes: 0,
+            downloaded_bytes: 0,
+            rules: Vec::new(),
+            stages: Vec::new(),
+            stages_started: Vec::new(),
+            mod_indexes: HashMap::new(),
+            mods_started: HashMap::new(),
+        }
+    }
+
+    /// Records the CurseForge project and file the pack archive was downloaded from.
+    pub(crate) fn set_pack_file(&mut self, project_id: u64, file_id: Option<u64>) {
+        self.project_id = Some(project_id);
+        self.file_id = file_id;
+    }
+
+    /// Updates the result with a progress event.
+    pub(crate) fn record(&mut self, event: &ProcessEvent) {
+        match event {
+            ProcessEvent::StageStarted { stage } => {
+                self.stages_started.push((stage.clone(), Instant::now()));
+            }
+            ProcessEvent::StageFinished { stage } => {
+                if let Some(index) = self.stages_started.iter().position(|(s, _)| s == stage) {
+                    let (stage, started) = self.stages_started.remove(index);
+                    self.stages.push(StageTiming {
+                        stage,
+                        duration_ms: millis(started),
+                    });
+                }
+            }
+            ProcessEvent::ModResolved {
+                project_id,
+                file_id,
+                name,
+                file_name,
+                size,
+            } => {
+                let result = self.mod_result(*project_id, *file_id);
+                result.name = Some(name.clone());
+                result.file_name = Some(file_name.clone());
+                result.bytes = result.bytes.or(*size);
+            }
+            ProcessEvent::ModDownloadStarted {
+                project_id,
+                file_id,
+                file_name,
+            } => {
+                self.mods_started
+                    .insert((*project_id, *file_id), Instant::now());
+                let result = self.mod_result(*project_id, *file_id);
+                result.file_name = Some(file_name.clone());
+            }
+            ProcessEvent::ModDownloadProgress { .. } => {}
+            ProcessEvent::ModDownloadCompleted {
+                project_id,
+                file_id,
+                path,
+                bytes,
+                source,
+            } => {
+                self.downloaded_bytes += bytes;
+                let duration_ms = self.mod_duration(*project_id, *file_id);
+                let result = self.mod_result(*project_id, *file_id);
+                result.status = ModStatus::Completed;
+                result.path = Some(path.clone());
+                result.bytes = Some(*bytes);
+                result.source = Some(source.clone());
+                result.duration_ms = duration_ms;
+            }
+            ProcessEvent::ModDownloadFailed {
+                project_id,
+                file_id,
+                error,
+            } => {
+                let duration_ms = self.mod_duration(*project_id, *file_id);
+                let result = self.mod_result(*project_id, *file_id);
+                result.status = ModStatus::Failed;
+                result.message = Some(error.clone());
+                result.duration_ms = duration_ms;
+            }
+            ProcessEvent::ManualDownloadRequired {
+                project_id,
+                file_id,
+                file_name,
+                url,
+            } => {
+                let result = self.mod_result(*project_id, *file_id);
+                result.file_name = Some(file_name.clone());
+                result.url = Some(url.clone());
+            }
+            ProcessEvent::ModSkipped {
+                project_id,
+                file_id,
+                reason,
+            } => {
+                let result = self.mod_result(*project_id, *file_id);
+                result.status = ModStatus::Skipped;
+                result.message = Some(reason.clone());
+            }
+            ProcessEvent::ModValidated {
+                project_id,
+                file_id,
+                passed,
+            } => {
+                self.mod_result(*project_id, *file_id).validated = Some(*passed);
+            }
+            ProcessEvent::OverridesCopied { files, bytes } => {
+                self.override_files = *files;
+                self.override_bytes = *bytes;
+            }
+            ProcessEvent::OutputWritten { .. } => {}
+            ProcessEvent::RuleApplied { rule } => {
+                self.rules.push(rule.clone());
+            }
+        }
+    }
+
+    /// Completes the result with the output and the manifest of the processed pack.
+    pub(crate) fn finish(self, output: PathBuf, manifest: Manifest) -> ProcessResult {
+        let minecraft = manifest.minecraft.as_ref();
+        ProcessResult {
+            output,
+            pack: PackInfo {
+                name: manifest.name.clone(),
+                version: manifest.version.clone(),
+                author: manifest.author.clone(),
+                project_id: self.project_id,
+                file_id: self.file_id,
+                minecraft_version: minecraft.map(|minecraft| minecraft.version.clone()),
+                mod_loader: minecraft
+                    .and_then(|minecraft| minecraft.primary_mod_loader())
+                    .map(|loader| loader.id.clone()),
+            },
+            mods: self.mods,
+            override_files: self.override_files,
+            override_bytes: self.override_bytes,
+            downloaded_bytes: self.downloaded_bytes,
+            rules: self.rules,
+            stages: self.stages,
+            duration_ms: millis(self.started),
+            manifest,
+        }
+    }
+
+    /// The result of a mod, created on first use.
+    fn mod_result(&mut self, project_id: u64, file_id: u64) -> &mut ModResult {
+        let mods = &mut self.mods;
+        let index = *self
+            .mod_indexes
+            .entry((project_id, file_id))
+            .or_insert_with(|| {
+                mods.push(ModResult {
+                    project_id,
+                    file_id,
+                    name: None,
+                    file_name: None,
+                    status: ModStatus::Pending,
+                    path: None,
+                    bytes: None,
+                    validated: None,
+                    message: None,
+                    url: None,
+                    source: None,
+                    duration_ms: None,
+                });
+                mods.len() - 1
+            });
+        &mut self.mods[index]
+    }
+
+    /// The time since the download of a mod started, if it did.
+    fn mod_duration(&mut self, project_id: u64, file_id: u64) -> Option<u64> {
+        self.mods_started.remove(&(project_id, file_id)).map(millis)
+    }
+}
+
+/// The milliseconds elapsed since `started`.
+fn millis(started: Instant) -> u64 {
+    u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX)
+}
@@ -0,0 +1,352 @@
+use crate::download_sources::DownloadSource;
+use crate::mod_rules::AppliedRule;
+use crate::pack_manifest::Manifest;
+use crate::process_result::{ProcessRecorder, ProcessResult};
+use crate::{ProcessProgressResponse, ProcessStage};
+use log::error;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// A single thing that happened while processing a pack.
+///
+/// Every mod event carries the project and file ID, so a UI can keep one row per mod
+/// and update it as events arrive.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum ProcessEvent {
+    /// A stage of the process has started.
+    StageStarted { stage: ProcessStage },
+    /// A stage of the process has finished successfully.
+    StageFinished { stage: ProcessStage },
+    /// The project and file information of a mod has been fetched from the API.
+    /// `size` is `None` if the API didn't report the file size.
+    ModResolved {
+        project_id: u64,
+        file_id: u64,
+        name: String,
+        file_name: String,
+        size: Option<u64>,
+    },
+    /// The download of a mod file has started.
+    ModDownloadStarted {
+        project_id: u64,
+        file_id: u64,
+        file_name: String,
+    },
+    /// Part of a mod file has been downloaded.
+    /// `total` is `None` if neither the API nor the server reported the file size.
+    ModDownloadProgress {
+        project_id: u64,
+        file_id: u64,
+        downloaded: u64,
+        total: Option<u64>,
+    },
+    /// A mod file has been downloaded and written to the output.
+    ModDownloadCompleted {
+        project_id: u64,
+        file_id: u64,
+        /// The path of the file relative to the output root.
+        path: PathBuf,
+        bytes: u64,
+        /// Where the file was taken from.
+        source: DownloadSource,
+    },
+    /// A mod could not be downloaded. Processing continues with the remaining mods, but the run
+    /// fails with `ModsFailed` before the output is finalized.
+    ModDownloadFailed {
+        project_id: u64,
+        file_id: u64,
+        error: String,
+    },
+    /// A mod can't be downloaded automatically, it is picked up once the user downloads it
+    /// from `url` into the watch directory of the `ManualDownloads`.
+    ManualDownloadRequired {
+        project_id: u64,
+        file_id: u64,
+        file_name: String,
+        url: String,
+    },
+    /// A mod was intentionally not downloaded.
+    ModSkipped {
+        project_id: u64,
+        file_id: u64,
+        reason: String,
+    },
+    /// A downloaded mod file has been checked against the hash reported by the API.
+    ModValidated {
+        project_id: u64,
+        file_id: u64,
+        passed: bool,
+    },
+    /// The overrides of the pack have been written to the output.
+    OverridesCopied { files: u64, bytes: u64 },
+    /// The output has been finalized at `path`, e.g. the directory or the archive file.
+    OutputWritten { path: PathBuf },
+    /// A rule of the `ModRules` changed the mods of the install.
+    RuleApplied { rule: AppliedRule },
+}
+
+impl Display for ProcessEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProcessEvent::StageStarted { stage } => write!(f, "{}", stage),
+            ProcessEvent::StageFinished { stage } => write!(f, "{} finished", stage),
+            ProcessEvent::ModResolved {
+                name, file_name, ..
+            } => write!(f, "Resolved {} ({})", name, file_name),
+            ProcessEvent::ModDownloadStarted { file_name, .. } => {
+                write!(f, "Downloading {}", file_name)
+            }
+            ProcessEvent::ModDownloadProgress {
+                file_id,
+                downloaded,
+                total,
+                ..
+            } => match total {
+                Some(total) => write!(
+                    f,
+                    "Downloading {}: {} of {} bytes",
+                    file_id, downloaded, total
+                ),
+                None => write!(f, "Downloading {}: {} bytes", file_id, downloaded),
+            },
+            ProcessEvent::ModDownloadCompleted { path, .. } => {
+                write!(f, "Downloaded {}", path.display())
+            }
+            ProcessEvent::ModDownloadFailed { file_id, error, .. } => {
+                write!(f, "Failed to download {}: {}", file_id, error)
+            }
+            ProcessEvent::ManualDownloadRequired { file_name, url, .. } => {
+                write!(f, "Download {} manually from {}", file_name, url)
+            }
+            ProcessEvent::ModSkipped {
+                file_id, reason, ..
+            } => {
+                write!(f, "Skipped {}: {}", file_id, reason)
+            }
+            ProcessEvent::ModValidated {
+                file_id, passed, ..
+            } => match passed {
+                true => write!(f, "{} passed validation", file_id),
+                false => write!(f, "{} failed validation", file_id),
+            },
+            ProcessEvent::OverridesCopied { files, bytes } => {
+                write!(f, "Copied {} override files ({} bytes)", files, bytes)
+            }
+            ProcessEvent::OutputWritten { path } => {
+                write!(f, "Pack written to {}", path.display())
+            }
+            ProcessEvent::RuleApplied { rule } => write!(f, "{}", rule),
+        }
+    }
+}
+
+/// The part of the overall progress each stage covers, as `(start, end)`.
+///
+/// Downloading mods takes by far the longest, so it gets most of the bar.
+/// Processing a local file starts at `ExtractingArchive`, so the bar jumps to 0.1 at the start.
+fn stage_range(stage: &ProcessStage) -> (f32, f32) {
+    match stage {
+        ProcessStage::DownloadingArchive => (0.0, 0.1),
+        ProcessStage::ExtractingArchive => (0.1, 0.2),
+        ProcessStage::DownloadingMods => (0.2, 0.95),
+        ProcessStage::Finalizing => (0.95, 1.0),
+    }
+}
+
+struct ReporterState {
+    stage: ProcessStage,
+    progress: f32,
+    total_mods: u64,
+    finished_mods: u64,
+    recorder: ProcessRecorder,
+}
+
+/// Turns `ProcessEvent`s into `ProcessProgressResponse`s for the progress callback.
+///
+/// The reporter keeps track of the current stage and how many mods have finished, so the
+/// overall progress can be derived from the events alone. It also records every event for the
+/// `ProcessResult` of the run. It is shared by reference between all concurrent downloads.
+pub struct ProgressReporter {
+    on_progress: Box<dyn Fn(ProcessProgressResponse) + Send + Sync>,
+    state: Mutex<ReporterState>,
+}
+
+impl ProgressReporter {
+    /// Creates a new reporter.
+    ///
+    /// # Parameters
+    ///
+    /// - `on_progress`: Called for every event, together with the overall progress.
+    pub fn new<F>(on_progress: F) -> Self
+    where
+        F: Fn(ProcessProgressResponse) + 'static + Send + Sync,
+    {
+        Self {
+            on_progress: Box::new(on_progress),
+            state: Mutex::new(ReporterState {
+                stage: ProcessStage::DownloadingArchive,
+                progress: 0.0,
+                total_mods: 0,
+                finished_mods: 0,
+                recorder: ProcessRecorder::new(),
+            }),
+        }
+    }
+
+    /// Sets the number of mods that will be downloaded, used to compute the mod progress.
+    pub fn set_total_mods(&self, total_mods: usize) {
+        if let Ok(mut state) = self.state.lock() {
+            state.total_mods = total_mods as u64;
+            state.finished_mods = 0;
+        }
+    }
+
+    /// Sets the CurseForge project and file the pack archive was downloaded from.
+    pub fn set_pack_file(&self, project_id: u64, file_id: Option<u64>) {
+        if let Ok(mut state) = self.state.lock() {
+            state.recorder.set_pack_file(project_id, file_id);
+        }
+    }
+
+    /// Builds the result of the run from every event reported so far.
+    ///
+    /// # Parameters
+    ///
+    /// - `output`: The path of the finished directory or archive.
+    /// - `manifest`: The manifest of the processed pack.
+    ///
+    /// # Returns
+    ///
+    /// The `ProcessResult`, or an error if the reporter lock is poisoned.
+    pub(crate) fn finish(
+        &self,
+        output: PathBuf,
+        manifest: Manifest,
+    ) -> Result<ProcessResult, Box<dyn Error>> {
+        let mut state = self
+            .state
+            .lock()
+            .map_err(|_| "Progress reporter lock poisoned")?;
+        let recorder = std::mem::replace(&mut state.recorder, ProcessRecorder::new());
+        Ok(recorder.finish(output, manifest))
+    }
+
+    /// Reports an event to the progress callback.
+    ///
+    /// # Parameters
+    ///
+    /// - `event`: The event that happened.
+    pub fn emit(&self, event: ProcessEvent) {
+        // The callback runs without the lock, so it may query the reporter or emit itself
+        let response = match self.update(event) {
+            Some(response) => response,
+            None => return,
+        };
+        (self.on_progress)(response);
+    }
+
+    /// Records an event and computes what is reported for it.
+    ///
+    /// # Returns
+    ///
+    /// The response for the callback, or `None` if the reporter lock is poisoned.
+    fn update(&self, event: ProcessEvent) -> Option<ProcessProgressResponse> {
+        let mut state = match self.state.lock() {
+            Ok(state) => state,
+            Err(_) => {
+                error!("Progress reporter lock poisoned, dropping event: {}", event);
+                return None;
+            }
+        };
+
+        state.recorder.record(&event);
+        let mut message = event.to_string();
+        let progress = match &event {
+            ProcessEvent::StageStarted { stage } => {
+                state.stage = stage.clone();
+                stage_range(stage).0
+            }
+            ProcessEvent::StageFinished { stage } => stage_range(stage).1,
+            ProcessEvent::ModDownloadCompleted { .. }
+            | ProcessEvent::ModDownloadFailed { .. }
+            | ProcessEvent::ModSkipped { .. } => {
+                state.finished_mods += 1;
+                message = format!(
+                    "{} ({} of {} mods)",
+                    message, state.finished_mods, state.total_mods
+                );
+
+                let (start, end) = stage_range(&ProcessStage::DownloadingMods);
+                let finished = state.finished_mods as f32 / state.total_mods.max(1) as f32;
+                start + (end - start) * finished.min(1.0)
+            }
+            _ => state.progress,
+        };
+
+        // Never move the bar backwards, e.g. when a stage is skipped
+        state.progress = state.progress.max(progress);
+
+        Some(ProcessProgressResponse {
+            stage: state.stage.clone(),
+            progress: state.progress,
+            message,
+            event,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, OnceLock};
+
+    #[test]
+    fn callbacks_can_use_the_reporter() {
+        let reporter = Arc::new(OnceLock::<Arc<ProgressReporter>>::new());
+        let messages = Arc::new(Mutex::new(Vec::new()));
+
+        let callback_reporter = reporter.clone();
+        let callback_messages = messages.clone();
+        let progress_reporter = Arc::new(ProgressReporter::new(move |response| {
+            if let Ok(mut messages) = callback_messages.lock() {
+                messages.push((response.progress, response.message));
+            }
+            // Emitting again from the callback must not deadlock
+            if let (Some(reporter), ProcessEvent::StageStarted { .. }) =
+                (callback_reporter.get(), &response.event)
+            {
+                reporter.set_total_mods(1);
+                reporter.emit(ProcessEvent::ModSkipped {
+                    project_id: 1,
+                    file_id: 2,
+                    reason: "Excluded".to_string(),
+                });
+            }
+        }));
+        assert!(reporter.set(progress_reporter.clone()).is_ok());
+
+        progress_reporter.emit(ProcessEvent::StageStarted {
+            stage: ProcessStage::DownloadingMods,
+        });
+
+        let messages = messages
+            .lock()
+            .map(|messages| messages.clone())
+            .unwrap_or_default();
+        assert_eq!(messages.len(), 2);
+        assert!(
+            messages[1].1.ends_with("(1 of 1 mods)"),
+            "{}",
+            messages[1].1
+        );
+        assert!(messages[1].0 > messages[0].0);
+    }
+}
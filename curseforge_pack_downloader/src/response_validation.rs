@@ -0,0 +1,275 @@
+use reqwest::header::CONTENT_TYPE;
+use reqwest::Response;
+use std::error::Error;
+use std::fmt::Display;
+use std::fs::File;
+use std::path::Path;
+use zip::ZipArchive;
+
+/// The extensions of files that have to be zip archives, e.g. mods and resource packs.
+const ARCHIVE_EXTENSIONS: [&str; 2] = ["jar", "zip"];
+
+/// The reasons a response or a downloaded file is rejected, so an error page or a truncated
+/// download never ends up in the output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResponseError {
+    /// The server answered with a status outside of 2xx.
+    Status { url: String, status: String },
+    /// The content type doesn't match what was requested, e.g. an HTML page instead of a file.
+    ContentType {
+        url: String,
+        content_type: String,
+        expected: ExpectedContent,
+    },
+    /// The download is shorter or longer than the size the API reports.
+    Length { expected: u64, actual: u64 },
+    /// A `.jar` or `.zip` file without a readable central directory.
+    NotAnArchive { file_name: String, error: String },
+}
+
+impl Display for ResponseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResponseError::Status { url, status } => {
+                write!(f, "Request to {} failed with {}", url, status)
+            }
+            ResponseError::ContentType {
+                url,
+                content_type,
+                expected,
+            } => write!(
+                f,
+                "Expected {} from {}, got content type '{}'",
+                expected, url, content_type
+            ),
+            ResponseError::Length { expected, actual } => {
+                write!(f, "Expected {} bytes, got {}", expected, actual)
+            }
+            ResponseError::NotAnArchive { file_name, error } => {
+                write!(f, "'{}' is not a valid archive: {}", file_name, error)
+            }
+        }
+    }
+}
+
+impl Error for ResponseError {}
+
+/// What a request is expected to return.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExpectedContent {
+    /// A response of the CurseForge API.
+    Json,
+    /// A file download, anything but a document like an HTML error page.
+    File,
+}
+
+impl Display for ExpectedContent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExpectedContent::Json => write!(f, "JSON"),
+            ExpectedContent::File => write!(f, "a file"),
+        }
+    }
+}
+
+impl ExpectedContent {
+    /// Whether a content type fits, a missing content type only fits a file.
+    fn accepts(&self, content_type: Option<&str>) -> bool {
+        let mime = content_type
+            .and_then(|content_type| content_type.split(';').next())
+            .map(|mime| mime.trim().to_ascii_lowercase());
+        match (self, mime) {
+            (ExpectedContent::Json, Some(mime)) => mime.ends_with("json"),
+            (ExpectedContent::Json, None) => false,
+            // Servers answer a missing file with an error page, sometimes with a success status
+            (ExpectedContent::File, Some(mime)) => {
+                !mime.starts_with("text/") && !mime.ends_with("json") && !mime.ends_with("xml")
+            }
+            (ExpectedContent::File, None) => true,
+        }
+    }
+}
+
+/// Checks the status and content type of a response before its body is read.
+///
+/// # Arguments
+///
+/// * `response` - The response to check.
+/// * `expected` - What the request is expected to return.
+///
+/// # Returns
+///
+/// The response if it has a 2xx status and a fitting content type, or a `ResponseError`.
+pub(crate) fn check_response(
+    response: Response,
+    expected: ExpectedContent,
+) -> Result<Response, ResponseError> {
+    if !response.status().is_success() {
+        return Err(ResponseError::Status {
+            url: response.url().to_string(),
+            status: response.status().to_string(),
+        });
+    }
+
+    let content_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok());
+    if !expected.accepts(content_type) {
+        return Err(ResponseError::ContentType {
+            url: response.url().to_string(),
+            content_type: content_type.unwrap_or("none").to_string(),
+            expected,
+        });
+    }
+    Ok(response)
+}
+
+/// Checks the size of a download against the size the API reports.
+///
+/// # Arguments
+///
+/// * `expected` - The size the API reports, not checked if `None`.
+/// * `actual` - The number of bytes that were downloaded.
+pub(crate) fn check_length(expected: Option<u64>, actual: u64) -> Result<(), ResponseError> {
+    match expected {
+        Some(expected) if expected != actual => Err(ResponseError::Length { expected, actual }),
+        _ => Ok(()),
+    }
+}
+
+/// Checks that a `.jar` or `.zip` file has a readable central directory, a cheap way to catch
+/// corrupted downloads that would otherwise crash the game at launch.
+/// Files with other extensions are not checked.
+///
+/// # Arguments
+///
+/// * `path` - The downloaded file.
+pub fn check_archive_structure(path: impl AsRef<Path>) -> Result<(), ResponseError> {
+    let path = path.as_ref();
+    let is_archive = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            ARCHIVE_EXTENSIONS
+                .iter()
+                .any(|archive| extension.eq_ignore_ascii_case(archive))
+        });
+    if !is_archive {
+        return Ok(());
+    }
+
+    let not_an_archive = |error: String| ResponseError::NotAnArchive {
+        file_name: path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        error,
+    };
+    let file = File::open(path).map_err(|err| not_an_archive(err.to_string()))?;
+    ZipArchive::new(file).map_err(|err| not_an_archive(err.to_string()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::path::PathBuf;
+    use zip::write::SimpleFileOptions;
+    use zip::ZipWriter;
+
+    /// A fresh directory below the system temp directory, unique per test.
+    fn test_directory(name: &str) -> Result<PathBuf, Box<dyn Error>> {
+        let directory = std::env::temp_dir().join(format!(
+            "response-validation-{}-{}",
+            name,
+            std::process::id()
+        ));
+        if directory.exists() {
+            std::fs::remove_dir_all(&directory)?;
+        }
+        std::fs::create_dir_all(&directory)?;
+        Ok(directory)
+    }
+
+    /// A zip archive with a single file.
+    fn zip_bytes() -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut writer = ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        writer.start_file("META-INF/MANIFEST.MF", SimpleFileOptions::default())?;
+        writer.write_all(b"Manifest-Version: 1.0\n")?;
+        Ok(writer.finish()?.into_inner())
+    }
+
+    #[test]
+    fn files_reject_documents() {
+        let file = ExpectedContent::File;
+        for content_type in [
+            "text/html; charset=utf-8",
+            "TEXT/PLAIN",
+            "application/json",
+            "application/problem+json",
+            "application/xml",
+        ] {
+            assert!(!file.accepts(Some(content_type)), "{}", content_type);
+        }
+        for content_type in [
+            "application/java-archive",
+            "application/octet-stream",
+            "application/zip",
+        ] {
+            assert!(file.accepts(Some(content_type)), "{}", content_type);
+        }
+        assert!(file.accepts(None));
+    }
+
+    #[test]
+    fn json_requires_a_json_content_type() {
+        let json = ExpectedContent::Json;
+        assert!(json.accepts(Some("application/json; charset=utf-8")));
+        assert!(!json.accepts(Some("text/html")));
+        assert!(!json.accepts(Some("application/octet-stream")));
+        assert!(!json.accepts(None));
+    }
+
+    #[test]
+    fn rejects_short_and_long_bodies() {
+        assert_eq!(
+            check_length(Some(1024), 512),
+            Err(ResponseError::Length {
+                expected: 1024,
+                actual: 512
+            })
+        );
+        assert!(check_length(Some(1024), 2048).is_err());
+        assert_eq!(check_length(Some(1024), 1024), Ok(()));
+        assert_eq!(check_length(None, 512), Ok(()));
+    }
+
+    #[test]
+    fn rejects_jars_that_arent_archives() -> Result<(), Box<dyn Error>> {
+        let directory = test_directory("archives")?;
+        let archive = zip_bytes()?;
+        let files = [
+            ("intact.jar", archive.clone()),
+            ("truncated.jar", archive[..archive.len() / 2].to_vec()),
+            ("error-page.JAR", b"<!DOCTYPE html><html></html>".to_vec()),
+            ("empty.zip", Vec::new()),
+            ("config.toml", b"not checked".to_vec()),
+        ];
+        let mut results = Vec::new();
+        for (name, contents) in &files {
+            let path = directory.join(name);
+            std::fs::write(&path, contents)?;
+            results.push(check_archive_structure(&path));
+        }
+        std::fs::remove_dir_all(&directory)?;
+
+        assert!(results[0].is_ok());
+        for result in &results[1..4] {
+            assert!(matches!(result, Err(ResponseError::NotAnArchive { .. })));
+        }
+        assert!(results[4].is_ok());
+        Ok(())
+    }
+}
@@ -0,0 +1,392 @@
+use std::error::Error;
+use std::fmt::Display;
+use std::path::{Component, Path, PathBuf};
+
+/// Names that Windows reserves for devices, regardless of extension or case.
+/// Writing to `NUL.jar` or `con.txt` on Windows talks to a device instead of creating a file.
+const RESERVED_DEVICE_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// The reasons a path coming from an untrusted source (a pack archive or the CurseForge API)
+/// can be rejected before anything is written to disk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UnsafePathError {
+    /// The path is empty or only consists of `.` components.
+    Empty,
+    /// The path is absolute or carries a drive/UNC prefix.
+    Absolute(String),
+    /// The path contains a `..` component.
+    ParentTraversal(String),
+    /// A component is a reserved device name such as `CON` or `NUL`.
+    DeviceName(String),
+    /// A component contains characters that are not allowed in file names.
+    InvalidComponent(String),
+    /// A file name was expected, but the value contains path separators.
+    NotAFileName(String),
+    /// The archive entry is a symbolic link.
+    Symlink(String),
+    /// The path resolves through a symbolic link to a location outside the root directory.
+    SymlinkEscape(String),
+}
+
+impl Display for UnsafePathError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UnsafePathError::Empty => write!(f, "Path is empty"),
+            UnsafePathError::Absolute(path) => write!(f, "Path '{}' is absolute", path),
+            UnsafePathError::ParentTraversal(path) => {
+                write!(f, "Path '{}' contains a parent directory component", path)
+            }
+            UnsafePathError::DeviceName(path) => {
+                write!(f, "Path '{}' contains a reserved device name", path)
+            }
+            UnsafePathError::InvalidComponent(path) => {
+                write!(f, "Path '{}' contains invalid characters", path)
+            }
+            UnsafePathError::NotAFileName(path) => write!(f, "'{}' is not a plain file name", path),
+            UnsafePathError::Symlink(path) => write!(f, "Entry '{}' is a symbolic link", path),
+            UnsafePathError::SymlinkEscape(path) => {
+                write!(
+                    f,
+                    "Path '{}' escapes the root directory through a symbolic link",
+                    path
+                )
+            }
+        }
+    }
+}
+
+impl Error for UnsafePathError {}
+
+/// Converts an untrusted, `/` or `\` separated entry name into a relative path.
+///
+/// Both separators are accepted because pack archives built on Windows frequently use `\`.
+/// Empty and `.` components are dropped.
+///
+/// # Arguments
+///
+/// * `name` - The entry name as stored in an archive or returned by the API.
+///
+/// # Returns
+///
+/// A relative `PathBuf` that is guaranteed to stay inside whatever root it is joined to,
+/// or an `UnsafePathError` describing why the name was rejected.
+pub fn sanitize_entry_name(name: impl AsRef<str>) -> Result<PathBuf, UnsafePathError> {
+    let name = name.as_ref();
+
+    // Leading separators make the path absolute on every platform we care about.
+    if name.starts_with('/') || name.starts_with('\\') {
+        return Err(UnsafePathError::Absolute(name.to_string()));
+    }
+
+    let mut path = PathBuf::new();
+    for (index, component) in name.split(['/', '\\']).enumerate() {
+        if component.is_empty() || component == "." {
+            continue;
+        }
+        if component == ".." {
+            return Err(UnsafePathError::ParentTraversal(name.to_string()));
+        }
+        // A drive letter such as `C:` is only meaningful as the first component.
+        if index == 0 && is_drive_prefix(component) {
+            return Err(UnsafePathError::Absolute(name.to_string()));
+        }
+        check_component(component, name)?;
+        path.push(component);
+    }
+
+    if path.as_os_str().is_empty() {
+        return Err(UnsafePathError::Empty);
+    }
+
+    Ok(path)
+}
+
+/// Validates a file name returned by the API so it can be joined onto a directory.
+///
+/// # Arguments
+///
+/// * `name` - The file name to validate.
+///
+/// # Returns
+///
+/// The file name if it is a single, plain component, or an `UnsafePathError` otherwise.
+pub fn sanitize_file_name(name: &str) -> Result<&str, UnsafePathError> {
+    if name.contains(['/', '\\']) {
+        return Err(UnsafePathError::NotAFileName(name.to_string()));
+    }
+    sanitize_entry_name(name)?;
+    Ok(name)
+}
+
+/// Validates an on-disk relative path, e.g. one produced by walking a directory.
+///
+/// # Arguments
+///
+/// * `path` - The relative path to validate.
+///
+/// # Returns
+///
+/// The normalized relative path, or an `UnsafePathError` if it is absolute or traverses upwards.
+pub fn sanitize_relative_path(path: impl AsRef<Path>) -> Result<PathBuf, UnsafePathError> {
+    let path = path.as_ref();
+    let display = path.to_string_lossy().to_string();
+
+    let mut result = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Prefix(_) | Component::RootDir => {
+                return Err(UnsafePathError::Absolute(display));
+            }
+            Component::ParentDir => return Err(UnsafePathError::ParentTraversal(display)),
+            Component::CurDir => continue,
+            Component::Normal(part) => {
+                let part = part
+                    .to_str()
+                    .ok_or_else(|| UnsafePathError::InvalidComponent(display.clone()))?;
+                check_component(part, &display)?;
+                result.push(part);
+            }
+        }
+    }
+
+    if result.as_os_str().is_empty() {
+        return Err(UnsafePathError::Empty);
+    }
+
+    Ok(result)
+}
+
+/// Joins an untrusted relative path onto a root directory.
+///
+/// The relative path is sanitized first, then every already existing ancestor of the
+/// resulting path is checked so that a symbolic link cannot redirect the write outside `root`.
+///
+/// # Arguments
+///
+/// * `root` - The trusted directory that everything must stay inside of.
+/// * `relative` - The untrusted relative path.
+///
+/// # Returns
+///
+/// The joined path, or an `UnsafePathError` if the path is unsafe.
+pub fn safe_join(
+    root: impl AsRef<Path>,
+    relative: impl AsRef<Path>,
+) -> Result<PathBuf, UnsafePathError> {
+    let root = root.as_ref();
+    let relative = sanitize_relative_path(relative)?;
+
+    // If the root does not exist yet, nothing below it can be a symbolic link.
+    let canonical_root = match root.canonicalize() {
+        Ok(path) => path,
+        Err(_) => return Ok(root.join(relative)),
+    };
+
+    let mut current = root.to_path_buf();
+    for component in relative.components() {
+        current.push(component);
+
+        let metadata = match current.symlink_metadata() {
+            Ok(metadata) => metadata,
+            // The rest of the path does not exist yet, so it will be created as real directories.
+            Err(_) => break,
+        };
+
+        if metadata.file_type().is_symlink() {
+            // Resolve the link and make sure it still points somewhere inside the root.
+            let escapes = match current.canonicalize() {
+                Ok(target) => !target.starts_with(&canonical_root),
+                Err(_) => true,
+            };
+            if escapes {
+                return Err(UnsafePathError::SymlinkEscape(
+                    relative.to_string_lossy().to_string(),
+                ));
+            }
+        }
+    }
+
+    Ok(root.join(relative))
+}
+
+/// Checks a single path component for device names and characters that are never valid.
+fn check_component(component: &str, full: &str) -> Result<(), UnsafePathError> {
+    if component.contains(['\0', ':']) {
+        return Err(UnsafePathError::InvalidComponent(full.to_string()));
+    }
+
+    // Device names are reserved with any extension and ignore trailing dots and spaces.
+    let stem = component
+        .split('.')
+        .next()
+        .unwrap_or(component)
+        .trim_end_matches([' ', '.']);
+    if RESERVED_DEVICE_NAMES
+        .iter()
+        .any(|device| device.eq_ignore_ascii_case(stem))
+    {
+        return Err(UnsafePathError::DeviceName(full.to_string()));
+    }
+
+    Ok(())
+}
+
+/// Returns true if the component looks like a Windows drive prefix, e.g. `C:`.
+fn is_drive_prefix(component: &str) -> bool {
+    let bytes = component.as_bytes();
+    bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_relative_names_with_either_separator() {
+        assert_eq!(
+            sanitize_entry_name("overrides\\config/./foo.toml"),
+            Ok(PathBuf::from("overrides").join("config").join("foo.toml"))
+        );
+        assert_eq!(
+            sanitize_entry_name("mods//a.jar"),
+            Ok(PathBuf::from("mods").join("a.jar"))
+        );
+    }
+
+    #[test]
+    fn rejects_parent_traversal() {
+        for name in [
+            "../evil.jar",
+            "mods/../../evil.jar",
+            "mods\\..\\evil.jar",
+            "..",
+        ] {
+            assert_eq!(
+                sanitize_entry_name(name),
+                Err(UnsafePathError::ParentTraversal(name.to_string())),
+                "{}",
+                name
+            );
+        }
+        assert!(matches!(
+            sanitize_relative_path(Path::new("mods").join("..").join("evil.jar")),
+            Err(UnsafePathError::ParentTraversal(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_absolute_paths() {
+        for name in ["/etc/passwd", "\\Windows\\evil.dll"] {
+            assert_eq!(
+                sanitize_entry_name(name),
+                Err(UnsafePathError::Absolute(name.to_string()))
+            );
+        }
+        assert!(matches!(
+            sanitize_relative_path(std::env::temp_dir().join("evil.jar")),
+            Err(UnsafePathError::Absolute(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_drive_and_unc_prefixes() {
+        for name in ["C:\\Windows\\evil.dll", "c:/evil.jar", "C:evil.jar"] {
+            assert_eq!(
+                sanitize_entry_name(name),
+                Err(UnsafePathError::Absolute(name.to_string())),
+                "{}",
+                name
+            );
+        }
+        for name in ["\\\\server\\share\\evil.jar", "//server/share/evil.jar"] {
+            assert_eq!(
+                sanitize_entry_name(name),
+                Err(UnsafePathError::Absolute(name.to_string()))
+            );
+        }
+        // A colon anywhere else is an alternate data stream on Windows
+        assert_eq!(
+            sanitize_entry_name("mods/a.jar:stream"),
+            Err(UnsafePathError::InvalidComponent(
+                "mods/a.jar:stream".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn rejects_reserved_device_names() {
+        for name in ["CON", "nul.txt", "mods/Com1.jar", "config/lpt9", "aux .log"] {
+            assert_eq!(
+                sanitize_entry_name(name),
+                Err(UnsafePathError::DeviceName(name.to_string())),
+                "{}",
+                name
+            );
+        }
+        assert!(sanitize_entry_name("console.txt").is_ok());
+        assert!(sanitize_entry_name("mods/com10.jar").is_ok());
+        assert!(matches!(
+            sanitize_file_name("NUL.jar"),
+            Err(UnsafePathError::DeviceName(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_empty_names() {
+        for name in ["", ".", "./", "a/.."] {
+            assert!(sanitize_entry_name(name).is_err(), "{}", name);
+        }
+        assert_eq!(sanitize_entry_name("./."), Err(UnsafePathError::Empty));
+    }
+
+    #[test]
+    fn file_names_must_be_a_single_component() {
+        assert_eq!(sanitize_file_name("a.jar"), Ok("a.jar"));
+        for name in ["mods/a.jar", "..\\a.jar"] {
+            assert_eq!(
+                sanitize_file_name(name),
+                Err(UnsafePathError::NotAFileName(name.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_overrides_prefix_with_parent_traversal() {
+        // The overrides folder comes from the manifest and is sanitized like any entry name
+        for overrides in ["../overrides", "overrides/..", "overrides/../../.minecraft"] {
+            assert!(
+                matches!(
+                    sanitize_entry_name(overrides),
+                    Err(UnsafePathError::ParentTraversal(_))
+                ),
+                "{}",
+                overrides
+            );
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn safe_join_rejects_symlinks_out_of_the_root() -> Result<(), Box<dyn Error>> {
+        let directory = std::env::temp_dir().join(format!("safe-path-test-{}", std::process::id()));
+        let root = directory.join("root");
+        let outside = directory.join("outside");
+        std::fs::create_dir_all(root.join("config"))?;
+        std::fs::create_dir_all(&outside)?;
+        std::os::unix::fs::symlink(&outside, root.join("mods"))?;
+        std::os::unix::fs::symlink(root.join("config"), root.join("settings"))?;
+
+        let escaped = safe_join(&root, Path::new("mods").join("a.jar"));
+        let inside = safe_join(&root, Path::new("settings").join("a.toml"));
+        let missing = safe_join(&root, Path::new("resourcepacks").join("a.zip"));
+        std::fs::remove_dir_all(&directory)?;
+
+        assert!(matches!(escaped, Err(UnsafePathError::SymlinkEscape(_))));
+        assert_eq!(inside?, root.join("settings").join("a.toml"));
+        assert_eq!(missing?, root.join("resourcepacks").join("a.zip"));
+        Ok(())
+    }
+}
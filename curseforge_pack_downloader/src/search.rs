@@ -0,0 +1,237 @@
+use crate::mod_type::ModType;
+use crate::project_structure::ProjectData;
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::str::FromStr;
+use uri_encode::encode_uri_component;
+
+/// The CurseForge game ID of Minecraft.
+pub const MINECRAFT_GAME_ID: u32 = 432;
+
+/// The most results the search endpoint returns per page.
+pub const MAX_PAGE_SIZE: u32 = 50;
+
+/// The field search results are sorted by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchSortField {
+    Featured,
+    Popularity,
+    LastUpdated,
+    Name,
+    Author,
+    TotalDownloads,
+}
+
+impl SearchSortField {
+    /// The value the API expects for `sortField`.
+    fn id(&self) -> u32 {
+        match self {
+            SearchSortField::Featured => 1,
+            SearchSortField::Popularity => 2,
+            SearchSortField::LastUpdated => 3,
+            SearchSortField::Name => 4,
+            SearchSortField::Author => 5,
+            SearchSortField::TotalDownloads => 6,
+        }
+    }
+}
+
+impl Display for SearchSortField {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            SearchSortField::Featured => "featured",
+            SearchSortField::Popularity => "popularity",
+            SearchSortField::LastUpdated => "last-updated",
+            SearchSortField::Name => "name",
+            SearchSortField::Author => "author",
+            SearchSortField::TotalDownloads => "downloads",
+        };
+        write!(f, "{}", str)
+    }
+}
+
+impl FromStr for SearchSortField {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "featured" => Ok(SearchSortField::Featured),
+            "popularity" => Ok(SearchSortField::Popularity),
+            "last-updated" | "updated" => Ok(SearchSortField::LastUpdated),
+            "name" => Ok(SearchSortField::Name),
+            "author" => Ok(SearchSortField::Author),
+            "downloads" | "total-downloads" => Ok(SearchSortField::TotalDownloads),
+            _ => Err(format!(
+                "Unknown sort field '{}', expected featured, popularity, last-updated, name, author or downloads",
+                s
+            )),
+        }
+    }
+}
+
+/// The mod loader a project targets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModLoaderType {
+    Forge,
+    Fabric,
+    Quilt,
+    NeoForge,
+}
+
+impl ModLoaderType {
+    /// The value the API expects for `modLoaderType`.
+    fn id(&self) -> u32 {
+        match self {
+            ModLoaderType::Forge => 1,
+            ModLoaderType::Fabric => 4,
+            ModLoaderType::Quilt => 5,
+            ModLoaderType::NeoForge => 6,
+        }
+    }
+}
+
+impl Display for ModLoaderType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            ModLoaderType::Forge => "forge",
+            ModLoaderType::Fabric => "fabric",
+            ModLoaderType::Quilt => "quilt",
+            ModLoaderType::NeoForge => "neoforge",
+        };
+        write!(f, "{}", str)
+    }
+}
+
+impl FromStr for ModLoaderType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "forge" => Ok(ModLoaderType::Forge),
+            "fabric" => Ok(ModLoaderType::Fabric),
+            "quilt" => Ok(ModLoaderType::Quilt),
+            "neoforge" => Ok(ModLoaderType::NeoForge),
+            _ => Err(format!(
+                "Unknown mod loader '{}', expected forge, fabric, quilt or neoforge",
+                s
+            )),
+        }
+    }
+}
+
+/// The filters of a project search, see `curseforge_api::search_projects`.
+///
+/// The defaults match the search of the app: modpacks, sorted in descending order, 50 per page.
+#[derive(Debug, Clone)]
+pub struct SearchQuery {
+    /// Free text matched against the project name and summary.
+    pub search_filter: Option<String>,
+    /// Only return the project with exactly this slug.
+    pub slug: Option<String>,
+    /// The kind of project to search for, `None` for any.
+    pub class_id: Option<ModType>,
+    /// Only return projects for this Minecraft version, e.g. `1.20.1`.
+    pub game_version: Option<String>,
+    /// Only return projects for this mod loader.
+    pub mod_loader: Option<ModLoaderType>,
+    /// The field results are sorted by, `None` for the API default.
+    pub sort_field: Option<SearchSortField>,
+    /// Sort in descending instead of ascending order.
+    pub descending: bool,
+    /// The number of results per page, at most `MAX_PAGE_SIZE`.
+    pub page_size: u32,
+    /// The index of the first result.
+    pub index: u32,
+}
+
+impl Default for SearchQuery {
+    fn default() -> Self {
+        Self {
+            search_filter: None,
+            slug: None,
+            class_id: Some(ModType::ModPack),
+            game_version: None,
+            mod_loader: None,
+            sort_field: None,
+            descending: true,
+            page_size: MAX_PAGE_SIZE,
+            index: 0,
+        }
+    }
+}
+
+impl SearchQuery {
+    /// Creates a modpack search for `search_filter`.
+    ///
+    /// # Parameters
+    ///
+    /// - `search_filter`: The text to search for.
+    pub fn new(search_filter: impl Into<String>) -> Self {
+        Self {
+            search_filter: Some(search_filter.into()),
+            ..Self::default()
+        }
+    }
+
+    /// Builds the URL of the search endpoint for this query.
+    pub fn url(&self) -> String {
+        let mut url = format!(
+            "https://api.curseforge.com/v1/mods/search?gameId={}",
+            MINECRAFT_GAME_ID
+        );
+
+        if let Some(search_filter) = &self.search_filter {
+            url.push_str(&format!(
+                "&searchFilter={}",
+                encode_uri_component(search_filter)
+            ));
+        }
+        if let Some(slug) = &self.slug {
+            url.push_str(&format!("&slug={}", encode_uri_component(slug)));
+        }
+        if let Some(class_id) = &self.class_id {
+            url.push_str(&format!("&classId={}", i64::from(class_id.clone())));
+        }
+        if let Some(game_version) = &self.game_version {
+            url.push_str(&format!(
+                "&gameVersion={}",
+                encode_uri_component(game_version)
+            ));
+        }
+        if let Some(mod_loader) = &self.mod_loader {
+            url.push_str(&format!("&modLoaderType={}", mod_loader.id()));
+        }
+        if let Some(sort_field) = &self.sort_field {
+            url.push_str(&format!("&sortField={}", sort_field.id()));
+        }
+        url.push_str(if self.descending {
+            "&sortOrder=desc"
+        } else {
+            "&sortOrder=asc"
+        });
+        url.push_str(&format!(
+            "&pageSize={}&index={}",
+            self.page_size.clamp(1, MAX_PAGE_SIZE),
+            self.index
+        ));
+
+        url
+    }
+}
+
+/// The pagination info of a search response.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Pagination {
+    pub index: u64,
+    pub page_size: u64,
+    pub result_count: u64,
+    pub total_count: u64,
+}
+
+/// A page of search results.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SearchResponse {
+    pub data: Vec<ProjectData>,
+    pub pagination: Option<Pagination>,
+}
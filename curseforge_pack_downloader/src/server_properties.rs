@@ -0,0 +1,215 @@
+use crate::output_sink::OutputSink;
+use std::error::Error;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// The configuration file of a dedicated Minecraft server, at the root of the install.
+pub const SERVER_PROPERTIES_FILE_NAME: &str = "server.properties";
+
+/// Wraps another sink and merges every write to `SERVER_PROPERTIES_FILE_NAME` instead of
+/// overwriting it, so e.g. the `level-name` of a world is combined with the file of the overrides.
+/// The merged file is written on finish. When the output is merged into an existing server,
+/// the writes are merged into the server's file, so its other settings are kept.
+pub(crate) struct ServerPropertiesSink {
+    inner: Box<dyn OutputSink>,
+    properties: Option<String>,
+}
+
+impl ServerPropertiesSink {
+    pub(crate) fn new(inner: Box<dyn OutputSink>) -> Self {
+        Self {
+            inner,
+            properties: None,
+        }
+    }
+
+    /// Merges the contents of a write into the held back file.
+    fn merge(&mut self, contents: &str) -> u64 {
+        let merged = match &self.properties {
+            Some(properties) => merge_properties(properties, contents),
+            None => contents.to_string(),
+        };
+        self.properties = Some(merged);
+        contents.len() as u64
+    }
+}
+
+impl OutputSink for ServerPropertiesSink {
+    fn write_file(
+        &mut self,
+        relative: &Path,
+        reader: &mut dyn Read,
+    ) -> Result<u64, Box<dyn Error>> {
+        if relative != Path::new(SERVER_PROPERTIES_FILE_NAME) {
+            return self.inner.write_file(relative, reader);
+        }
+        let mut contents = String::new();
+        reader.read_to_string(&mut contents)?;
+        Ok(self.merge(&contents))
+    }
+
+    fn add_file(
+        &mut self,
+        relative: &Path,
+        source: &Path,
+        known_md5: Option<&str>,
+    ) -> Result<u64, Box<dyn Error>> {
+        if relative != Path::new(SERVER_PROPERTIES_FILE_NAME) {
+            return self.inner.add_file(relative, source, known_md5);
+        }
+        let contents = fs::read_to_string(source)?;
+        Ok(self.merge(&contents))
+    }
+
+    fn create_dir(&mut self, relative: &Path) -> Result<(), Box<dyn Error>> {
+        self.inner.create_dir(relative)
+    }
+
+    fn existing_file(&self, relative: &Path) -> Option<PathBuf> {
+        self.inner.existing_file(relative)
+    }
+
+    fn merged_file(&self, relative: &Path) -> Option<PathBuf> {
+        self.inner.merged_file(relative)
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<PathBuf, Box<dyn Error>> {
+        if let Some(mut properties) = self.properties.take() {
+            let path = Path::new(SERVER_PROPERTIES_FILE_NAME);
+            if let Some(existing) = self.inner.merged_file(path) {
+                properties = merge_properties(&fs::read_to_string(existing)?, &properties);
+            }
+            self.inner.write_file(path, &mut properties.as_bytes())?;
+        }
+        self.inner.finish()
+    }
+}
+
+/// Formats a single `key=value` line, escaping the characters Java properties files reserve.
+pub(crate) fn property_line(key: &str, value: &str) -> String {
+    let value = value
+        .replace('\\', "\\\\")
+        .replace(':', "\\:")
+        .replace('=', "\\=");
+    format!("{}={}\n", key, value)
+}
+
+/// Merges two properties files, the values of `update` win.
+///
+/// Comments and the order of `base` are kept, keys that only `update` has are appended.
+fn merge_properties(base: &str, update: &str) -> String {
+    let updates = update
+        .lines()
+        .filter_map(|line| Some((property_key(line)?, line)))
+        .collect::<Vec<_>>();
+
+    let mut merged = String::new();
+    let mut replaced = Vec::new();
+    for line in base.lines() {
+        match property_key(line).and_then(|key| updates.iter().find(|(k, _)| *k == key)) {
+            Some((key, update)) => {
+                merged.push_str(update);
+                replaced.push(*key);
+            }
+            None => merged.push_str(line),
+        }
+        merged.push('\n');
+    }
+    for (key, line) in &updates {
+        if !replaced.contains(key) {
+            merged.push_str(line);
+            merged.push('\n');
+        }
+    }
+    merged
+}
+
+/// The key of a `key=value` line, `None` for comments and blank lines.
+fn property_key(line: &str) -> Option<&str> {
+    let line = line.trim_start();
+    if line.is_empty() || line.starts_with('#') || line.starts_with('!') {
+        return None;
+    }
+    let end = line.find(['=', ':']).unwrap_or(line.len());
+    Some(line[..end].trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output_sink::{DirectorySink, OutputPolicy};
+
+    /// A fresh directory below the system temp directory, unique per test.
+    fn test_directory(name: &str) -> Result<PathBuf, Box<dyn Error>> {
+        let directory =
+            std::env::temp_dir().join(format!("server-properties-{}-{}", name, std::process::id()));
+        if directory.exists() {
+            fs::remove_dir_all(&directory)?;
+        }
+        fs::create_dir_all(&directory)?;
+        Ok(directory)
+    }
+
+    /// Installs a pack that only sets the `level-name` over an existing server.
+    fn install_world(name: &str, policy: OutputPolicy) -> Result<String, Box<dyn Error>> {
+        let directory = test_directory(name)?;
+        let target = directory.join("server");
+        fs::create_dir_all(&target)?;
+        fs::write(
+            target.join(SERVER_PROPERTIES_FILE_NAME),
+            "#Minecraft server properties\nmotd=My server\nlevel-name=world\nmax-players=5\n",
+        )?;
+
+        let result = (|| {
+            let inner = Box::new(DirectorySink::new(&target, policy)?);
+            let mut sink = Box::new(ServerPropertiesSink::new(inner));
+            sink.write_file(
+                Path::new(SERVER_PROPERTIES_FILE_NAME),
+                &mut property_line("level-name", "saves/Pack World").as_bytes(),
+            )?;
+            sink.finish()?;
+            Ok(fs::read_to_string(
+                target.join(SERVER_PROPERTIES_FILE_NAME),
+            )?)
+        })();
+        fs::remove_dir_all(&directory)?;
+        result
+    }
+
+    #[test]
+    fn merges_into_the_properties_of_an_existing_server() -> Result<(), Box<dyn Error>> {
+        assert_eq!(
+            install_world("merge", OutputPolicy::Merge)?,
+            "#Minecraft server properties\nmotd=My server\nlevel-name=saves/Pack World\nmax-players=5\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn replaces_the_properties_of_an_overwritten_server() -> Result<(), Box<dyn Error>> {
+        assert_eq!(
+            install_world("overwrite", OutputPolicy::Overwrite)?,
+            "level-name=saves/Pack World\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn merges_writes_of_the_same_run() {
+        let base = "# Pack settings\ndifficulty=hard\nlevel-name=world\n";
+        let update = "level-name=saves/Pack World\nallow-flight=true\n";
+        assert_eq!(
+            merge_properties(base, update),
+            "# Pack settings\ndifficulty=hard\nlevel-name=saves/Pack World\nallow-flight=true\n"
+        );
+    }
+
+    #[test]
+    fn escapes_property_values() {
+        assert_eq!(
+            property_line("level-name", "C:\\a=b"),
+            "level-name=C\\:\\\\a\\=b\n"
+        );
+    }
+}
@@ -1,5 +1,13 @@
-use clap::Parser;
+use clap::{ArgAction, Args, Parser, Subcommand};
+use curseforge_pack_downloader::download_sources::CdnMirror;
+use curseforge_pack_downloader::mod_type::ModType;
+use curseforge_pack_downloader::modpack_version_file::{PackVersionSelector, ReleaseType};
+use curseforge_pack_downloader::optional_mods::OptionalModsPolicy;
+use curseforge_pack_downloader::output_sink::{OutputKind, OutputPolicy};
+use curseforge_pack_downloader::pack_identifier::PackIdentifier;
+use curseforge_pack_downloader::search::{ModLoaderType, SearchSortField};
 use std::path::PathBuf;
+use std::str::FromStr;
 
 #[derive(Parser, Debug, Clone)]
 #[clap(
@@ -7,17 +15,170 @@ use std::path::PathBuf;
     name = "Curseforge Modpack Downloader",
     version,
     author,
-    long_about = "A command line tool for downloading CurseForge modpacks"
+    long_about = "A command line tool for downloading CurseForge modpacks",
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true,
+    // `--version` selects the pack version, so the tool version is only available as `-V`
+    disable_version_flag = true
 )]
 pub struct CommandlineArgs {
-    /// The Project ID
-    #[arg(short, long, conflicts_with = "file", required_unless_present = "file")]
-    pub id: Option<u64>,
+    /// Print the version of this tool
+    #[arg(short = 'V', action = ArgAction::Version)]
+    pub print_version: Option<bool>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Downloading without a subcommand is kept for compatibility, it is the same as `download`.
+    #[command(flatten)]
+    pub download: DownloadArgs,
+
+    /// The CurseForge API key.
+    ///
+    /// If not set, the key is read from the `CURSEFORGE_API_KEY` environment variable,
+    /// then from `curseforge_api_key=<key>` in the user config file
+    /// (e.g. `~/.config/curseforge_pack_downloader/config.ini`),
+    /// then from the env.ini embedded at build time.
+    #[arg(long, value_name = "KEY", global = true)]
+    pub api_key: Option<String>,
+
+    /// The directory of the download cache shared by every install.
+    ///
+    /// Defaults to `curseforge_pack_downloader` in the user cache directory
+    /// (e.g. `~/.cache/curseforge_pack_downloader`).
+    #[arg(long, value_name = "DIR", global = true)]
+    pub cache_dir: Option<PathBuf>,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Search CurseForge for modpacks
+    Search(SearchArgs),
+
+    /// Show the details of a project
+    Info {
+        /// The pack: a project ID, a slug or a CurseForge URL
+        #[arg(value_name = "ID|SLUG|URL")]
+        id: PackIdentifier,
+    },
+
+    /// List the versions of a modpack, newest first
+    Versions {
+        /// The pack: a project ID, a slug or a CurseForge URL
+        #[arg(value_name = "ID|SLUG|URL")]
+        id: PackIdentifier,
+    },
+
+    /// Download a modpack and install it (the default when no subcommand is given)
+    Download(Box<DownloadArgs>),
+
+    /// Check an install for missing or modified files
+    ///
+    /// Exits with code 1 if the install doesn't match its install record.
+    Verify {
+        /// The directory of the install
+        path: PathBuf,
+
+        /// Also list files that aren't part of the install, like worlds or added mods
+        #[arg(long)]
+        untracked: bool,
+    },
+
+    /// Show how an install differs from its install record, or how two installs differ
+    Diff {
+        /// The directory of the install
+        path: PathBuf,
+
+        /// The directory of a second install to compare against, e.g. of a newer pack version
+        other: Option<PathBuf>,
+    },
+
+    /// Manage the download cache
+    #[command(subcommand)]
+    Cache(CacheCommand),
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum CacheCommand {
+    /// Show where the cache is and how large it is
+    Info,
+
+    /// Remove the least recently used files until the cache fits into a size
+    Gc {
+        /// The size to reduce the cache to, e.g. `10G`, `500M` or `0` to empty it
+        #[arg(long, value_name = "SIZE")]
+        max_size: ByteSize,
+    },
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct SearchArgs {
+    /// The text to search for, lists the most popular packs if omitted
+    pub query: Option<String>,
+
+    /// Only show packs for this Minecraft version, e.g. `1.20.1`
+    #[arg(long, value_name = "VERSION")]
+    pub game_version: Option<String>,
+
+    /// Only show packs for this mod loader: `forge`, `fabric`, `quilt` or `neoforge`
+    #[arg(long)]
+    pub loader: Option<ModLoaderType>,
+
+    /// Sort by `featured`, `popularity`, `last-updated`, `name`, `author` or `downloads`
+    #[arg(long, value_name = "FIELD")]
+    pub sort: Option<SearchSortField>,
+
+    /// Sort in ascending instead of descending order
+    #[arg(long)]
+    pub ascending: bool,
+
+    /// The number of results to show (at most 50)
+    #[arg(short, long, default_value_t = 20, value_name = "NUMBER")]
+    pub limit: u32,
+
+    /// Skip this many results, for paging through results
+    #[arg(long, default_value_t = 0, value_name = "NUMBER")]
+    pub index: u32,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct DownloadArgs {
+    /// The pack to download: a project ID, a slug or a CurseForge URL.
+    ///
+    /// For example `925200`, `all-the-mods-10` or
+    /// `https://www.curseforge.com/minecraft/modpacks/all-the-mods-10`.
+    /// A `/files/<fileId>` link downloads that version instead of `--version`.
+    #[arg(
+        short,
+        long,
+        conflicts_with = "file",
+        required_unless_present = "file",
+        value_name = "ID|SLUG|URL"
+    )]
+    pub id: Option<PackIdentifier>,
 
     /// The input pack archive file
     #[arg(short, long, conflicts_with = "id", required_unless_present = "id")]
     pub file: Option<String>,
 
+    /// The pack version to download: a file ID, a display name, `latest` or `latest-release`.
+    ///
+    /// `latest` is the newest version by release date, limited to `--channel` if set.
+    /// Run the `versions` command to list the available versions.
+    #[arg(
+        long,
+        conflicts_with = "file",
+        default_value = "latest",
+        value_name = "VERSION"
+    )]
+    pub version: PackVersionSelector,
+
+    /// The least stable release channel `latest` may pick: `release`, `beta` or `alpha`.
+    ///
+    /// For example, `beta` picks the newest release or beta version.
+    #[arg(long, conflicts_with = "file", value_name = "CHANNEL")]
+    pub channel: Option<ReleaseType>,
+
     /// Where the downloaded finalized pack will output.
     ///
     /// You can use `%PACK_NAME%` as a variable for the pack name.
@@ -35,6 +196,106 @@ pub struct CommandlineArgs {
     #[arg(short, long, default_value = "./%PACK_NAME%-%PACK_VERSION%-%TIME%")]
     pub output: PathBuf,
 
+    /// How the finalized pack is written: `directory`, `zip` or `tar-zst`.
+    ///
+    /// Archive outputs are deterministic, rebuilding the same pack version yields an identical file.
+    /// The archive extension is appended to the output path if it's missing.
+    #[arg(long, default_value = "directory", value_name = "KIND")]
+    pub output_kind: OutputKind,
+
+    /// What to do if the resolved output path already exists: `fail-if-exists`, `overwrite`,
+    /// `merge` or `backup`.
+    ///
+    /// `merge` keeps files of the existing directory the pack doesn't have, `backup` renames the
+    /// existing output with a timestamp. Defaults to `overwrite` with `--incremental`
+    /// and to `fail-if-exists` otherwise.
+    #[arg(long, value_name = "POLICY")]
+    pub output_policy: Option<OutputPolicy>,
+
+    /// What to do with the mods the pack marks as optional: `include`, `exclude`, `disable`
+    /// or a comma separated list of the project IDs to include.
+    ///
+    /// `disable` installs them with a `.disabled` suffix, so they can be enabled in a launcher.
+    /// Use `--dry-run` to list the optional mods of a pack.
+    #[arg(long, default_value = "include", value_name = "POLICY")]
+    pub optional_mods: OptionalModsPolicy,
+
+    /// A JSON file with rules that exclude mods of the pack, replace them with other files
+    /// or add extra mods, see the README for the format.
+    ///
+    /// Every rule that changes the install is listed in the summary.
+    #[arg(long, value_name = "FILE")]
+    pub rules: Option<PathBuf>,
+
+    /// Install the files of a project class into another directory, e.g. `plugin=plugins` or
+    /// `datapack=global_packs/required_data`. Can be repeated.
+    ///
+    /// Classes are mod, resourcepack, shaderpack, world, datapack, customization, addon, plugin
+    /// or a class ID. The directory is relative to the output, leave it empty for the root.
+    #[arg(long, value_name = "CLASS=DIR")]
+    pub install_path: Vec<InstallPath>,
+
+    /// Keep the existing install at the output and reuse its files whose size and hash match,
+    /// instead of downloading them again. Makes re-running an interrupted install cheap.
+    #[arg(long)]
+    pub incremental: bool,
+
+    /// Pick up mods whose authors disabled third-party distribution from this directory,
+    /// e.g. `~/Downloads`.
+    ///
+    /// Such mods are listed with a link to their CurseForge page. Download them in a browser
+    /// and they are recognized by their fingerprint while the rest of the pack is installed.
+    /// Without it, they are downloaded from the CDN mirrors, which may fail.
+    #[arg(long, value_name = "DIR")]
+    pub watch_dir: Option<PathBuf>,
+
+    /// How many seconds to wait for the mods of `--watch-dir` before they fail,
+    /// waits until cancelled by default.
+    #[arg(long, value_name = "SECONDS", requires = "watch_dir")]
+    pub watch_timeout: Option<u64>,
+
+    /// A CDN mirror to download mods from when the API withholds their download URL
+    /// or the download fails, can be repeated. Mirrors are tried in order.
+    ///
+    /// The template may use %FILE_ID%, %FILE_ID_PREFIX%, %FILE_ID_SUFFIX% and %FILE_NAME%,
+    /// e.g. `https://mirror.example.com/files/%FILE_ID_PREFIX%/%FILE_ID_SUFFIX%/%FILE_NAME%`.
+    /// Replaces the default mirrors, edge.forgecdn.net and mediafilez.forgecdn.net.
+    #[arg(long, value_name = "TEMPLATE")]
+    pub cdn_mirror: Vec<CdnMirror>,
+
+    /// Always download, without reading from or adding to the download cache.
+    #[arg(long)]
+    pub no_cache: bool,
+
+    /// Hardlink files between the download cache and the install instead of reflinking or
+    /// copying them, so an install only takes space once.
+    ///
+    /// A mod edited in place in the install is edited in the cache too, the cache notices
+    /// that by the hash and downloads the mod again next time.
+    #[arg(long, conflicts_with = "no_cache")]
+    pub cache_hardlinks: bool,
+
+    /// Install the pack as a dedicated server.
+    ///
+    /// A world of the pack is set as the `level-name` of `server.properties`.
+    #[arg(long)]
+    pub server: bool,
+
+    /// Print what would be installed instead of installing it.
+    ///
+    /// Lists the mods with their target paths and sizes, the mods that need the fallback
+    /// download URL and the override files. With `--id` only the pack archive is downloaded,
+    /// to a temporary directory, nothing is written to the output.
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// How progress and the result are printed: `text` or `json`.
+    ///
+    /// `json` prints one JSON object per line to stdout: a `progress` object for every event
+    /// and a final `result` (or `error`) object. Logs always go to stderr.
+    #[arg(long, default_value = "text", value_name = "FORMAT")]
+    pub output_format: OutputFormat,
+
     /// This will validate the downloaded mods based on the provided hash. (Note: this can take significantly longer)
     #[arg(long)]
     pub validate: bool,
@@ -50,3 +311,103 @@ pub struct CommandlineArgs {
     #[arg(long, requires = "validate", value_name = "BYTES")]
     pub validate_if_size_less_than: Option<u64>,
 }
+
+/// How the download command prints progress and its result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Human readable log lines.
+    Text,
+    /// Newline delimited JSON on stdout, for scripts.
+    Json,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            _ => Err(format!(
+                "Unknown output format '{}', expected text or json",
+                s
+            )),
+        }
+    }
+}
+
+/// A directory for the files of a project class, `CLASS=DIR`.
+#[derive(Debug, Clone)]
+pub struct InstallPath {
+    pub mod_type: ModType,
+    pub path: String,
+}
+
+impl FromStr for InstallPath {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (mod_type, path) = s
+            .split_once('=')
+            .ok_or_else(|| format!("Invalid install path '{}', expected CLASS=DIR", s))?;
+        Ok(InstallPath {
+            mod_type: mod_type.parse()?,
+            path: path.to_string(),
+        })
+    }
+}
+
+/// A size in bytes, with an optional `K`, `M`, `G` or `T` suffix (powers of 1024).
+#[derive(Debug, Clone, Copy)]
+pub struct ByteSize(pub u64);
+
+impl FromStr for ByteSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid size '{}', expected e.g. 1048576, 500M or 10G", s);
+        let upper = s.trim().to_ascii_uppercase();
+        let number = upper.trim_end_matches('B').trim_end_matches('I').trim_end();
+        let (number, shift) = match number.char_indices().last() {
+            Some((index, 'K')) => (&number[..index], 10),
+            Some((index, 'M')) => (&number[..index], 20),
+            Some((index, 'G')) => (&number[..index], 30),
+            Some((index, 'T')) => (&number[..index], 40),
+            _ => (number, 0),
+        };
+        let value = number.trim().parse::<u64>().map_err(|_| invalid())?;
+        value
+            .checked_mul(1 << shift)
+            .map(ByteSize)
+            .ok_or_else(invalid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bytes(s: &str) -> Result<u64, String> {
+        s.parse::<ByteSize>().map(|size| size.0)
+    }
+
+    #[test]
+    fn parses_byte_sizes() {
+        assert_eq!(bytes("0"), Ok(0));
+        assert_eq!(bytes("1048576"), Ok(1_048_576));
+        assert_eq!(bytes("1K"), Ok(1024));
+        assert_eq!(bytes("500M"), Ok(500 << 20));
+        assert_eq!(bytes("10G"), Ok(10 << 30));
+        assert_eq!(bytes("2T"), Ok(2 << 40));
+        assert_eq!(bytes(" 10 gib "), Ok(10 << 30));
+        assert_eq!(bytes("10GB"), Ok(10 << 30));
+        assert_eq!(bytes("512b"), Ok(512));
+    }
+
+    #[test]
+    fn rejects_invalid_byte_sizes() {
+        for size in ["", "G", "-1", "1.5G", "10X", "ten", "16777216T"] {
+            assert!(bytes(size).is_err(), "{}", size);
+        }
+    }
+}
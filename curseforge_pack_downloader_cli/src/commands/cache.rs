@@ -0,0 +1,24 @@
+use curseforge_pack_downloader::download_cache::DownloadCache;
+use indicatif::HumanBytes;
+use std::error::Error;
+
+/// Prints where the cache is and how large it is.
+pub fn info(cache: &DownloadCache) -> Result<(), Box<dyn Error>> {
+    let stats = cache.stats()?;
+    println!("Cache directory: {}", cache.root().display());
+    println!("{} files, {}", stats.files, HumanBytes(stats.bytes));
+    Ok(())
+}
+
+/// Removes the least recently used files until the cache is at most `max_bytes` large.
+pub fn gc(cache: &DownloadCache, max_bytes: u64) -> Result<(), Box<dyn Error>> {
+    let result = cache.collect_garbage(max_bytes)?;
+    println!(
+        "Removed {} files ({}), {} files ({}) left",
+        result.removed_files,
+        HumanBytes(result.removed_bytes),
+        result.remaining.files,
+        HumanBytes(result.remaining.bytes)
+    );
+    Ok(())
+}
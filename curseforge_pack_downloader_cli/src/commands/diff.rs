@@ -0,0 +1,23 @@
+use curseforge_pack_downloader::install_record::InstallRecord;
+use std::error::Error;
+use std::path::Path;
+
+/// Prints how an install differs from its install record, or how a second install differs from it.
+pub fn run(path: &Path, other: Option<&Path>) -> Result<(), Box<dyn Error>> {
+    let record = InstallRecord::read(path)?;
+
+    let changes = match other {
+        Some(other) => record.diff(&InstallRecord::read(other)?),
+        // Without a second install, compare against the files on disk, including untracked ones
+        None => record.verify(path, true)?,
+    };
+
+    if changes.is_empty() {
+        println!("No differences");
+    }
+    for change in &changes {
+        println!("{}", change);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,368 @@
+use crate::commandline_args::{DownloadArgs, OutputFormat};
+use crate::download_report::JsonLine;
+use crate::progress_display::ProgressDisplay;
+use crate::{api_client, run_cache};
+use curseforge_pack_downloader::cancellation::{is_cancelled, CancellationToken};
+use curseforge_pack_downloader::curseforge_api::ModsFailed;
+use curseforge_pack_downloader::install_plan::InstallPlan;
+use curseforge_pack_downloader::manual_downloads::ManualDownloads;
+use curseforge_pack_downloader::mod_rules::ModRules;
+use curseforge_pack_downloader::mod_type::ModPaths;
+use curseforge_pack_downloader::output_sink::OutputPolicy;
+use curseforge_pack_downloader::pack_identifier::PackIdentifier;
+use curseforge_pack_downloader::progress::ProcessEvent;
+use curseforge_pack_downloader::CurseforgePackDownloader;
+use futures::StreamExt;
+use indicatif::{HumanBytes, MultiProgress};
+use log::{error, info, warn, LevelFilter};
+use std::error::Error;
+use std::ffi::OsStr;
+use std::io::{stderr, IsTerminal};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Downloads a pack by ID or processes a local pack archive.
+///
+/// Progress is shown as progress bars on a terminal, as log lines otherwise,
+/// or as JSON lines with `--output-format json`. In JSON mode every error, including one before
+/// processing starts, ends the output with an error line.
+pub async fn run(
+    args: DownloadArgs,
+    api_key: Option<String>,
+    cache_dir: Option<PathBuf>,
+    progress_bars: MultiProgress,
+) -> Result<(), Box<dyn Error>> {
+    let output_format = args.output_format;
+    let context = if args.dry_run {
+        "Failed to plan pack"
+    } else {
+        "Failed to process pack"
+    };
+    download(args, api_key, cache_dir, progress_bars)
+        .await
+        .map_err(|err| {
+            let cancelled = is_cancelled(err.as_ref());
+            if output_format == OutputFormat::Json {
+                JsonLine::Error {
+                    message: err.to_string(),
+                    cancelled,
+                    failures: err
+                        .downcast_ref::<ModsFailed>()
+                        .map(|failed| failed.failures.clone())
+                        .unwrap_or_default(),
+                }
+                .print();
+            }
+            // Keep the error as is, so the caller can tell a cancellation apart
+            if cancelled {
+                err
+            } else {
+                format!("{}: {}", context, err).into()
+            }
+        })
+}
+
+/// Does the work of `run`, without reporting errors.
+async fn download(
+    args: DownloadArgs,
+    api_key: Option<String>,
+    cache_dir: Option<PathBuf>,
+    progress_bars: MultiProgress,
+) -> Result<(), Box<dyn Error>> {
+    let client = api_client(api_key.as_deref())?;
+    let cache = run_cache(&args, cache_dir)?;
+
+    // Record the start time of the process
+    let start_time = std::time::SystemTime::now();
+
+    // Create an instance of the `CurseforgePackDownloader` struct.
+    let mut downloader = CurseforgePackDownloader::new();
+
+    // Set downloader options based on input arguments
+    downloader.set_client(client);
+    downloader.set_validate(args.validate);
+    downloader.set_parallel_downloads(args.parallel_downloads);
+    downloader.set_output_directory(&args.output);
+    downloader.set_output_kind(args.output_kind);
+    // An incremental run updates the existing install, so replacing it is the expected outcome
+    downloader.set_output_policy(args.output_policy.unwrap_or(if args.incremental {
+        OutputPolicy::Overwrite
+    } else {
+        OutputPolicy::FailIfExists
+    }));
+    downloader.set_pack_version_selector(args.version);
+    downloader.set_release_channel(args.channel);
+    downloader.set_optional_mods(args.optional_mods);
+    if let Some(rules) = &args.rules {
+        downloader.set_mod_rules(ModRules::from_file(rules)?);
+    }
+    let mut paths = ModPaths::default();
+    for install_path in &args.install_path {
+        paths
+            .set(install_path.mod_type.clone(), &install_path.path)
+            .map_err(|err| {
+                format!(
+                    "Invalid install path for {}: {}",
+                    install_path.mod_type, err
+                )
+            })?;
+    }
+    downloader.set_mod_paths(paths);
+    downloader.set_server_mode(args.server);
+    downloader.set_download_cache(cache);
+    downloader.set_incremental(args.incremental);
+    downloader.set_manual_downloads(args.watch_dir.as_ref().map(|watch_dir| {
+        let mut manual_downloads = ManualDownloads::new(watch_dir);
+        manual_downloads.timeout = args.watch_timeout.map(Duration::from_secs);
+        manual_downloads
+    }));
+    if !args.cdn_mirror.is_empty() {
+        downloader.set_cdn_mirrors(args.cdn_mirror.clone());
+    }
+
+    // Cancel the running download when Ctrl+C is pressed,
+    // the downloader removes partial files and leaves any previous install untouched
+    let cancellation_token = CancellationToken::new();
+    downloader.set_cancellation_token(cancellation_token.clone());
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            warn!("Cancelling, cleaning up partial files...");
+            cancellation_token.cancel();
+        }
+    });
+
+    // Set validation size limit if provided and validation is enabled
+    if let Some(validate_if_less_than_bytes) = args.validate_if_size_less_than {
+        downloader.set_validate_if_size_less_than(validate_if_less_than_bytes);
+    }
+
+    // Use a new temp directory for every run, named after the pack
+    let temp_name = match (&args.id, &args.file) {
+        (Some(PackIdentifier::ProjectId(project_id)), _) => project_id.to_string(),
+        (Some(PackIdentifier::Slug { slug, .. }), _) => slug.clone(),
+        (None, Some(file)) => PathBuf::from(file)
+            .file_name()
+            .unwrap_or(OsStr::new("unknown"))
+            .to_str()
+            .unwrap_or("unknown")
+            .to_string(),
+        // Neither an ID nor a file is specified
+        (None, None) => return Err("You must specify a url or file to download".into()),
+    };
+    downloader.set_temp_directory(format!(
+        "{}-{}.temp",
+        temp_name,
+        std::time::SystemTime::now()
+            .duration_since(std::time::SystemTime::UNIX_EPOCH)
+            .unwrap_or_else(|_| std::time::Duration::new(0, 0))
+            .as_millis()
+    ));
+
+    if args.dry_run {
+        let plan = match (args.id, args.file) {
+            (Some(id), _) => downloader.plan_identifier(&id).await,
+            (None, Some(file)) => downloader.plan_file(file).await,
+            (None, None) => return Err("You must specify a url or file to download".into()),
+        }?;
+        match args.output_format {
+            OutputFormat::Text => print_plan(&plan),
+            OutputFormat::Json => JsonLine::Plan(&plan).print(),
+        }
+        return Ok(());
+    }
+
+    // Determine processing path based on input ID or file
+    let handle = match (args.id, args.file) {
+        (Some(id), _) => downloader.process_identifier_stream(id),
+        (None, Some(file)) => downloader.process_file_stream(file),
+        (None, None) => return Err("You must specify a url or file to download".into()),
+    };
+
+    // Show progress bars if they are drawn to a terminal and log every stage as it starts
+    // otherwise, the library already logs the individual mods.
+    // In JSON mode every event is printed to stdout instead
+    let output_format = args.output_format;
+    let mut display = (output_format == OutputFormat::Text && stderr().is_terminal())
+        .then(|| ProgressDisplay::new(progress_bars));
+    let max_level = log::max_level();
+    if display.is_some() {
+        // The bars replace the info lines, warnings and errors are still printed above them
+        log::set_max_level(max_level.min(LevelFilter::Warn));
+    }
+    let mut events = handle.subscribe();
+    let show_events = async {
+        while let Some(progress) = events.next().await {
+            match (output_format, &mut display) {
+                (OutputFormat::Text, Some(display)) => display.update(&progress),
+                (OutputFormat::Text, None) => {
+                    if let ProcessEvent::StageStarted { .. } = progress.event {
+                        info!("{}...", progress.message);
+                    }
+                }
+                (OutputFormat::Json, _) => JsonLine::Progress(&progress).print(),
+            }
+        }
+    };
+    let (result, _) = tokio::join!(handle.result(), show_events);
+    if let Some(display) = display {
+        display.finish();
+        log::set_max_level(max_level);
+    }
+
+    let result = result?;
+    match output_format {
+        OutputFormat::Text => info!(
+            "Installed {} {} ({}, {}) to {}",
+            result.pack.name,
+            result
+                .pack
+                .version
+                .as_deref()
+                .unwrap_or("[Unknown version]"),
+            result
+                .pack
+                .minecraft_version
+                .as_deref()
+                .unwrap_or("unknown Minecraft version"),
+            result.pack.mod_loader.as_deref().unwrap_or("no mod loader"),
+            result.output.display()
+        ),
+        OutputFormat::Json => JsonLine::Result(&result).print(),
+    }
+
+    // Calculate and log the duration of the process
+    let end_time = std::time::SystemTime::now();
+    let duration = end_time.duration_since(start_time).unwrap_or_else(|err| {
+        error!("Unable to get current time: {}", err);
+        std::time::Duration::new(0, 0)
+    });
+    info!("Finished in {} seconds", duration.as_secs());
+
+    Ok(())
+}
+
+/// Prints the plan of a `--dry-run` in a human readable form.
+fn print_plan(plan: &InstallPlan) {
+    println!(
+        "{} {} by {}",
+        plan.name,
+        plan.version.as_deref().unwrap_or("[Unknown version]"),
+        plan.author.as_deref().unwrap_or("[Unknown author]")
+    );
+    println!("Output: {}", plan.output.display());
+
+    println!(
+        "\nMods ({}, {} to download):",
+        plan.mods.len(),
+        HumanBytes(plan.download_size())
+    );
+    for planned in &plan.mods {
+        println!(
+            "  {:<60} {:>10}  {}{}{}",
+            planned.path.display(),
+            planned
+                .size
+                .map(|size| HumanBytes(size).to_string())
+                .unwrap_or("?".to_string()),
+            planned.name,
+            if planned.required { "" } else { " [optional]" },
+            if planned.uses_fallback_url {
+                " [fallback URL]"
+            } else {
+                ""
+            }
+        );
+    }
+
+    let fallback = plan
+        .mods
+        .iter()
+        .filter(|planned| planned.uses_fallback_url)
+        .count();
+    if fallback > 0 {
+        println!(
+            "{} mods don't allow API downloads and would use the fallback download URL",
+            fallback
+        );
+    }
+
+    let manual = plan.manual_downloads().collect::<Vec<_>>();
+    if !manual.is_empty() {
+        println!(
+            "\nMods that can be downloaded by hand with --watch-dir ({}):",
+            manual.len()
+        );
+        for planned in manual {
+            println!(
+                "  {:<40} {}",
+                planned.name,
+                planned.manual_download_url.as_deref().unwrap_or_default()
+            );
+        }
+    }
+
+    if !plan.local.is_empty() {
+        println!(
+            "\nLocal mods ({}, {} to copy):",
+            plan.local.len(),
+            HumanBytes(plan.local_size())
+        );
+        for file in &plan.local {
+            println!(
+                "  {:<60} {:>10}",
+                file.path.display(),
+                HumanBytes(file.size).to_string()
+            );
+        }
+    }
+
+    if !plan.skipped.is_empty() {
+        println!("\nSkipped mods ({}):", plan.skipped.len());
+        for skipped in &plan.skipped {
+            println!(
+                "  {:<60} {:>10}  {} (project {}): {}",
+                skipped.planned.path.display(),
+                skipped
+                    .planned
+                    .size
+                    .map(|size| HumanBytes(size).to_string())
+                    .unwrap_or("?".to_string()),
+                skipped.planned.name,
+                skipped.planned.project_id,
+                skipped.reason
+            );
+        }
+    }
+
+    if !plan.rules.is_empty() {
+        println!("\nApplied rules ({}):", plan.rules.len());
+        for rule in &plan.rules {
+            println!("  {}", rule);
+        }
+    }
+
+    if !plan.unresolved.is_empty() {
+        println!("\nUnresolved mods ({}):", plan.unresolved.len());
+        for unresolved in &plan.unresolved {
+            println!(
+                "  {} (file {}): {}",
+                unresolved.project_id, unresolved.file_id, unresolved.reason
+            );
+        }
+    }
+
+    println!(
+        "\nOverrides ({} files, {}):",
+        plan.overrides.len(),
+        HumanBytes(plan.overrides_size())
+    );
+    for file in &plan.overrides {
+        println!(
+            "  {:<60} {:>10}",
+            file.path.display(),
+            HumanBytes(file.size).to_string()
+        );
+    }
+
+    println!("\nDry run, nothing was downloaded or written");
+}
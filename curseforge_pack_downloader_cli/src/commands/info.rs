@@ -0,0 +1,69 @@
+use curseforge_pack_downloader::curseforge_api::get_project;
+use curseforge_pack_downloader::curseforge_client::CurseforgeClient;
+use curseforge_pack_downloader::pack_identifier::PackIdentifier;
+use std::error::Error;
+
+/// Prints the details of a project.
+pub async fn run(id: &PackIdentifier, client: &CurseforgeClient) -> Result<(), Box<dyn Error>> {
+    // The file of a `/files/<fileId>` link doesn't matter for the project
+    let (id, _) = id.resolve(client).await?;
+    let project = get_project(id, client).await?.data;
+
+    println!("{}", project.name.as_deref().unwrap_or("[Unnamed]"));
+    if let Some(summary) = &project.summary {
+        println!("{}", summary);
+    }
+    println!();
+
+    let authors = project
+        .authors
+        .iter()
+        .flatten()
+        .filter_map(|author| author.name.as_deref())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let categories = project
+        .categories
+        .iter()
+        .flatten()
+        .filter_map(|category| category.name.as_deref())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let website = project
+        .links
+        .as_ref()
+        .and_then(|links| links.website_url.as_deref());
+
+    print_field("ID", project.id);
+    print_field("Slug", project.slug.as_deref());
+    print_field(
+        "Authors",
+        Some(authors).filter(|authors| !authors.is_empty()),
+    );
+    print_field(
+        "Categories",
+        Some(categories).filter(|categories| !categories.is_empty()),
+    );
+    print_field("Downloads", project.download_count);
+    print_field("Main file", project.main_file_id);
+    print_field("Created", project.date_created.as_deref());
+    print_field("Updated", project.date_modified.as_deref());
+    print_field("Released", project.date_released.as_deref());
+    print_field(
+        "Distribution",
+        project.allow_mod_distribution.map(|allowed| match allowed {
+            true => "allowed",
+            false => "not allowed, files have to be downloaded manually",
+        }),
+    );
+    print_field("Website", website);
+
+    Ok(())
+}
+
+/// Prints a labeled value, or nothing if the API didn't return it.
+fn print_field(label: &str, value: Option<impl std::fmt::Display>) {
+    if let Some(value) = value {
+        println!("{:<14}{}", format!("{}:", label), value);
+    }
+}
@@ -0,0 +1,7 @@
+pub mod cache;
+pub mod diff;
+pub mod download;
+pub mod info;
+pub mod search;
+pub mod verify;
+pub mod versions;
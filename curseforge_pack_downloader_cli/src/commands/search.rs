@@ -0,0 +1,49 @@
+use crate::commandline_args::SearchArgs;
+use curseforge_pack_downloader::curseforge_api::search_projects;
+use curseforge_pack_downloader::curseforge_client::CurseforgeClient;
+use curseforge_pack_downloader::search::{SearchQuery, SearchResponse};
+use std::error::Error;
+
+/// Searches for modpacks and prints one line per result.
+pub async fn run(args: SearchArgs, client: &CurseforgeClient) -> Result<(), Box<dyn Error>> {
+    // Use the same query as the app, with the filters from the command line on top
+    let query = SearchQuery {
+        search_filter: args.query,
+        game_version: args.game_version,
+        mod_loader: args.loader,
+        sort_field: args.sort,
+        descending: !args.ascending,
+        page_size: args.limit,
+        index: args.index,
+        ..SearchQuery::default()
+    };
+    let response: SearchResponse = search_projects(&query, client).await?;
+
+    if response.data.is_empty() {
+        println!("No modpacks found");
+        return Ok(());
+    }
+
+    for project in &response.data {
+        println!(
+            "{:>8}  {}  ({} downloads)",
+            project.id.unwrap_or_default(),
+            project.name.as_deref().unwrap_or("[Unnamed]"),
+            project.download_count.unwrap_or_default()
+        );
+        if let Some(summary) = &project.summary {
+            println!("          {}", summary);
+        }
+    }
+
+    if let Some(pagination) = &response.pagination {
+        println!(
+            "\nShowing {}-{} of {} results",
+            pagination.index + 1,
+            pagination.index + pagination.result_count,
+            pagination.total_count
+        );
+    }
+
+    Ok(())
+}
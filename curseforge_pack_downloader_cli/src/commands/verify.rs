@@ -0,0 +1,27 @@
+use curseforge_pack_downloader::install_record::InstallRecord;
+use std::error::Error;
+use std::path::Path;
+
+/// Checks an install against its install record.
+///
+/// Returns an error if any file is missing or modified, so the process exits with a failure code.
+pub fn run(path: &Path, untracked: bool) -> Result<(), Box<dyn Error>> {
+    let record = InstallRecord::read(path)?;
+    let changes = record.verify(path, untracked)?;
+
+    for change in &changes {
+        println!("{}", change);
+    }
+
+    if changes.is_empty() {
+        println!(
+            "{} {}: all {} files are intact",
+            record.name,
+            record.version.as_deref().unwrap_or(""),
+            record.files.len()
+        );
+        Ok(())
+    } else {
+        Err(format!("{} files differ from the install record", changes.len()).into())
+    }
+}
@@ -0,0 +1,48 @@
+use curseforge_pack_downloader::curseforge_api::get_pack_versions;
+use curseforge_pack_downloader::curseforge_client::CurseforgeClient;
+use curseforge_pack_downloader::pack_identifier::PackIdentifier;
+use std::error::Error;
+
+/// Prints the versions of a modpack, newest first.
+pub async fn run(id: &PackIdentifier, client: &CurseforgeClient) -> Result<(), Box<dyn Error>> {
+    // The file of a `/files/<fileId>` link doesn't matter for the project
+    let (id, _) = id.resolve(client).await?;
+    let mut versions = get_pack_versions(id, client).await?;
+    // ISO 8601 dates sort chronologically as strings
+    versions.sort_by(|a, b| b.file_date.cmp(&a.file_date));
+
+    if versions.is_empty() {
+        println!("No versions found");
+        return Ok(());
+    }
+
+    println!(
+        "{:>10}  {:<8}  {:<10}  {:<12}  {:<40}  GAME VERSIONS",
+        "FILE ID", "CHANNEL", "DATE", "SERVER PACK", "NAME"
+    );
+    for version in &versions {
+        let server_pack = match version.server_pack_file_id {
+            Some(server_pack_file_id) => server_pack_file_id.to_string(),
+            None => "-".to_string(),
+        };
+        println!(
+            "{:>10}  {:<8}  {:<10}  {:<12}  {:<40}  {}",
+            version.id.unwrap_or_default(),
+            version
+                .release_channel()
+                .map(|channel| channel.to_string())
+                .unwrap_or("unknown".to_string()),
+            // Only the day of the ISO 8601 timestamp
+            version
+                .file_date
+                .as_deref()
+                .and_then(|date| date.get(..10))
+                .unwrap_or("-"),
+            server_pack,
+            version.display_name.as_deref().unwrap_or("[Unnamed]"),
+            version.game_versions.clone().unwrap_or_default().join(", ")
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,38 @@
+use curseforge_pack_downloader::curseforge_api::ModFailure;
+use curseforge_pack_downloader::install_plan::InstallPlan;
+use curseforge_pack_downloader::process_result::ProcessResult;
+use curseforge_pack_downloader::ProcessProgressResponse;
+use serde::Serialize;
+use std::io::{self, Write};
+
+/// A line of the `--output-format json` output.
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum JsonLine<'a> {
+    /// A progress event, printed as it happens.
+    Progress(&'a ProcessProgressResponse),
+    /// The summary of a successful download, always the last line.
+    Result(&'a ProcessResult),
+    /// The plan of a `--dry-run`, the only line.
+    Plan(&'a InstallPlan),
+    /// The download failed, always the last line.
+    /// Lists every mod that failed, e.g. because its download was rejected as corrupted.
+    #[serde(rename_all = "camelCase")]
+    Error {
+        message: String,
+        cancelled: bool,
+        failures: Vec<ModFailure>,
+    },
+}
+
+impl JsonLine<'_> {
+    /// Writes the line to stdout and flushes it, so scripts see it right away.
+    pub fn print(&self) {
+        let mut stdout = io::stdout().lock();
+        // Nothing useful can be done if stdout is gone, the result is still the exit code
+        if serde_json::to_writer(&mut stdout, self).is_ok() {
+            let _ = writeln!(stdout);
+            let _ = stdout.flush();
+        }
+    }
+}
@@ -3,121 +3,129 @@
 #![deny(clippy::panic)]
 #![deny(unused_must_use)]
 
-use crate::commandline_args::CommandlineArgs;
+use crate::commandline_args::{CacheCommand, Command, CommandlineArgs, DownloadArgs};
 use clap::Parser;
-use curseforge_pack_downloader::CurseforgePackDownloader;
+use curseforge_pack_downloader::cancellation::is_cancelled;
+use curseforge_pack_downloader::curseforge_client::{
+    config_file_path, resolve_api_key, CurseforgeClient, API_KEY_ENV_VAR,
+};
+use curseforge_pack_downloader::download_cache::DownloadCache;
+use indicatif::MultiProgress;
+use indicatif_log_bridge::LogWrapper;
 use log::{error, info, warn};
 use std::env::set_var;
-use std::ffi::OsStr;
-use std::fs::{create_dir_all, remove_dir_all};
+use std::error::Error;
 use std::path::PathBuf;
 use std::process::exit;
 
 mod commandline_args;
+mod commands;
+mod download_report;
 mod env;
+mod progress_display;
 
 #[tokio::main]
 async fn main() {
-    // Record the start time of the process
-    let start_time = std::time::SystemTime::now();
-
     // Parse command line arguments
     let args = CommandlineArgs::parse();
 
-    // Set up the logging environment
+    // Set up the logging environment,
+    // log lines go through the progress bars so they are printed above them
     set_var("RUST_LOG", "info");
-    env_logger::init();
+    let logger = env_logger::Builder::from_default_env().build();
+    let max_level = logger.filter();
+    let progress_bars = MultiProgress::new();
+    if LogWrapper::new(progress_bars.clone(), logger)
+        .try_init()
+        .is_ok()
+    {
+        log::set_max_level(max_level);
+    }
 
     // Log starting information about the tool
     info!("Starting Curseforge Pack Downloader");
     warn!("This tool is not affiliated with CurseForge in any way, in fact we strongly dislike curseforge's bullshit!");
 
-    // Attempt to read environment variables from the embedded env.ini file
-    let env = match env::Env::new() {
-        Ok(env) => env,
-        Err(err) => {
-            // Log an error and exit if env variables cannot be read
-            error!("Unable to read environment variables from the env.ini file, make sure its filled out before building: {}", err);
-            exit(1);
+    // Run the subcommand, downloading is the default for compatibility
+    let result = match args.command {
+        Some(Command::Search(search)) => match api_client(args.api_key.as_deref()) {
+            Ok(client) => commands::search::run(search, &client).await,
+            Err(err) => Err(err),
+        },
+        Some(Command::Info { id }) => match api_client(args.api_key.as_deref()) {
+            Ok(client) => commands::info::run(&id, &client).await,
+            Err(err) => Err(err),
+        },
+        Some(Command::Versions { id }) => match api_client(args.api_key.as_deref()) {
+            Ok(client) => commands::versions::run(&id, &client).await,
+            Err(err) => Err(err),
+        },
+        Some(Command::Download(download)) => {
+            commands::download::run(*download, args.api_key, args.cache_dir, progress_bars).await
+        }
+        Some(Command::Verify { path, untracked }) => commands::verify::run(&path, untracked),
+        Some(Command::Diff { path, other }) => commands::diff::run(&path, other.as_deref()),
+        Some(Command::Cache(CacheCommand::Info)) => {
+            download_cache(args.cache_dir).and_then(|cache| commands::cache::info(&cache))
+        }
+        Some(Command::Cache(CacheCommand::Gc { max_size })) => {
+            download_cache(args.cache_dir).and_then(|cache| commands::cache::gc(&cache, max_size.0))
+        }
+        None => {
+            commands::download::run(args.download, args.api_key, args.cache_dir, progress_bars)
+                .await
         }
     };
 
-    // set the `CURSEFORGE_API_KEY` environment variable
-    // this will be required by the `CurseforgePackDownloader`
-    set_var("CURSEFORGE_API_KEY", &env.curseforge_api_key);
-
-    // Create an instance of the `CurseforgePackDownloader` struct.
-    let mut downloader = CurseforgePackDownloader::new();
-
-    // Set downloader options based on input arguments
-    downloader.set_validate(args.validate);
-    downloader.set_parallel_downloads(args.parallel_downloads);
-    downloader.set_output_directory(&args.output);
-
-    match create_dir_all(&args.output) {
-        Ok(_) => match remove_dir_all(&args.output) {
-            Ok(_) => {
-                info!("Removed old output directory");
-            }
-            Err(err) => {
-                error!("Unable to remove old output directory: {}", err);
-                exit(1);
-            }
-        },
+    match result {
+        Ok(_) => {}
+        Err(err) if is_cancelled(err.as_ref()) => {
+            // Exit with the conventional code for SIGINT if the user cancelled
+            warn!("Cancelled by user");
+            exit(130);
+        }
         Err(err) => {
-            error!("Invalid output directory: {}", err);
+            // Log an error and exit if the command fails
+            error!("{}", err);
             exit(1);
         }
-    };
+    }
+}
 
-    // Set validation size limit if provided and validation is enabled
-    if let Some(validate_if_less_than_bytes) = args.validate_if_size_less_than {
-        downloader.set_validate_if_size_less_than(validate_if_less_than_bytes);
+/// Creates the API client.
+///
+/// The key is resolved from `--api-key`, then the environment, then the config file, then env.ini.
+fn api_client(api_key: Option<&str>) -> Result<CurseforgeClient, Box<dyn Error>> {
+    match resolve_api_key(api_key, Some(env::ENV_FILE)) {
+        Some((api_key, _)) => Ok(CurseforgeClient::new(api_key)),
+        None => Err(format!(
+            "No CurseForge API key found, pass --api-key, set {} or add 'curseforge_api_key=<key>' to {}",
+            API_KEY_ENV_VAR,
+            config_file_path()
+                .map(|path| path.display().to_string())
+                .unwrap_or("the config file".to_string())
+        )
+        .into()),
     }
+}
 
-    // Determine processing path based on input ID or file
-    match if let Some(id) = args.id {
-        downloader.set_temp_directory(format!(
-            "{}-{}.temp",
-            id,
-            std::time::SystemTime::now()
-                .duration_since(std::time::SystemTime::UNIX_EPOCH)
-                .unwrap_or_else(|_| std::time::Duration::new(0, 0))
-                .as_millis()
-        ));
-        downloader.process_id(id, |_| {}).await
-    } else if let Some(file) = args.file {
-        downloader.set_temp_directory(format!(
-            "{}-{}.temp",
-            PathBuf::from(&file)
-                .file_name()
-                .unwrap_or(OsStr::new("unknown"))
-                .to_str()
-                .unwrap_or("unknown"),
-            std::time::SystemTime::now()
-                .duration_since(std::time::SystemTime::UNIX_EPOCH)
-                .unwrap_or_else(|_| std::time::Duration::new(0, 0))
-                .as_millis()
-        ));
-        downloader.process_file(file, |_| {}).await
-    } else {
-        // Log an error if neither an ID nor a file is specified and exit
-        error!("You must specify a url or file to download");
-        exit(1);
-    } {
-        Ok(manifest) => manifest,
-        Err(err) => {
-            // Log an error and exit if processing fails
-            error!("Failed to process pack: {}", err);
-            exit(1);
-        }
-    };
+/// The download cache in `--cache-dir` or the user cache directory, an error if there is neither.
+fn download_cache(cache_dir: Option<PathBuf>) -> Result<DownloadCache, Box<dyn Error>> {
+    cache_dir
+        .map(DownloadCache::new)
+        .or_else(DownloadCache::default_location)
+        .ok_or_else(|| "No cache directory found, pass --cache-dir or --no-cache".into())
+}
 
-    // Calculate and log the duration of the process
-    let end_time = std::time::SystemTime::now();
-    let duration = end_time.duration_since(start_time).unwrap_or_else(|err| {
-        error!("Unable to get current time: {}", err);
-        std::time::Duration::new(0, 0)
-    });
-    info!("Finished in {} seconds", duration.as_secs());
+/// The download cache a download uses, `None` with `--no-cache`.
+fn run_cache(
+    download: &DownloadArgs,
+    cache_dir: Option<PathBuf>,
+) -> Result<Option<DownloadCache>, Box<dyn Error>> {
+    if download.no_cache {
+        return Ok(None);
+    }
+    let mut cache = download_cache(cache_dir)?;
+    cache.set_hardlinks(download.cache_hardlinks);
+    Ok(Some(cache))
 }
@@ -0,0 +1,189 @@
+use curseforge_pack_downloader::mod_rules::AppliedRule;
+use curseforge_pack_downloader::progress::ProcessEvent;
+use curseforge_pack_downloader::ProcessProgressResponse;
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// The resolution of the overall bar, the progress of the library goes from 0 to 1.
+const OVERALL_STEPS: u64 = 1000;
+
+/// Terminal progress bars for a download: one overall bar and one bar per active mod download.
+pub struct ProgressDisplay {
+    multi: MultiProgress,
+    overall: ProgressBar,
+    downloads: HashMap<(u64, u64), ProgressBar>,
+    /// The names of resolved mods, for the download bars and the summary.
+    names: HashMap<(u64, u64), String>,
+    completed: usize,
+    /// The name and error of every mod that failed to download.
+    failures: Vec<(String, String)>,
+    /// The rules that changed the install.
+    rules: Vec<AppliedRule>,
+}
+
+impl ProgressDisplay {
+    /// Creates the overall bar, log lines are printed above the bars.
+    ///
+    /// # Parameters
+    ///
+    /// - `multi`: The progress bars the logger was set up with.
+    pub fn new(multi: MultiProgress) -> Self {
+        let overall = multi.add(ProgressBar::new(OVERALL_STEPS));
+        overall.set_style(style(
+            "{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {percent:>3}% {msg}",
+        ));
+        overall.enable_steady_tick(Duration::from_millis(100));
+
+        Self {
+            multi,
+            overall,
+            downloads: HashMap::new(),
+            names: HashMap::new(),
+            completed: 0,
+            failures: Vec::new(),
+            rules: Vec::new(),
+        }
+    }
+
+    /// Updates the bars with a progress event.
+    pub fn update(&mut self, progress: &ProcessProgressResponse) {
+        self.overall
+            .set_position((progress.progress * OVERALL_STEPS as f32) as u64);
+
+        match &progress.event {
+            ProcessEvent::ModResolved {
+                project_id,
+                file_id,
+                name,
+                ..
+            } => {
+                self.names.insert((*project_id, *file_id), name.clone());
+            }
+            ProcessEvent::ModDownloadStarted {
+                project_id,
+                file_id,
+                file_name,
+            } => {
+                // The size is unknown until the first chunk arrives, so start with a spinner
+                let bar = self.multi.add(ProgressBar::no_length());
+                bar.set_style(style(
+                    "  {spinner} {msg:40!} {bytes:>10}/{total_bytes:<10} {bytes_per_sec:>12} [{bar:30}]",
+                ));
+                bar.set_message(
+                    self.names
+                        .get(&(*project_id, *file_id))
+                        .unwrap_or(file_name)
+                        .clone(),
+                );
+                self.downloads.insert((*project_id, *file_id), bar);
+            }
+            ProcessEvent::ModDownloadProgress {
+                project_id,
+                file_id,
+                downloaded,
+                total,
+            } => {
+                if let Some(bar) = self.downloads.get(&(*project_id, *file_id)) {
+                    if let Some(total) = total {
+                        bar.set_length(*total);
+                    }
+                    bar.set_position(*downloaded);
+                }
+            }
+            ProcessEvent::ModDownloadCompleted {
+                project_id,
+                file_id,
+                ..
+            } => {
+                self.completed += 1;
+                self.remove_download(*project_id, *file_id);
+            }
+            ProcessEvent::ModDownloadFailed {
+                project_id,
+                file_id,
+                error,
+            } => {
+                self.failures
+                    .push((self.mod_name(*project_id, *file_id), error.clone()));
+                self.remove_download(*project_id, *file_id);
+            }
+            ProcessEvent::ModSkipped {
+                project_id,
+                file_id,
+                ..
+            } => {
+                self.remove_download(*project_id, *file_id);
+            }
+            ProcessEvent::ManualDownloadRequired {
+                project_id,
+                file_id,
+                ..
+            } => {
+                if let Some(bar) = self.downloads.get(&(*project_id, *file_id)) {
+                    bar.set_message(format!(
+                        "{} (download manually)",
+                        self.mod_name(*project_id, *file_id)
+                    ));
+                }
+            }
+            ProcessEvent::RuleApplied { rule } => {
+                self.rules.push(rule.clone());
+            }
+            _ => {}
+        }
+
+        // Byte progress of single mods would only flicker in the overall message
+        if !matches!(progress.event, ProcessEvent::ModDownloadProgress { .. }) {
+            self.overall.set_message(progress.message.clone());
+        }
+    }
+
+    /// Removes every bar and prints a summary of the downloaded and failed mods and the applied rules.
+    pub fn finish(self) {
+        for bar in self.downloads.values() {
+            bar.finish_and_clear();
+        }
+        self.overall.finish_and_clear();
+        let _ = self.multi.clear();
+
+        if self.completed > 0 {
+            println!("Downloaded {} mods", self.completed);
+        }
+        if !self.failures.is_empty() {
+            println!("Failed to download {} mods:", self.failures.len());
+            for (name, error) in &self.failures {
+                println!("  {}: {}", name, error);
+            }
+        }
+        if !self.rules.is_empty() {
+            println!("Applied {} rules:", self.rules.len());
+            for rule in &self.rules {
+                println!("  {}", rule);
+            }
+        }
+    }
+
+    /// Removes the bar of a finished mod download.
+    fn remove_download(&mut self, project_id: u64, file_id: u64) {
+        if let Some(bar) = self.downloads.remove(&(project_id, file_id)) {
+            bar.finish_and_clear();
+            self.multi.remove(&bar);
+        }
+    }
+
+    /// The name of a mod, falling back to its IDs if it was never resolved.
+    fn mod_name(&self, project_id: u64, file_id: u64) -> String {
+        self.names
+            .get(&(project_id, file_id))
+            .cloned()
+            .unwrap_or_else(|| format!("{} (file {})", project_id, file_id))
+    }
+}
+
+/// Creates a bar style, the templates are constant so parsing only fails on a typo.
+fn style(template: &str) -> ProgressStyle {
+    ProgressStyle::with_template(template)
+        .unwrap_or_else(|_| ProgressStyle::default_bar())
+        .progress_chars("=> ")
+}